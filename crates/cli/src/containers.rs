@@ -0,0 +1,383 @@
+//! `containers` subcommands.
+
+use crate::{
+    logs::parse_since,
+    output::{self, OutputArgs, Row},
+    spec,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use container_codes_client::{Client, ListQuery, LogOptions};
+use container_codes_shared::types::{
+    ContainerCreateRequest, ContainerExecRequest, ContainerInfo, ContainerStatus, ResourceUsage,
+};
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::{io::Write, path::PathBuf};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List containers
+    Ls {
+        /// Only containers with this status, e.g. running
+        #[arg(long)]
+        status: Option<String>,
+        #[command(flatten)]
+        list: ListArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Create a container from flags or a spec file
+    Create {
+        #[command(flatten)]
+        spec: Box<ContainerSpec>,
+        /// Start the container once it is created
+        #[arg(long)]
+        start: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Start containers
+    Start { ids: Vec<String> },
+    /// Stop containers
+    Stop { ids: Vec<String> },
+    /// Remove containers
+    Rm {
+        ids: Vec<String>,
+        /// Stop running containers first
+        #[arg(long, short)]
+        force: bool,
+    },
+    /// Print a container's logs
+    Logs(LogFlags),
+    /// Run a command in a running container, exiting with its exit code
+    Exec {
+        id: String,
+        /// Environment variable as KEY=VALUE
+        #[arg(long, short, value_parser = spec::key_value)]
+        env: Vec<(String, String)>,
+        /// Working directory inside the container
+        #[arg(long, short)]
+        workdir: Option<String>,
+        /// User to run the command as
+        #[arg(long, short)]
+        user: Option<String>,
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Show resource usage
+    Stats {
+        #[arg(required = true)]
+        ids: Vec<String>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+/// A `ContainerCreateRequest`; flags override fields from `--file`.
+#[derive(Args)]
+pub struct ContainerSpec {
+    /// YAML or JSON ContainerCreateRequest (`-` for stdin)
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Image to run; required without --file
+    image: Option<String>,
+    /// Container name; required without --file
+    #[arg(long)]
+    name: Option<String>,
+    /// Environment variable as KEY=VALUE
+    #[arg(long, short, value_parser = spec::key_value)]
+    env: Vec<(String, String)>,
+    /// Published port as HOST:CONTAINER
+    #[arg(long, short, value_parser = spec::mapping)]
+    publish: Vec<(String, String)>,
+    /// Mounted volume as SOURCE:DESTINATION
+    #[arg(long, short, value_parser = spec::mapping)]
+    volume: Vec<(String, String)>,
+    /// Label as KEY=VALUE
+    #[arg(long, short, value_parser = spec::key_value)]
+    label: Vec<(String, String)>,
+    /// CPU limit in cores, e.g. 0.5
+    #[arg(long)]
+    cpus: Option<String>,
+    /// Memory limit, e.g. 512m
+    #[arg(long)]
+    memory: Option<String>,
+    /// Disk limit, e.g. 10g
+    #[arg(long)]
+    disk: Option<String>,
+    #[arg(long)]
+    network: Option<String>,
+    /// Restart policy, e.g. on-failure
+    #[arg(long)]
+    restart: Option<String>,
+    /// Command to run instead of the image's default
+    #[arg(last = true)]
+    command: Vec<String>,
+}
+
+impl ContainerSpec {
+    fn request(&self) -> anyhow::Result<ContainerCreateRequest> {
+        let mut request = match &self.file {
+            Some(path) => spec::read(path)?,
+            None => ContainerCreateRequest {
+                name: self
+                    .name
+                    .clone()
+                    .context("--name is required without --file")?,
+                image: self
+                    .image
+                    .clone()
+                    .context("an image is required without --file")?,
+                command: None,
+                environment: None,
+                ports: None,
+                volumes: None,
+                resources: None,
+                network_mode: None,
+                restart_policy: None,
+                labels: None,
+            },
+        };
+
+        if let Some(name) = &self.name {
+            request.name.clone_from(name);
+        }
+        if let Some(image) = &self.image {
+            request.image.clone_from(image);
+        }
+        if !self.command.is_empty() {
+            request.command = Some(self.command.clone());
+        }
+        spec::extend(&mut request.environment, &self.env);
+        spec::extend(&mut request.ports, &self.publish);
+        spec::extend(&mut request.volumes, &self.volume);
+        spec::extend(&mut request.labels, &self.label);
+        spec::limits(&mut request.resources, &self.cpus, &self.memory, &self.disk);
+        if self.network.is_some() {
+            request.network_mode.clone_from(&self.network);
+        }
+        if self.restart.is_some() {
+            request.restart_policy.clone_from(&self.restart);
+        }
+        Ok(request)
+    }
+}
+
+/// Filtering and sorting shared by `containers ls` and `jobs ls`.
+#[derive(Args)]
+pub struct ListArgs {
+    /// Filter as FIELD:OP:VALUE, e.g. created_at:gte:2026-01-01T00:00:00Z
+    #[arg(long)]
+    filter: Vec<String>,
+    /// Sort fields, `-` prefixed for descending, e.g. -created_at
+    #[arg(long)]
+    sort: Option<String>,
+    /// At most this many items
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+impl ListArgs {
+    pub fn query(&self, status: Option<&str>) -> ListQuery {
+        let mut query = ListQuery::new();
+        if let Some(status) = status {
+            query = query.filter("status", status);
+        }
+        for filter in &self.filter {
+            query = query.filter("filter", filter);
+        }
+        if let Some(sort) = &self.sort {
+            query = query.sort(sort);
+        }
+        if let Some(limit) = self.limit {
+            query = query.page_size(limit.min(100) as u32);
+        }
+        query
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(usize::MAX)
+    }
+}
+
+/// Log options shared by `containers logs` and `jobs logs`.
+#[derive(Args)]
+pub struct LogFlags {
+    id: String,
+    /// Keep printing new lines as they are written
+    #[arg(long, short)]
+    follow: bool,
+    /// Only the last N lines
+    #[arg(long)]
+    tail: Option<u32>,
+    /// Only lines after this time: a duration ago ("10m") or an RFC 3339 time
+    #[arg(long, value_parser = parse_since)]
+    since: Option<DateTime<Utc>>,
+    /// Prefix lines with their timestamp
+    #[arg(long, short)]
+    timestamps: bool,
+}
+
+impl LogFlags {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn options(&self) -> LogOptions {
+        LogOptions {
+            follow: self.follow,
+            tail: self.tail,
+            since: self.since.map(|since| since.to_rfc3339()),
+            timestamps: self.timestamps,
+        }
+    }
+}
+
+/// Prints lines as they arrive, until the stream ends.
+pub async fn print_lines(
+    lines: impl Stream<Item = container_codes_client::Result<String>>,
+) -> anyhow::Result<()> {
+    let mut lines = std::pin::pin!(lines);
+    let mut stdout = std::io::stdout();
+    while let Some(line) = lines.try_next().await? {
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+pub async fn run(command: &Command, client: &Client) -> anyhow::Result<()> {
+    match command {
+        Command::Ls {
+            status,
+            list,
+            output,
+        } => {
+            let containers: Vec<ContainerInfo> = client
+                .list_containers(list.query(status.as_deref()))
+                .take(list.limit())
+                .try_collect()
+                .await?;
+            output.list(&containers)?;
+        }
+        Command::Create {
+            spec,
+            start,
+            output,
+        } => {
+            let mut container = client.create_container(&spec.request()?).await?;
+            if *start {
+                container = client.start_container(&container.id).await?;
+            }
+            output.one(&container)?;
+        }
+        Command::Start { ids } => {
+            for id in ids {
+                client.start_container(id).await?;
+                println!("{}", id);
+            }
+        }
+        Command::Stop { ids } => {
+            for id in ids {
+                client.stop_container(id).await?;
+                println!("{}", id);
+            }
+        }
+        Command::Rm { ids, force } => {
+            for id in ids {
+                if *force && client.get_container(id).await?.status == ContainerStatus::Running {
+                    client.stop_container(id).await?;
+                }
+                client.remove_container(id).await?;
+                println!("{}", id);
+            }
+        }
+        Command::Logs(flags) => {
+            print_lines(client.container_logs(flags.id(), &flags.options()).await?).await?;
+        }
+        Command::Exec {
+            id,
+            env,
+            workdir,
+            user,
+            command,
+        } => {
+            let request = ContainerExecRequest {
+                command: command.clone(),
+                environment: (!env.is_empty()).then(|| env.iter().cloned().collect()),
+                working_dir: workdir.clone(),
+                user: user.clone(),
+            };
+            let result = client.exec_container(id, &request).await?;
+            print!("{}", result.stdout);
+            eprint!("{}", result.stderr);
+            if result.exit_code != 0 {
+                std::process::exit(result.exit_code);
+            }
+        }
+        Command::Stats { ids, output } => {
+            let mut stats = vec![];
+            for id in ids {
+                let usage = client.container_stats(id).await?;
+                stats.push(Stats {
+                    container_id: id.clone(),
+                    usage,
+                });
+            }
+            output.list(&stats)?;
+        }
+    }
+    Ok(())
+}
+
+impl Row for ContainerInfo {
+    const HEADERS: &'static [&'static str] = &["ID", "NAME", "IMAGE", "STATUS", "CREATED", "PORTS"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            output::short_id(&self.id),
+            self.name.clone(),
+            self.image.clone(),
+            output::name(&self.status),
+            output::time(&self.created_at),
+            output::or_dash((!self.ports.is_empty()).then(|| self.ports.join(","))),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct Stats {
+    container_id: String,
+    #[serde(flatten)]
+    usage: ResourceUsage,
+}
+
+impl Row for Stats {
+    const HEADERS: &'static [&'static str] =
+        &["CONTAINER", "CPU", "MEMORY", "NET RX / TX", "BLOCK R / W"];
+
+    fn cells(&self) -> Vec<String> {
+        let usage = &self.usage;
+        vec![
+            output::short_id(&self.container_id),
+            output::percent(usage.cpu_usage),
+            format!(
+                "{} / {}",
+                output::bytes(usage.memory_usage),
+                output::bytes(usage.memory_limit)
+            ),
+            format!(
+                "{} / {}",
+                output::bytes(usage.network_rx),
+                output::bytes(usage.network_tx)
+            ),
+            format!(
+                "{} / {}",
+                output::bytes(usage.block_read),
+                output::bytes(usage.block_write)
+            ),
+        ]
+    }
+}
@@ -0,0 +1,353 @@
+//! Named contexts: servers the CLI can talk to, kept in
+//! `$XDG_CONFIG_HOME/container-codes/contexts.toml`.
+
+use crate::output::{self, OutputArgs, Row};
+use anyhow::{bail, Context as _};
+use clap::Subcommand;
+use container_codes_client::{Auth, Client, ClientBuilder, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use url::{Host, Url};
+
+pub const DEFAULT_URL: &str = "http://127.0.0.1:8080";
+/// `context use default` goes back to the local server.
+const DEFAULT_CONTEXT: &str = "default";
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List contexts; the current one is marked with *
+    Ls {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Switch the current context, or `default` for the local server
+    Use { name: String },
+    /// Add a context
+    Add {
+        name: String,
+        /// Server URL, e.g. https://cc.example.com
+        #[arg(long)]
+        url: String,
+        /// API key for the management API
+        #[arg(long)]
+        api_key: Option<String>,
+        /// JWT for the management API
+        #[arg(long, conflicts_with = "api_key")]
+        token: Option<String>,
+        /// PEM bundle of the CA that signed the server's certificate
+        #[arg(long)]
+        ca_cert: Option<PathBuf>,
+        /// Make it the current context
+        #[arg(long = "use")]
+        use_it: bool,
+        /// Replace an existing context of the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a context
+    Rm { name: String },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Contexts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    current: Option<String>,
+    #[serde(default)]
+    contexts: BTreeMap<String, Context>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Context {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ca_cert: Option<PathBuf>,
+}
+
+impl Contexts {
+    pub fn path() -> PathBuf {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_else(std::env::temp_dir);
+        config_home.join("container-codes").join("contexts.toml")
+    }
+
+    /// The contexts in `path`; none if the file does not exist.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                toml::from_str(&content).with_context(|| format!("invalid {}", path.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("cannot read {}", path.display())),
+        }
+    }
+
+    /// Writes the file readable by its owner only, since it holds credentials.
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        file.write_all(toml::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Where API calls go and whether local files describe that server.
+pub struct Target {
+    /// `None` for the local default.
+    pub context: Option<String>,
+    pub url: String,
+    auth: Option<Auth>,
+    ca_cert: Option<PathBuf>,
+}
+
+impl Target {
+    /// `--url`, `--api-key` and `--token` (or their env vars) win over the
+    /// context's values; without a context the local server is the target.
+    pub fn resolve(
+        context: Option<&str>,
+        url: Option<&str>,
+        api_key: Option<&str>,
+        token: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let contexts = Contexts::load(&Contexts::path())?;
+        let name = context
+            .or(contexts.current.as_deref())
+            .filter(|name| *name != DEFAULT_CONTEXT);
+        let selected = match name {
+            Some(name) => Some(contexts.contexts.get(name).with_context(|| {
+                format!(
+                    "no context named '{}'; see `container-codes context ls`",
+                    name
+                )
+            })?),
+            None => None,
+        };
+
+        let auth = match (api_key, token) {
+            (Some(key), _) => Some(Auth::ApiKey(key.to_string())),
+            (_, Some(token)) => Some(Auth::Bearer(token.to_string())),
+            _ => selected.and_then(|context| match (&context.api_key, &context.token) {
+                (Some(key), _) => Some(Auth::ApiKey(key.clone())),
+                (_, Some(token)) => Some(Auth::Bearer(token.clone())),
+                _ => None,
+            }),
+        };
+
+        Ok(Self {
+            context: name.map(str::to_string),
+            url: url
+                .map(str::to_string)
+                .or_else(|| selected.map(|context| context.url.clone()))
+                .unwrap_or_else(|| DEFAULT_URL.to_string()),
+            auth,
+            ca_cert: selected.and_then(|context| context.ca_cert.clone()),
+        })
+    }
+
+    /// True when the server runs on this machine, so its PID file, config
+    /// and log files can be read directly.
+    pub fn is_local(&self) -> bool {
+        Url::parse(&self.url).is_ok_and(|url| match url.host() {
+            Some(Host::Domain(domain)) => domain == "localhost",
+            Some(Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip).is_loopback(),
+            None => false,
+        })
+    }
+
+    /// Fails for commands that act on this machine when the target is remote.
+    pub fn require_local(&self, command: &str) -> anyhow::Result<()> {
+        if !self.is_local() {
+            bail!(
+                "`{}` works on the server on this machine, but {} points at {}; use `--context default`",
+                command,
+                self.context.as_deref().map_or("--url".to_string(), |name| format!("context '{}'", name)),
+                self.url
+            );
+        }
+        Ok(())
+    }
+
+    pub fn client(&self) -> anyhow::Result<Client> {
+        Ok(self.builder()?.build()?)
+    }
+
+    /// A client for polling a server that may not be up: no retries, short timeout.
+    pub fn probe_client(&self) -> anyhow::Result<Client> {
+        Ok(self
+            .builder()?
+            .retry(RetryPolicy::none())
+            .timeout(Duration::from_secs(2))
+            .build()?)
+    }
+
+    fn builder(&self) -> anyhow::Result<ClientBuilder> {
+        let mut builder = Client::builder(&self.url);
+        if let Some(auth) = &self.auth {
+            builder = builder.auth(auth.clone());
+        }
+        if let Some(ca_cert) = &self.ca_cert {
+            let pem = std::fs::read(ca_cert)
+                .with_context(|| format!("cannot read {}", ca_cert.display()))?;
+            builder = builder.ca_cert(pem);
+        }
+        Ok(builder)
+    }
+}
+
+pub fn run(command: &Command) -> anyhow::Result<()> {
+    let path = Contexts::path();
+    let mut contexts = Contexts::load(&path)?;
+
+    match command {
+        Command::Ls { output } => {
+            let current = contexts.current.as_deref().unwrap_or(DEFAULT_CONTEXT);
+            let mut rows = vec![Listed {
+                current: current == DEFAULT_CONTEXT,
+                name: DEFAULT_CONTEXT.to_string(),
+                url: DEFAULT_URL.to_string(),
+                auth: None,
+                ca_cert: None,
+            }];
+            rows.extend(contexts.contexts.iter().map(|(name, context)| Listed {
+                current: current == name,
+                name: name.clone(),
+                url: context.url.clone(),
+                auth: match (&context.api_key, &context.token) {
+                    (Some(_), _) => Some("api key"),
+                    (_, Some(_)) => Some("token"),
+                    _ => None,
+                },
+                ca_cert: context.ca_cert.clone(),
+            }));
+            return output.list(&rows);
+        }
+        Command::Use { name } => {
+            if name != DEFAULT_CONTEXT && !contexts.contexts.contains_key(name) {
+                bail!("no context named '{}'", name);
+            }
+            contexts.current = (name != DEFAULT_CONTEXT).then(|| name.clone());
+            println!("Using context {}", name);
+        }
+        Command::Add {
+            name,
+            url,
+            api_key,
+            token,
+            ca_cert,
+            use_it,
+            force,
+        } => {
+            if name == DEFAULT_CONTEXT {
+                bail!("'{}' is the built-in local context", DEFAULT_CONTEXT);
+            }
+            if contexts.contexts.contains_key(name) && !force {
+                bail!(
+                    "context '{}' already exists, pass --force to replace it",
+                    name
+                );
+            }
+            Url::parse(url).with_context(|| format!("invalid URL {}", url))?;
+            // Stored absolute so the context works from any directory.
+            let ca_cert = ca_cert
+                .as_ref()
+                .map(|path| {
+                    path.canonicalize()
+                        .with_context(|| format!("cannot read {}", path.display()))
+                })
+                .transpose()?;
+
+            contexts.contexts.insert(
+                name.clone(),
+                Context {
+                    url: url.clone(),
+                    api_key: api_key.clone(),
+                    token: token.clone(),
+                    ca_cert,
+                },
+            );
+            if *use_it {
+                contexts.current = Some(name.clone());
+            }
+            println!("Added context {}", name);
+        }
+        Command::Rm { name } => {
+            if contexts.contexts.remove(name).is_none() {
+                bail!("no context named '{}'", name);
+            }
+            if contexts.current.as_deref() == Some(name.as_str()) {
+                contexts.current = None;
+            }
+            println!("Removed context {}", name);
+        }
+    }
+
+    contexts.save(&path)
+}
+
+/// A context as `context ls` shows it, without credentials.
+#[derive(Serialize)]
+struct Listed {
+    current: bool,
+    name: String,
+    url: String,
+    auth: Option<&'static str>,
+    ca_cert: Option<PathBuf>,
+}
+
+impl Row for Listed {
+    const HEADERS: &'static [&'static str] = &["", "NAME", "URL", "AUTH", "CA"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            if self.current { "*" } else { "" }.to_string(),
+            self.name.clone(),
+            self.url.clone(),
+            output::or_dash(self.auth.map(str::to_string)),
+            output::or_dash(self.ca_cert.as_ref().map(|path| path.display().to_string())),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback_targets_are_local() {
+        let target = |url: &str| Target {
+            context: None,
+            url: url.to_string(),
+            auth: None,
+            ca_cert: None,
+        };
+
+        assert!(target(DEFAULT_URL).is_local());
+        assert!(target("http://localhost:9000").is_local());
+        assert!(target("http://[::1]:8080").is_local());
+        assert!(!target("https://cc.example.com").is_local());
+        assert!(!target("http://10.0.0.5:8080").is_local());
+    }
+}
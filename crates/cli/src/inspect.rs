@@ -0,0 +1,197 @@
+//! `config show` and `config diff`.
+
+use anyhow::bail;
+use clap::ValueEnum;
+use container_codes_client::Client;
+use container_codes_shared::{
+    config::{redact, redacted, Config, Sources},
+    reload,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+/// One effective value and where it came from.
+#[derive(Serialize)]
+struct Annotated {
+    path: String,
+    value: Value,
+    source: String,
+}
+
+/// Prints the effective configuration: the file with env overrides and
+/// defaults applied, secrets redacted.
+pub fn show(path: &Path, format: Format, with_sources: bool) -> anyhow::Result<()> {
+    let (config, sources) = Config::load_with_sources(path)?;
+
+    if let Format::Toml = format {
+        // Rendered from the struct rather than a `Value` to keep field order.
+        let mut document: DocumentMut = toml::to_string(&config)?.parse()?;
+        annotate(
+            document.as_table_mut(),
+            "",
+            with_sources.then_some(&sources),
+        );
+        print!("{}", document);
+        return Ok(());
+    }
+
+    let mut value = serde_json::to_value(&config)?;
+    redact(&mut value);
+    if !with_sources {
+        return print(format, &value);
+    }
+
+    let mut values = vec![];
+    leaves(String::new(), &value, &mut values);
+    let annotated: Vec<_> = values
+        .into_iter()
+        .map(|(path, value)| Annotated {
+            source: sources.of(&path).to_string(),
+            path,
+            value,
+        })
+        .collect();
+    print(format, &annotated)
+}
+
+/// Prints the configuration a remote server is running with. The server
+/// redacts secrets; where values came from is only known from the file.
+pub async fn show_remote(
+    client: &Client,
+    format: Format,
+    with_sources: bool,
+) -> anyhow::Result<()> {
+    if with_sources {
+        bail!("--sources needs the config file; pass --file or use a local context");
+    }
+    let mut value = client.get_config().await?;
+    if let Format::Toml = format {
+        // TOML has no null; unset options are simply left out.
+        strip_nulls(&mut value);
+    }
+    print(format, &value)
+}
+
+/// Compares two config files value by value, so formatting, key order and
+/// spelled-out defaults do not count as differences. False if they differ.
+pub fn diff(a: &Path, b: &Path) -> anyhow::Result<bool> {
+    let changes = reload::diff(&Config::parse_file(a)?, &Config::parse_file(b)?)?;
+
+    for change in &changes {
+        let old = redacted(&change.path, &change.old);
+        let new = redacted(&change.path, &change.new);
+        match (old.is_null(), new.is_null()) {
+            (true, _) => println!("+ {} = {}", change.path, new),
+            (_, true) => println!("- {} = {}", change.path, old),
+            // Only a secret changed, so both sides redact the same way.
+            _ if old == new => println!("~ {}: changed (redacted)", change.path),
+            _ => println!("~ {}: {} → {}", change.path, old, new),
+        }
+    }
+    if changes.is_empty() {
+        println!("No differences");
+    }
+    Ok(changes.is_empty())
+}
+
+fn print(format: Format, value: &impl Serialize) -> anyhow::Result<()> {
+    match format {
+        Format::Toml => print!("{}", toml::to_string(value)?),
+        Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Format::Yaml => print!("{}", serde_yaml::to_string(value)?),
+    }
+    Ok(())
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Leaf values by dotted path, descending into tables and arrays of tables.
+fn leaves(path: String, value: &Value, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                leaves(join(&path, key), value, out);
+            }
+        }
+        Value::Array(items) if items.iter().any(Value::is_object) => {
+            for (i, item) in items.iter().enumerate() {
+                leaves(format!("{}[{}]", path, i), item, out);
+            }
+        }
+        _ => out.push((path, value.clone())),
+    }
+}
+
+/// Redacts secrets and, given `sources`, adds a `# <source>` comment after
+/// every value.
+fn annotate(table: &mut Table, path: &str, sources: Option<&Sources>) {
+    for (key, item) in table.iter_mut() {
+        let path = join(path, key.get());
+        match item {
+            Item::Value(value) => {
+                redact_toml(&path, value);
+                if let Some(sources) = sources {
+                    value
+                        .decor_mut()
+                        .set_suffix(format!("  # {}", sources.of(&path)));
+                }
+            }
+            Item::Table(table) => annotate(table, &path, sources),
+            Item::ArrayOfTables(tables) => {
+                for (i, table) in tables.iter_mut().enumerate() {
+                    annotate(table, &format!("{}[{}]", path, i), sources);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn redact_toml(path: &str, value: &mut toml_edit::Value) {
+    match value {
+        toml_edit::Value::String(s) => {
+            if let Value::String(redacted) = redacted(path, &Value::String(s.value().clone())) {
+                if redacted != *s.value() {
+                    *value = redacted.into();
+                }
+            }
+        }
+        toml_edit::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                redact_toml(&format!("{}[{}]", path, i), item);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (key, item) in table.iter_mut() {
+                redact_toml(&join(path, key.get()), item);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -0,0 +1,506 @@
+//! `install` and `uninstall`: running the server as a sandboxed systemd
+//! service.
+//!
+//! Both build a [`Plan`] of files and commands first, so `--dry-run` prints
+//! exactly what would be done without needing root.
+
+use crate::process::{self, SYSTEM_PID_FILE};
+use anyhow::{bail, Context};
+use clap::Args;
+use container_codes_shared::config::{schema, Config};
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+const SERVICE: &str = "container-codes";
+const LOG_DIR: &str = "/var/log/container-codes";
+const STATE_DIR: &str = "/var/lib/container-codes";
+/// Added to `server.shutdown_timeout` before systemd resorts to SIGKILL.
+const STOP_MARGIN: Duration = Duration::from_secs(10);
+
+#[derive(Args)]
+pub struct InstallArgs {
+    /// Print the files and commands instead of applying them
+    #[arg(long)]
+    dry_run: bool,
+    /// Also install a socket unit, so systemd holds the listening port
+    #[arg(long)]
+    socket: bool,
+    /// Account the service runs as; created if missing
+    #[arg(long, default_value = SERVICE)]
+    user: String,
+    /// Server binary the unit runs (defaults to container-codes-server next to this binary, then $PATH)
+    #[arg(long)]
+    server_bin: Option<PathBuf>,
+    /// Configuration the service reads; a default one is written if missing
+    #[arg(long, default_value = "/etc/container-codes/server.toml")]
+    config: PathBuf,
+    /// Address the socket unit listens on (defaults to server.host and server.port from the config)
+    #[arg(long, requires = "socket")]
+    listen: Option<String>,
+    /// Directory the unit files go in
+    #[arg(long, default_value = "/etc/systemd/system")]
+    unit_dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct UninstallArgs {
+    /// Print the files and commands instead of applying them
+    #[arg(long)]
+    dry_run: bool,
+    /// Also delete the service account and its log and state directories
+    #[arg(long)]
+    purge: bool,
+    /// Account the service runs as
+    #[arg(long, default_value = SERVICE)]
+    user: String,
+    /// Directory the unit files are in
+    #[arg(long, default_value = "/etc/systemd/system")]
+    unit_dir: PathBuf,
+}
+
+enum Step {
+    Write {
+        path: PathBuf,
+        content: String,
+        mode: u32,
+    },
+    Remove(PathBuf),
+    Run(Vec<String>),
+}
+
+#[derive(Default)]
+struct Plan(Vec<Step>);
+
+impl Plan {
+    fn write(&mut self, path: PathBuf, content: String, mode: u32) {
+        self.0.push(Step::Write {
+            path,
+            content,
+            mode,
+        });
+    }
+
+    fn remove(&mut self, path: impl Into<PathBuf>) {
+        self.0.push(Step::Remove(path.into()));
+    }
+
+    fn run(&mut self, command: &[&str]) {
+        self.0.push(Step::Run(
+            command.iter().map(|arg| arg.to_string()).collect(),
+        ));
+    }
+
+    fn print(&self) {
+        for step in &self.0 {
+            match step {
+                Step::Write {
+                    path,
+                    content,
+                    mode,
+                } => {
+                    println!("# {} (mode {:o})", path.display(), mode);
+                    println!("{}", content);
+                }
+                Step::Remove(path) => println!("$ rm -rf {}", path.display()),
+                Step::Run(command) => println!("$ {}", command.join(" ")),
+            }
+        }
+    }
+
+    fn apply(&self) -> anyhow::Result<()> {
+        for step in &self.0 {
+            match step {
+                Step::Write {
+                    path,
+                    content,
+                    mode,
+                } => {
+                    if let Some(dir) = path.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    std::fs::write(path, content)
+                        .with_context(|| format!("failed to write {}", path.display()))?;
+                    std::fs::set_permissions(path, std::fs::Permissions::from_mode(*mode))?;
+                    println!("  wrote {}", path.display());
+                }
+                Step::Remove(path) => {
+                    let removed = if path.is_dir() {
+                        std::fs::remove_dir_all(path)
+                    } else {
+                        std::fs::remove_file(path)
+                    };
+                    match removed {
+                        Ok(()) => println!("  removed {}", path.display()),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => {
+                            return Err(e)
+                                .with_context(|| format!("failed to remove {}", path.display()))
+                        }
+                    }
+                }
+                Step::Run(command) => {
+                    println!("  $ {}", command.join(" "));
+                    let status = Command::new(&command[0])
+                        .args(&command[1..])
+                        .status()
+                        .with_context(|| format!("failed to run {}", command[0]))?;
+                    if !status.success() {
+                        bail!("`{}` failed with {}", command.join(" "), status);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&self, dry_run: bool) -> anyhow::Result<()> {
+        if dry_run {
+            self.print();
+            return Ok(());
+        }
+        self.apply()
+    }
+}
+
+pub fn install(args: &InstallArgs) -> anyhow::Result<()> {
+    let plan = install_plan(args)?;
+    if !args.dry_run {
+        println!("⚙️ Installing Container Codes as a systemd service...");
+    }
+    plan.execute(args.dry_run)?;
+    if !args.dry_run {
+        println!("✅ Installed; check it with `systemctl status {}`", SERVICE);
+    }
+    Ok(())
+}
+
+pub fn uninstall(args: &UninstallArgs) -> anyhow::Result<()> {
+    let plan = uninstall_plan(args);
+    if !args.dry_run {
+        println!("🗑️ Removing the Container Codes service...");
+    }
+    plan.execute(args.dry_run)?;
+    if !args.dry_run {
+        println!("✅ Uninstalled");
+    }
+    Ok(())
+}
+
+fn install_plan(args: &InstallArgs) -> anyhow::Result<Plan> {
+    let config = if args.config.exists() {
+        Config::load_from_file(&args.config)?
+    } else {
+        Config::default()
+    };
+    let unit = UnitOptions {
+        user: args.user.clone(),
+        server_bin: resolve_server_bin(args.server_bin.as_deref())?,
+        config: std::path::absolute(&args.config)?,
+        socket: args.socket,
+        docker_group: succeeds(&["getent", "group", "docker"]),
+        stop_timeout: config.server.shutdown_timeout.as_duration() + STOP_MARGIN,
+        writable: writable_paths(&config),
+    };
+    let mut plan = Plan::default();
+
+    if !succeeds(&["id", "-u", &args.user]) {
+        plan.run(&[
+            "useradd",
+            "--system",
+            "--user-group",
+            "--home-dir",
+            STATE_DIR,
+            "--no-create-home",
+            "--shell",
+            "/usr/sbin/nologin",
+            &args.user,
+        ]);
+    }
+    if !args.config.exists() {
+        // Readable by the service but not world-readable: it holds secrets.
+        plan.write(unit.config.clone(), schema::default_toml()?, 0o640);
+        plan.run(&[
+            "chown",
+            &format!("root:{}", args.user),
+            &unit.config.to_string_lossy(),
+        ]);
+    }
+    plan.run(&[
+        "install", "-d", "-o", &args.user, "-g", &args.user, "-m", "0750", LOG_DIR,
+    ]);
+
+    plan.write(
+        args.unit_dir.join(format!("{}.service", SERVICE)),
+        service_unit(&unit),
+        0o644,
+    );
+    if args.socket {
+        let listen = args
+            .listen
+            .clone()
+            .unwrap_or_else(|| format!("{}:{}", config.server.host, config.server.port));
+        plan.write(
+            args.unit_dir.join(format!("{}.socket", SERVICE)),
+            socket_unit(&listen),
+            0o644,
+        );
+    }
+
+    plan.run(&["systemctl", "daemon-reload"]);
+    let enabled = if args.socket {
+        format!("{}.socket", SERVICE)
+    } else {
+        format!("{}.service", SERVICE)
+    };
+    plan.run(&["systemctl", "enable", "--now", &enabled]);
+    Ok(plan)
+}
+
+fn uninstall_plan(args: &UninstallArgs) -> Plan {
+    let mut plan = Plan::default();
+    let units: Vec<_> = ["socket", "service"]
+        .iter()
+        .map(|kind| format!("{}.{}", SERVICE, kind))
+        .filter(|unit| args.unit_dir.join(unit).exists())
+        .collect();
+
+    if !units.is_empty() {
+        let mut disable = vec!["systemctl", "disable", "--now"];
+        disable.extend(units.iter().map(String::as_str));
+        plan.run(&disable);
+        for unit in &units {
+            plan.remove(args.unit_dir.join(unit));
+        }
+        plan.run(&["systemctl", "daemon-reload"]);
+    }
+    if args.purge {
+        plan.remove(LOG_DIR);
+        plan.remove(STATE_DIR);
+        if succeeds(&["id", "-u", &args.user]) {
+            plan.run(&["userdel", &args.user]);
+        }
+    }
+    plan
+}
+
+struct UnitOptions {
+    user: String,
+    server_bin: PathBuf,
+    config: PathBuf,
+    socket: bool,
+    /// Whether a `docker` group exists to grant access to the Docker socket.
+    docker_group: bool,
+    stop_timeout: Duration,
+    /// Extra paths the service writes to, see [`writable_paths`].
+    writable: Vec<PathBuf>,
+}
+
+/// Absolute paths from `config` the service writes to outside the state,
+/// log and runtime directories systemd creates for it. Relative paths
+/// resolve against the state directory, the unit's working directory.
+fn writable_paths(config: &Config) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(&config.server.static_files.root)];
+    if config.logging.output == "file" {
+        if let Some(dir) = config
+            .logging
+            .file_path
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+        {
+            paths.push(dir.to_path_buf());
+        }
+    }
+    let pid_dir = Path::new(SYSTEM_PID_FILE).parent();
+    paths.retain(|path| {
+        path.is_absolute()
+            && !path.starts_with(STATE_DIR)
+            && !path.starts_with(LOG_DIR)
+            && !pid_dir.is_some_and(|dir| path.starts_with(dir))
+    });
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn service_unit(unit: &UnitOptions) -> String {
+    let mut after = "network-online.target docker.service".to_string();
+    let mut requires = String::new();
+    if unit.socket {
+        after.push_str(&format!(" {}.socket", SERVICE));
+        requires = format!("Requires={}.socket\n", SERVICE);
+    }
+    let groups = if unit.docker_group {
+        "SupplementaryGroups=docker\n"
+    } else {
+        ""
+    };
+    // `-` tolerates paths that do not exist yet instead of failing the unit.
+    let writable = if unit.writable.is_empty() {
+        String::new()
+    } else {
+        let paths: Vec<String> = unit
+            .writable
+            .iter()
+            .map(|path| format!("-{}", path.display()))
+            .collect();
+        format!("ReadWritePaths={}\n", paths.join(" "))
+    };
+
+    format!(
+        r#"[Unit]
+Description=Container Codes server
+Wants=network-online.target
+After={after}
+{requires}
+[Service]
+Type=simple
+User={user}
+Group={user}
+{groups}Environment=CONTAINER_CODES_CONFIG={config}
+ExecStart={server_bin} --pid-file {pid_file}
+ExecReload=/bin/kill -HUP $MAINPID
+KillSignal=SIGTERM
+TimeoutStopSec={stop_timeout}s
+Restart=on-failure
+RestartSec=5s
+RuntimeDirectory={service}
+StateDirectory={service}
+LogsDirectory={service}
+WorkingDirectory={state_dir}
+UMask=0027
+LimitNOFILE=65536
+
+# Sandboxing: the only privilege kept is binding ports below 1024.
+CapabilityBoundingSet=CAP_NET_BIND_SERVICE
+AmbientCapabilities=CAP_NET_BIND_SERVICE
+NoNewPrivileges=yes
+ProtectSystem=strict
+{writable}ProtectHome=yes
+PrivateTmp=yes
+PrivateDevices=yes
+ProtectKernelTunables=yes
+ProtectKernelModules=yes
+ProtectKernelLogs=yes
+ProtectControlGroups=yes
+ProtectClock=yes
+ProtectHostname=yes
+RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6
+RestrictNamespaces=yes
+RestrictRealtime=yes
+RestrictSUIDSGID=yes
+LockPersonality=yes
+MemoryDenyWriteExecute=yes
+SystemCallArchitectures=native
+SystemCallFilter=@system-service
+
+[Install]
+WantedBy=multi-user.target
+"#,
+        user = unit.user,
+        config = unit.config.display(),
+        server_bin = unit.server_bin.display(),
+        pid_file = SYSTEM_PID_FILE,
+        stop_timeout = unit.stop_timeout.as_secs(),
+        service = SERVICE,
+        state_dir = STATE_DIR,
+    )
+}
+
+fn socket_unit(listen: &str) -> String {
+    format!(
+        r#"[Unit]
+Description=Container Codes server socket
+
+[Socket]
+ListenStream={listen}
+NoDelay=true
+Backlog=1024
+
+[Install]
+WantedBy=sockets.target
+"#
+    )
+}
+
+/// The server binary as an absolute path, which systemd requires.
+fn resolve_server_bin(explicit: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let bin = process::server_binary(explicit);
+    if bin.is_absolute() {
+        return Ok(bin);
+    }
+    if bin.components().count() > 1 {
+        return Ok(std::path::absolute(&bin)?);
+    }
+    std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(&bin))
+        .find(|candidate| candidate.is_file())
+        .with_context(|| format!("{} not found; pass --server-bin", bin.display()))
+}
+
+fn succeeds(command: &[&str]) -> bool {
+    Command::new(command[0])
+        .args(&command[1..])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_unit_is_sandboxed() {
+        let unit = service_unit(&UnitOptions {
+            user: "cc".into(),
+            server_bin: "/usr/local/bin/container-codes-server".into(),
+            config: "/etc/container-codes/server.toml".into(),
+            socket: true,
+            docker_group: false,
+            stop_timeout: Duration::from_secs(40),
+            writable: vec!["/srv/www".into(), "/data/logs".into()],
+        });
+
+        for directive in [
+            "User=cc\n",
+            "ExecStart=/usr/local/bin/container-codes-server --pid-file /run/container-codes/container-codes.pid\n",
+            "RuntimeDirectory=container-codes\n",
+            "CapabilityBoundingSet=CAP_NET_BIND_SERVICE\n",
+            "NoNewPrivileges=yes\n",
+            "ProtectSystem=strict\n",
+            "TimeoutStopSec=40s\n",
+            "Requires=container-codes.socket\n",
+            "WorkingDirectory=/var/lib/container-codes\n",
+            "StateDirectory=container-codes\n",
+            "LogsDirectory=container-codes\n",
+            "ReadWritePaths=-/srv/www -/data/logs\n",
+        ] {
+            assert!(unit.contains(directive), "missing {:?} in:\n{}", directive, unit);
+        }
+        assert!(!unit.contains("SupplementaryGroups"));
+    }
+
+    #[test]
+    fn test_writable_paths_from_config() {
+        // The defaults are relative, so they land in the state directory.
+        assert!(writable_paths(&Config::default()).is_empty());
+
+        let mut config = Config::default();
+        config.server.static_files.root = "/srv/www".into();
+        config.logging.output = "file".into();
+        config.logging.file_path = Some("/data/logs/server.log".into());
+        assert_eq!(
+            writable_paths(&config),
+            [PathBuf::from("/data/logs"), PathBuf::from("/srv/www")]
+        );
+
+        config.server.static_files.root = "/var/lib/container-codes/public".into();
+        config.logging.file_path = Some("/var/log/container-codes/server.log".into());
+        assert!(writable_paths(&config).is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+//! `jobs` subcommands.
+
+use crate::{
+    containers::{print_lines, ListArgs, LogFlags},
+    output::{self, OutputArgs, Row},
+    spec,
+};
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use container_codes_client::{Client, LogOptions};
+use container_codes_shared::types::{
+    FileMapping, JobCreateRequest, JobInfo, JobStatus, RetryPolicy,
+};
+use futures::{StreamExt, TryStreamExt};
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Submit a job from flags or a spec file
+    Submit {
+        #[command(flatten)]
+        spec: Box<JobSpec>,
+        /// Stream the job's logs until it finishes; exits 1 unless it completes
+        #[arg(long, short)]
+        follow: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List jobs
+    Ls {
+        /// Only jobs with this status, e.g. running
+        #[arg(long)]
+        status: Option<String>,
+        #[command(flatten)]
+        list: ListArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Show a job
+    Status {
+        id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print a job's logs
+    Logs(LogFlags),
+    /// Cancel a queued or running job
+    Cancel {
+        id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Run a finished job again
+    Retry {
+        id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List a job's output files, or download them
+    Output {
+        id: String,
+        /// Download the files into this directory
+        #[arg(long, short)]
+        download: Option<PathBuf>,
+    },
+}
+
+/// A `JobCreateRequest`; flags override fields from `--file`.
+#[derive(Args)]
+pub struct JobSpec {
+    /// YAML or JSON JobCreateRequest (`-` for stdin)
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Image to run; required without --file
+    image: Option<String>,
+    /// Job name; required without --file
+    #[arg(long)]
+    name: Option<String>,
+    /// Environment variable as KEY=VALUE
+    #[arg(long, short, value_parser = spec::key_value)]
+    env: Vec<(String, String)>,
+    /// Input file as SOURCE:DESTINATION
+    #[arg(long, short, value_parser = spec::mapping)]
+    input: Vec<(String, String)>,
+    /// Glob of files to keep as output, e.g. 'results/*.csv'
+    #[arg(long)]
+    collect: Vec<String>,
+    /// CPU limit in cores, e.g. 0.5
+    #[arg(long)]
+    cpus: Option<String>,
+    /// Memory limit, e.g. 512m
+    #[arg(long)]
+    memory: Option<String>,
+    /// Disk limit, e.g. 10g
+    #[arg(long)]
+    disk: Option<String>,
+    /// low, normal or high
+    #[arg(long)]
+    priority: Option<String>,
+    /// Attempts before the job fails permanently
+    #[arg(long)]
+    max_attempts: Option<u32>,
+    /// Maximum run time, e.g. 30m
+    #[arg(long)]
+    timeout: Option<String>,
+    /// Command to run instead of the image's default
+    #[arg(last = true)]
+    command: Vec<String>,
+}
+
+impl JobSpec {
+    fn request(&self) -> anyhow::Result<JobCreateRequest> {
+        let mut request = match &self.file {
+            Some(path) => spec::read(path)?,
+            None => JobCreateRequest {
+                name: self
+                    .name
+                    .clone()
+                    .context("--name is required without --file")?,
+                image: self
+                    .image
+                    .clone()
+                    .context("an image is required without --file")?,
+                command: None,
+                environment: None,
+                input_files: None,
+                output_patterns: None,
+                resources: None,
+                priority: None,
+                retry_policy: None,
+                timeout: None,
+            },
+        };
+
+        if let Some(name) = &self.name {
+            request.name.clone_from(name);
+        }
+        if let Some(image) = &self.image {
+            request.image.clone_from(image);
+        }
+        if !self.command.is_empty() {
+            request.command = Some(self.command.clone());
+        }
+        spec::extend(&mut request.environment, &self.env);
+        if !self.input.is_empty() {
+            request
+                .input_files
+                .get_or_insert_with(Vec::new)
+                .extend(self.input.iter().map(|(source, destination)| FileMapping {
+                    source: source.clone(),
+                    destination: destination.clone(),
+                    permissions: None,
+                }));
+        }
+        if !self.collect.is_empty() {
+            request
+                .output_patterns
+                .get_or_insert_with(Vec::new)
+                .extend(self.collect.iter().cloned());
+        }
+        spec::limits(&mut request.resources, &self.cpus, &self.memory, &self.disk);
+        if self.priority.is_some() {
+            request.priority.clone_from(&self.priority);
+        }
+        if let Some(max_attempts) = self.max_attempts {
+            let policy = request.retry_policy.get_or_insert(RetryPolicy {
+                max_attempts,
+                backoff: "exponential".to_string(),
+                delay: None,
+            });
+            policy.max_attempts = max_attempts;
+        }
+        if self.timeout.is_some() {
+            request.timeout.clone_from(&self.timeout);
+        }
+        Ok(request)
+    }
+}
+
+pub async fn run(command: &Command, client: &Client) -> anyhow::Result<()> {
+    match command {
+        Command::Submit {
+            spec,
+            follow,
+            output,
+        } => {
+            let job = client.submit_job(&spec.request()?).await?;
+            if !follow {
+                return output.one(&job);
+            }
+
+            eprintln!("Submitted {}", job.job_id);
+            let options = LogOptions {
+                follow: true,
+                ..LogOptions::default()
+            };
+            print_lines(client.job_logs(&job.job_id, &options).await?).await?;
+
+            let job = client.get_job(&job.job_id).await?;
+            output.one(&job)?;
+            if job.status != JobStatus::Completed {
+                std::process::exit(1);
+            }
+        }
+        Command::Ls {
+            status,
+            list,
+            output,
+        } => {
+            let jobs: Vec<JobInfo> = client
+                .list_jobs(list.query(status.as_deref()))
+                .take(list.limit())
+                .try_collect()
+                .await?;
+            output.list(&jobs)?;
+        }
+        Command::Status { id, output } => output.one(&client.get_job(id).await?)?,
+        Command::Logs(flags) => {
+            print_lines(client.job_logs(flags.id(), &flags.options()).await?).await?;
+        }
+        Command::Cancel { id, output } => output.one(&client.cancel_job(id).await?)?,
+        Command::Retry { id, output } => output.one(&client.retry_job(id).await?)?,
+        Command::Output { id, download } => {
+            let files = client.job_outputs(id).await?;
+            let Some(dir) = download else {
+                for file in files {
+                    println!("{}", file);
+                }
+                return Ok(());
+            };
+
+            std::fs::create_dir_all(dir)?;
+            for file in files {
+                let name = file.rsplit('/').next().unwrap_or(&file);
+                let path = dir.join(name);
+                std::fs::write(&path, client.download_file(&file).await?)
+                    .with_context(|| format!("cannot write {}", path.display()))?;
+                println!("{}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Row for JobInfo {
+    const HEADERS: &'static [&'static str] = &[
+        "ID", "NAME", "STATUS", "PROGRESS", "CREATED", "FINISHED", "ERROR",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.job_id.clone(),
+            self.name.clone(),
+            output::name(&self.status),
+            output::or_dash(self.progress.map(output::percent)),
+            output::time(&self.created_at),
+            output::or_dash(self.finished_at.as_ref().map(output::time)),
+            output::or_dash(self.error_message.clone()),
+        ]
+    }
+}
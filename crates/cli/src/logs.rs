@@ -0,0 +1,494 @@
+//! `logs`: reading, filtering and following the server's log file.
+//!
+//! Lines in the JSON format are parsed and pretty-printed; lines in the
+//! pretty and compact formats are filtered on a best-effort basis and
+//! printed as written.
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use clap::{Args, ColorChoice, ValueEnum};
+use container_codes_shared::{
+    config::{Config, HumanDuration},
+    log_file::rotated_files,
+};
+use serde_json::{Map, Value};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, IsTerminal, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Args)]
+pub struct LogsArgs {
+    /// Keep printing lines as they are written
+    #[arg(long, short)]
+    follow: bool,
+    /// Only lines from the last duration ("10m") or after a time (RFC 3339)
+    #[arg(long, value_parser = parse_since)]
+    since: Option<DateTime<Utc>>,
+    /// Minimum level to show
+    #[arg(long, value_enum)]
+    level: Option<Level>,
+    /// Only lines logged while handling this request
+    #[arg(long)]
+    request_id: Option<String>,
+    /// Only lines about this job
+    #[arg(long)]
+    job: Option<String>,
+    /// Only lines about this container
+    #[arg(long)]
+    container: Option<String>,
+    /// Number of matching entries to show before following; 0 for all
+    #[arg(long, short = 'n', default_value_t = 100)]
+    lines: usize,
+    /// Print lines as written instead of pretty-printing JSON
+    #[arg(long)]
+    raw: bool,
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorChoice,
+    /// Log file (defaults to logging.file_path, else the daemon log next to the PID file)
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Config to read logging.file_path from (defaults to $CONTAINER_CODES_CONFIG or config/server.toml)
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn parse(s: &str) -> Option<Self> {
+        Self::from_str(s, true).ok()
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Level::Trace => "\x1b[35m",
+            Level::Debug => "\x1b[34m",
+            Level::Info => "\x1b[32m",
+            Level::Warn => "\x1b[33m",
+            Level::Error => "\x1b[31m",
+        }
+    }
+}
+
+pub fn parse_since(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(ago) = s.parse::<HumanDuration>() {
+        let ago = chrono::Duration::from_std(ago.as_duration()).map_err(|e| e.to_string())?;
+        return Ok(Utc::now() - ago);
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "expected a duration such as \"10m\" or an RFC 3339 time, got \"{}\"",
+                s
+            )
+        })
+}
+
+/// One log line, parsed as far as its format allows.
+struct Line {
+    text: String,
+    json: Option<Map<String, Value>>,
+    timestamp: Option<DateTime<Utc>>,
+    level: Option<Level>,
+}
+
+impl Line {
+    fn parse(text: &str) -> Self {
+        let text = text.trim_end_matches(['\n', '\r']).to_string();
+        if let Ok(Value::Object(json)) = serde_json::from_str(&text) {
+            let str_of = |key: &str| json.get(key).and_then(Value::as_str);
+            return Self {
+                timestamp: str_of("timestamp")
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                    .map(|t| t.to_utc()),
+                level: str_of("level").and_then(Level::parse),
+                json: Some(json),
+                text,
+            };
+        }
+
+        let plain = strip_ansi(&text);
+        let mut tokens = plain.split_whitespace();
+        let timestamp = tokens
+            .next()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.to_utc());
+        let level = timestamp.and(tokens.next()).and_then(Level::parse);
+        Self {
+            text,
+            json: None,
+            timestamp,
+            level,
+        }
+    }
+
+    /// Whether this line starts an entry; the pretty format continues entries
+    /// over several lines that carry no timestamp.
+    fn starts_entry(&self) -> bool {
+        self.json.is_some() || self.timestamp.is_some()
+    }
+
+    /// A field of the event or of any span it was logged in.
+    fn field(&self, name: &str) -> Option<String> {
+        let json = self.json.as_ref()?;
+        let spans = json
+            .get("spans")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten();
+        std::iter::once(json.get("fields"))
+            .chain(std::iter::once(json.get("span")))
+            .flatten()
+            .chain(spans)
+            .find_map(|scope| scope.get(name))
+            .map(display_value)
+    }
+}
+
+struct Filter {
+    since: Option<DateTime<Utc>>,
+    level: Option<Level>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Filter {
+    fn new(args: &LogsArgs) -> Self {
+        let fields = [
+            ("request_id", &args.request_id),
+            ("job_id", &args.job),
+            ("container_id", &args.container),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.clone().map(|value| (name, value)))
+        .collect();
+        Self {
+            since: args.since,
+            level: args.level,
+            fields,
+        }
+    }
+
+    fn matches(&self, line: &Line) -> bool {
+        if let (Some(since), Some(timestamp)) = (self.since, line.timestamp) {
+            if timestamp < since {
+                return false;
+            }
+        }
+        if let (Some(min), Some(level)) = (self.level, line.level) {
+            if level < min {
+                return false;
+            }
+        }
+        self.fields.iter().all(|(name, value)| match &line.json {
+            Some(_) => line.field(name).as_deref() == Some(value.as_str()),
+            None => line.text.contains(value.as_str()),
+        })
+    }
+}
+
+struct Printer {
+    raw: bool,
+    color: bool,
+}
+
+impl Printer {
+    fn print(&self, line: &Line) {
+        match &line.json {
+            Some(json) if !self.raw => println!("{}", self.pretty(line, json)),
+            _ => println!(
+                "{}",
+                if self.color {
+                    line.text.clone()
+                } else {
+                    strip_ansi(&line.text)
+                }
+            ),
+        }
+    }
+
+    /// `<time> <LEVEL> <target>: <message> key=value ...`, span fields last.
+    fn pretty(&self, line: &Line, json: &Map<String, Value>) -> String {
+        let paint = |code: &str, text: &str| {
+            if self.color {
+                format!("{}{}\x1b[0m", code, text)
+            } else {
+                text.to_string()
+            }
+        };
+        let timestamp = line
+            .timestamp
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+            .unwrap_or_default();
+        let level = match line.level {
+            Some(level) => paint(
+                level.color(),
+                &format!("{:>5}", format!("{:?}", level).to_uppercase()),
+            ),
+            None => "    -".to_string(),
+        };
+        let target = json
+            .get("target")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        let mut out = format!(
+            "{} {} {}",
+            paint("\x1b[2m", &timestamp),
+            level,
+            paint("\x1b[2m", &format!("{}:", target))
+        );
+        let fields = json.get("fields").and_then(Value::as_object);
+        if let Some(message) = fields.and_then(|f| f.get("message")) {
+            out.push(' ');
+            out.push_str(&display_value(message));
+        }
+        let spans = json
+            .get("spans")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten();
+        let extra = fields
+            .into_iter()
+            .flatten()
+            .chain(spans.filter_map(Value::as_object).flatten());
+        for (key, value) in extra {
+            if key != "message" && key != "name" {
+                out.push_str(&format!(
+                    " {}={}",
+                    paint("\x1b[1m", key),
+                    display_value(value)
+                ));
+            }
+        }
+        out
+    }
+}
+
+impl LogsArgs {
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+}
+
+pub async fn logs(args: &LogsArgs, pid_file: &Path) -> anyhow::Result<()> {
+    let path = log_path(args, pid_file)?;
+    let filter = Filter::new(args);
+    let printer = Printer {
+        raw: args.raw,
+        color: match args.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        },
+    };
+
+    // Whole entries, so a pretty-format entry is kept or dropped as a unit.
+    let mut entries: VecDeque<Vec<Line>> = VecDeque::new();
+    let mut matched = false;
+    for file in rotated_files(&path).iter().chain(std::iter::once(&path)) {
+        let Ok(file) = File::open(file) else { continue };
+        for text in BufReader::new(file).lines() {
+            let line = Line::parse(&text?);
+            if line.starts_entry() {
+                matched = filter.matches(&line);
+                if matched {
+                    entries.push_back(vec![]);
+                    if args.lines > 0 && entries.len() > args.lines {
+                        entries.pop_front();
+                    }
+                }
+            }
+            if let (true, Some(entry)) = (matched, entries.back_mut()) {
+                entry.push(line);
+            }
+        }
+    }
+    entries
+        .iter()
+        .flatten()
+        .for_each(|line| printer.print(line));
+
+    if args.follow {
+        follow(&path, &filter, &printer, matched).await?;
+    }
+    Ok(())
+}
+
+/// Prints lines appended to `path`, reopening it when it is rotated.
+async fn follow(
+    path: &Path,
+    filter: &Filter,
+    printer: &Printer,
+    matched: bool,
+) -> anyhow::Result<()> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut inode = file.metadata()?.ino();
+    let mut tail = Tail {
+        offset: file.seek(SeekFrom::End(0))?,
+        pending: String::new(),
+        matched,
+    };
+
+    loop {
+        tail.drain(&file, filter, printer)?;
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+        let Ok(metadata) = std::fs::metadata(path) else {
+            continue;
+        };
+        if metadata.ino() == inode && metadata.len() >= tail.offset {
+            continue;
+        }
+        // Lines written just before the rotation are still in the old file,
+        // and files rotated out since the last poll were never seen.
+        tail.drain(&file, filter, printer)?;
+        let rotated = rotated_files(path);
+        let seen = rotated
+            .iter()
+            .position(|f| std::fs::metadata(f).is_ok_and(|m| m.ino() == inode));
+        if let Some(seen) = seen {
+            for missed in &rotated[seen + 1..] {
+                tail.drain(&File::open(missed)?, filter, printer)?;
+            }
+        }
+        file = File::open(path)?;
+        inode = file.metadata()?.ino();
+        tail.offset = 0;
+    }
+}
+
+struct Tail {
+    offset: u64,
+    /// A line still being written.
+    pending: String,
+    /// Whether the entry being continued matched the filter.
+    matched: bool,
+}
+
+impl Tail {
+    fn drain(&mut self, file: &File, filter: &Filter, printer: &Printer) -> anyhow::Result<()> {
+        let mut reader = BufReader::new(file);
+        loop {
+            let read = reader.read_line(&mut self.pending)?;
+            if read == 0 || !self.pending.ends_with('\n') {
+                self.offset += read as u64;
+                return Ok(());
+            }
+            self.offset += read as u64;
+            let line = Line::parse(&self.pending);
+            self.pending.clear();
+            if line.starts_entry() {
+                self.matched = filter.matches(&line);
+            }
+            if self.matched {
+                printer.print(&line);
+            }
+        }
+    }
+}
+
+/// `--file`, else `logging.file_path` when the server logs to a file, else
+/// the output file of a server started by `container-codes start`.
+fn log_path(args: &LogsArgs, pid_file: &Path) -> anyhow::Result<PathBuf> {
+    if let Some(file) = &args.file {
+        return Ok(file.clone());
+    }
+
+    let config_path = args.config.clone().unwrap_or_else(Config::path_from_env);
+    let logging = if args.config.is_some() || config_path.exists() {
+        let logging = Config::load_from_file(&config_path)?.logging;
+        if let (Some(file), "file") = (&logging.file_path, logging.output.as_str()) {
+            return Ok(PathBuf::from(file));
+        }
+        Some(logging)
+    } else {
+        None
+    };
+
+    let daemon_log = pid_file.with_extension("log");
+    if daemon_log.exists() {
+        return Ok(daemon_log);
+    }
+    match logging {
+        Some(logging) => bail!(
+            "the server logs to {} (logging.output); pass --file, or see `journalctl -u container-codes` for the service",
+            logging.output
+        ),
+        None => bail!("no log file found; pass --file or --config"),
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the escape sequence, e.g. `\x1b[1;32m`.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_json_lines_by_span_field_and_level() {
+        let line = Line::parse(
+            r#"{"timestamp":"2026-10-18T16:51:56.8Z","level":"WARN","fields":{"message":"slow","job_id":"j1"},"target":"t","spans":[{"name":"request","request_id":"r1"}]}"#,
+        );
+        let filter = |level, request_id: &str| Filter {
+            since: None,
+            level: Some(level),
+            fields: vec![
+                ("request_id", request_id.to_string()),
+                ("job_id", "j1".to_string()),
+            ],
+        };
+
+        assert!(filter(Level::Info, "r1").matches(&line));
+        assert!(!filter(Level::Error, "r1").matches(&line));
+        assert!(!filter(Level::Info, "r2").matches(&line));
+
+        let printer = Printer {
+            raw: false,
+            color: false,
+        };
+        assert_eq!(
+            printer.pretty(&line, line.json.as_ref().unwrap()),
+            "2026-10-18T16:51:56.800Z  WARN t: slow job_id=j1 request_id=r1"
+        );
+    }
+}
@@ -1,33 +1,155 @@
-use clap::{Parser, Subcommand};
+mod containers;
+mod context;
+mod inspect;
+mod install;
+mod jobs;
+mod logs;
+mod output;
+mod process;
+mod spec;
+mod top;
+
+use clap::{CommandFactory, Parser, Subcommand};
+use container_codes_client::Client;
+use container_codes_shared::{
+    config::{schema, Config, HumanDuration},
+    database::Database,
+    error::ConfigError,
+    Error,
+};
+use context::Target;
+use install::{InstallArgs, UninstallArgs};
+use logs::LogsArgs;
+use process::ServerArgs;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "container-codes")]
 #[command(about = "Container Codes CLI - Ultimate webserver management")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 struct Cli {
+    /// Server URL [default: the context's URL, or http://127.0.0.1:8080]
+    #[arg(long, global = true, env = "CONTAINER_CODES_URL")]
+    url: Option<String>,
+    /// API key for the management API
+    #[arg(
+        long,
+        global = true,
+        env = "CONTAINER_CODES_API_KEY",
+        hide_env_values = true
+    )]
+    api_key: Option<String>,
+    /// JWT for the management API
+    #[arg(
+        long,
+        global = true,
+        env = "CONTAINER_CODES_TOKEN",
+        hide_env_values = true,
+        conflicts_with = "api_key"
+    )]
+    token: Option<String>,
+    /// Named context to use instead of the current one (see `context ls`)
+    #[arg(long, global = true, env = "CONTAINER_CODES_CONTEXT")]
+    context: Option<String>,
+    /// PID file of the managed server
+    #[arg(long, global = true, env = "CONTAINER_CODES_PID_FILE", default_value_os_t = process::default_pid_file())]
+    pid_file: PathBuf,
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    fn target(&self) -> anyhow::Result<Target> {
+        Target::resolve(
+            self.context.as_deref(),
+            self.url.as_deref(),
+            self.api_key.as_deref(),
+            self.token.as_deref(),
+        )
+    }
+
+    /// The target, which must be the server on this machine.
+    fn local_target(&self, command: &str) -> anyhow::Result<Target> {
+        let target = self.target()?;
+        target.require_local(command)?;
+        Ok(target)
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
-    /// Start the server
-    Start,
-    /// Stop the server  
-    Stop,
-    /// Restart the server
-    Restart,
+    /// Start the server, as a daemon unless --foreground is given
+    Start {
+        /// Run in this terminal instead of daemonizing
+        #[arg(long)]
+        foreground: bool,
+        #[command(flatten)]
+        server: ServerArgs,
+    },
+    /// Stop the server once in-flight requests finish
+    Stop {
+        /// How long to wait for the server to exit
+        #[arg(long, default_value = "60s")]
+        timeout: HumanDuration,
+        /// Kill the server if it is still running after --timeout
+        #[arg(long)]
+        force: bool,
+    },
+    /// Restart the server without closing its listening port
+    Restart {
+        #[command(flatten)]
+        server: ServerArgs,
+    },
     /// Show server status
-    Status,
-    /// Install as system service
-    Install,
+    Status {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Install as a systemd service
+    Install(InstallArgs),
+    /// Remove the systemd service
+    Uninstall(UninstallArgs),
     /// Configuration commands
     Config {
         #[command(subcommand)]
         action: ConfigAction,
     },
-    /// View logs
-    Logs,
+    /// Database schema migrations
+    Db {
+        /// Config file with the database settings (defaults to $CONTAINER_CODES_CONFIG or config/server.toml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// View and follow server logs
+    Logs(LogsArgs),
+    /// Submit and manage jobs
+    Jobs {
+        #[command(subcommand)]
+        command: jobs::Command,
+    },
+    /// Create and manage containers
+    Containers {
+        #[command(subcommand)]
+        command: containers::Command,
+    },
+    /// Live dashboard of the server, proxy, containers and jobs
+    Top,
+    /// Manage named server contexts
+    Context {
+        #[command(subcommand)]
+        command: context::Command,
+    },
+    /// Print a shell completion script
+    Completions { shell: clap_complete::Shell },
+    /// Print the man page, or write one per command into a directory
+    Man {
+        /// Directory for container-codes.1 and a page per subcommand
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
     /// Certificate management
     Certs,
 }
@@ -35,58 +157,147 @@ enum Commands {
 #[derive(Subcommand)]
 enum ConfigAction {
     /// Validate configuration
-    Validate,
+    Validate {
+        /// Config file to validate (defaults to $CONTAINER_CODES_CONFIG or config/server.toml)
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
     /// Reload configuration
     Reload,
-    /// Show effective configuration
-    Show,
+    /// Show the effective configuration, with secrets redacted
+    Show {
+        /// Config file to load (defaults to $CONTAINER_CODES_CONFIG or config/server.toml)
+        #[arg(long)]
+        file: Option<PathBuf>,
+        #[arg(long, value_enum, default_value = "toml")]
+        format: inspect::Format,
+        /// Annotate each value with where it came from: default, file and line, or env var
+        #[arg(long)]
+        sources: bool,
+    },
+    /// Compare two config files value by value
+    Diff { a: PathBuf, b: PathBuf },
+    /// Print the JSON Schema for server.toml
+    Schema,
+    /// Write a fully commented default configuration
+    Init {
+        /// Where to write the config (prints to stdout if omitted)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Apply pending migrations
+    Migrate,
+    /// List migrations and whether each is applied
+    Status,
+    /// Revert the most recently applied migrations
+    Rollback {
+        /// Number of migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Start => {
+    match &cli.command {
+        Commands::Start { foreground, server } => {
+            let target = cli.local_target("start")?;
             println!("🚀 Starting Container Codes server...");
-            // TODO: Implement server start
+            process::start(&cli.pid_file, server, *foreground, &target.probe_client()?).await?;
         }
-        Commands::Stop => {
+        Commands::Stop { timeout, force } => {
+            cli.local_target("stop")?;
             println!("🛑 Stopping Container Codes server...");
-            // TODO: Implement server stop
+            process::stop(&cli.pid_file, timeout.as_duration(), *force).await?;
         }
-        Commands::Restart => {
+        Commands::Restart { server } => {
+            let target = cli.local_target("restart")?;
             println!("🔄 Restarting Container Codes server...");
-            // TODO: Implement server restart
+            process::restart(&cli.pid_file, server, &target.probe_client()?).await?;
         }
-        Commands::Status => {
-            println!("📊 Container Codes server status:");
-            // TODO: Implement status check
+        Commands::Status { json } => {
+            let target = cli.target()?;
+            let pid_file = target.is_local().then_some(cli.pid_file.as_path());
+            if !process::status(pid_file, &target.probe_client()?, *json).await? {
+                std::process::exit(1);
+            }
         }
-        Commands::Install => {
-            println!("⚙️ Installing Container Codes as system service...");
-            // TODO: Implement service installation
+        Commands::Install(args) => {
+            cli.local_target("install")?;
+            install::install(args)?;
         }
-        Commands::Config { action } => {
-            match action {
-                ConfigAction::Validate => {
-                    println!("✅ Validating configuration...");
-                    // TODO: Implement config validation
-                }
-                ConfigAction::Reload => {
-                    println!("🔄 Reloading configuration...");
-                    // TODO: Implement config reload
+        Commands::Uninstall(args) => {
+            cli.local_target("uninstall")?;
+            install::uninstall(args)?;
+        }
+        Commands::Config { action } => match action {
+            ConfigAction::Validate { file } => {
+                let path = file.clone().unwrap_or_else(Config::path_from_env);
+                println!("✅ Validating configuration...");
+                validate_config(&path);
+            }
+            ConfigAction::Reload => {
+                println!("🔄 Reloading configuration...");
+                reload_config(&cli.target()?.client()?).await?;
+            }
+            ConfigAction::Show {
+                file,
+                format,
+                sources,
+            } => {
+                let target = cli.target()?;
+                if file.is_none() && !target.is_local() {
+                    inspect::show_remote(&target.client()?, *format, *sources).await?;
+                } else {
+                    let path = file.clone().unwrap_or_else(Config::path_from_env);
+                    inspect::show(&path, *format, *sources)?;
                 }
-                ConfigAction::Show => {
-                    println!("📋 Effective configuration:");
-                    // TODO: Implement config display
+            }
+            ConfigAction::Diff { a, b } => {
+                if !inspect::diff(a, b)? {
+                    std::process::exit(1);
                 }
             }
+            ConfigAction::Schema => {
+                println!("{}", serde_json::to_string_pretty(&schema::json_schema())?);
+            }
+            ConfigAction::Init { output, force } => init_config(output.as_deref(), *force)?,
+        },
+        Commands::Db { config, action } => {
+            cli.local_target("db")?;
+            let path = config.clone().unwrap_or_else(Config::path_from_env);
+            run_db(&path, action).await?;
+        }
+        Commands::Logs(args) => {
+            if args.file().is_none() {
+                cli.local_target("logs")?;
+            }
+            logs::logs(args, &cli.pid_file).await?;
         }
-        Commands::Logs => {
-            println!("📜 Viewing Container Codes logs...");
-            // TODO: Implement log viewing
+        Commands::Jobs { command } => jobs::run(command, &cli.target()?.client()?).await?,
+        Commands::Containers { command } => {
+            containers::run(command, &cli.target()?.client()?).await?
         }
+        Commands::Top => top::top(&cli.target()?.probe_client()?).await?,
+        Commands::Context { command } => context::run(command)?,
+        Commands::Completions { shell } => {
+            clap_complete::generate(
+                *shell,
+                &mut Cli::command(),
+                "container-codes",
+                &mut std::io::stdout(),
+            );
+        }
+        Commands::Man { output } => man(output.as_deref())?,
         Commands::Certs => {
             println!("🔐 Certificate management:");
             // TODO: Implement certificate management
@@ -94,4 +305,139 @@ async fn main() -> anyhow::Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn man(output: Option<&Path>) -> anyhow::Result<()> {
+    let command = Cli::command();
+    let Some(dir) = output else {
+        clap_mangen::Man::new(command).render(&mut std::io::stdout())?;
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(dir)?;
+    clap_mangen::generate_to(command, dir)?;
+    println!("✅ Wrote man pages to {}", dir.display());
+    Ok(())
+}
+
+async fn reload_config(client: &Client) -> anyhow::Result<()> {
+    let report = client.reload_config().await?;
+
+    for change in &report.applied {
+        println!("  ✅ applied {}", change.path);
+    }
+    for change in &report.restart_required {
+        println!("  ⏳ restart required for {}", change.path);
+    }
+    if report.applied.is_empty() && report.restart_required.is_empty() {
+        println!("  No changes");
+    }
+    Ok(())
+}
+
+async fn run_db(path: &Path, action: &DbAction) -> anyhow::Result<()> {
+    let config = Config::load_from_file(path)?;
+    if config.database.url.is_empty() {
+        anyhow::bail!("database.url is not set in {}", path.display());
+    }
+    let database = Database::connect(&config.database).await?;
+
+    match action {
+        DbAction::Migrate => {
+            println!("🗄️ Applying migrations...");
+            database.migrate().await?;
+            println!("✅ Database is up to date");
+        }
+        DbAction::Status => {
+            for migration in database.migration_status().await? {
+                let state = match (migration.applied_at, migration.modified) {
+                    (Some(_), true) => "modified since applied".to_string(),
+                    (Some(at), false) => format!("applied {}", at.format("%Y-%m-%d %H:%M:%S")),
+                    (None, _) => "pending".to_string(),
+                };
+                println!(
+                    "  {:>4}  {:<28} {}",
+                    migration.version, migration.description, state
+                );
+            }
+        }
+        DbAction::Rollback { steps } => {
+            let reverted = database.rollback(*steps).await?;
+            if reverted.is_empty() {
+                println!("  No applied migrations");
+            }
+            for version in reverted {
+                println!("  ↩️ reverted {}", version);
+            }
+        }
+    }
+
+    database.close().await;
+    Ok(())
+}
+
+fn init_config(output: Option<&Path>, force: bool) -> anyhow::Result<()> {
+    let content = schema::default_toml()?;
+
+    let Some(path) = output else {
+        print!("{}", content);
+        return Ok(());
+    };
+
+    let schema_path = path.with_file_name(schema::SCHEMA_FILE);
+    for existing in [path, schema_path.as_path()] {
+        if existing.exists() && !force {
+            anyhow::bail!(
+                "{} already exists, pass --force to overwrite it",
+                existing.display()
+            );
+        }
+    }
+    std::fs::write(path, content)?;
+
+    std::fs::write(
+        &schema_path,
+        serde_json::to_string_pretty(&schema::json_schema())? + "\n",
+    )?;
+
+    println!("✅ Wrote default configuration to {}", path.display());
+    println!("   Editor schema: {}", schema_path.display());
+    Ok(())
+}
+
+fn validate_config(path: &Path) {
+    match Config::load_from_file(path) {
+        Ok(_) => println!("✅ {} is valid", path.display()),
+        Err(Error::Config(ConfigError::Validation(errors))) => {
+            eprintln!("❌ {} has {} error(s):", path.display(), errors.len());
+            for issue in errors.iter() {
+                match issue.location {
+                    Some(loc) => eprintln!(
+                        "  {}:{}:{}: {}: {}",
+                        path.display(),
+                        loc.line,
+                        loc.column,
+                        issue.path,
+                        issue.message
+                    ),
+                    None => eprintln!("  {}: {}", issue.path, issue.message),
+                }
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+}
@@ -0,0 +1,201 @@
+//! Table, JSON and template output for resource commands.
+
+use anyhow::{bail, Context};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+#[derive(Args)]
+pub struct OutputArgs {
+    #[arg(long, short, value_enum, default_value = "table")]
+    output: Format,
+    /// Print each item with a template such as '{{job_id}} {{status}}'; nested
+    /// fields are dotted, e.g. '{{resource_usage.cpu_usage}}'
+    #[arg(long, conflicts_with = "output")]
+    template: Option<String>,
+}
+
+/// A resource that can be shown as a table row.
+pub trait Row: Serialize {
+    const HEADERS: &'static [&'static str];
+
+    fn cells(&self) -> Vec<String>;
+}
+
+impl OutputArgs {
+    pub fn list<T: Row>(&self, items: &[T]) -> anyhow::Result<()> {
+        match (&self.template, self.output) {
+            (Some(template), _) => {
+                for item in items {
+                    println!("{}", render(template, &serde_json::to_value(item)?)?);
+                }
+            }
+            (None, Format::Json) => println!("{}", serde_json::to_string_pretty(items)?),
+            (None, Format::Table) => {
+                let rows: Vec<_> = items.iter().map(Row::cells).collect();
+                print!("{}", table(T::HEADERS, &rows));
+            }
+        }
+        Ok(())
+    }
+
+    /// A single item; tables are printed one field per line.
+    pub fn one<T: Row>(&self, item: &T) -> anyhow::Result<()> {
+        match (&self.template, self.output) {
+            (Some(template), _) => println!("{}", render(template, &serde_json::to_value(item)?)?),
+            (None, Format::Json) => println!("{}", serde_json::to_string_pretty(item)?),
+            (None, Format::Table) => {
+                let rows: Vec<_> = T::HEADERS
+                    .iter()
+                    .zip(item.cells())
+                    .map(|(header, cell)| vec![format!("{}:", header), cell])
+                    .collect();
+                print!("{}", table(&[], &rows));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Left-aligned columns two spaces apart; no header line if `headers` is empty.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let header: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let lines: Vec<&Vec<String>> = std::iter::once(&header)
+        .filter(|h| !h.is_empty())
+        .chain(rows)
+        .collect();
+
+    let columns = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            lines
+                .iter()
+                .filter_map(|line| line.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for line in lines {
+        let cells: Vec<String> = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Replaces every `{{path}}` in `template` with that field of `value`.
+/// Strings are inserted as-is, nulls as nothing, anything else as JSON.
+pub fn render(template: &str, value: &Value) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .with_context(|| format!("unclosed '{{{{' in template: {}", template))?;
+        let path = rest[start + 2..start + end].trim();
+
+        let field = path
+            .split('.')
+            .filter(|key| !key.is_empty())
+            .try_fold(value, |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => value.get(key),
+            });
+        match field {
+            Some(Value::String(s)) => out.push_str(s),
+            Some(Value::Null) => {}
+            Some(other) => out.push_str(&other.to_string()),
+            None => bail!("template field '{}' does not exist", path),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A 0–100 percentage such as `cpu_usage`, to one decimal.
+pub fn percent(value: f64) -> String {
+    format!("{:.1}%", value)
+}
+
+/// Bytes with a binary suffix and one decimal, e.g. `12.5MiB`.
+pub fn bytes(n: u64) -> String {
+    let mut size = n as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return if unit == "B" {
+                format!("{}B", n)
+            } else {
+                format!("{:.1}{}", size, unit)
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1}TiB", size)
+}
+
+pub fn time(time: &chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// The first 12 characters, as Docker shows container ids.
+pub fn short_id(id: &str) -> String {
+    id.chars().take(12).collect()
+}
+
+/// The serialized name of a unit enum variant, e.g. `running`.
+pub fn name(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => "-".to_string(),
+    }
+}
+
+/// `-` for absent values, so table columns never collapse.
+pub fn or_dash(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let value = serde_json::json!({
+            "job_id": "j1",
+            "progress": 0.5,
+            "error_message": null,
+            "output_files": ["a.txt", "b.txt"],
+            "resource_usage": { "cpu_usage": 0.25 },
+        });
+
+        let rendered = render(
+            "{{job_id}} {{ progress }}{{error_message}} {{output_files.1}}",
+            &value,
+        )
+        .unwrap();
+        assert_eq!(rendered, "j1 0.5 b.txt");
+        assert_eq!(
+            render("{{resource_usage.cpu_usage}}", &value).unwrap(),
+            "0.25"
+        );
+        assert!(render("{{missing}}", &value).is_err());
+        assert!(render("{{job_id", &value).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Managing the server process: `start`, `stop`, `restart` and `status`.
+//!
+//! The server's PID file doubles as its lock (see
+//! [`container_codes_shared::pidfile`]), so these commands ask the lock, not
+//! the file's existence, whether a server is running.
+
+use anyhow::{bail, Context};
+use clap::Args;
+use container_codes_client::Client;
+use container_codes_shared::{
+    pidfile::{self, Signal},
+    types::{HealthStatus, SystemInfo},
+};
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const SERVER_BIN: &str = "container-codes-server";
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// How long a new server may take to take the lock and answer health checks.
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a restart may take: the old server's drain plus the new one's startup.
+const RESTART_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Args)]
+pub struct ServerArgs {
+    /// Server configuration (defaults to $CONTAINER_CODES_CONFIG or config/server.toml)
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Server binary (defaults to container-codes-server next to this binary, then $PATH)
+    #[arg(long, env = "CONTAINER_CODES_SERVER_BIN")]
+    server_bin: Option<PathBuf>,
+    /// Where a daemonized server writes its output (defaults to the PID file with a .log extension)
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+}
+
+/// Where the systemd unit written by `install` keeps the PID file.
+pub const SYSTEM_PID_FILE: &str = "/run/container-codes/container-codes.pid";
+
+/// The installed service's PID file if there is one, else
+/// `$XDG_RUNTIME_DIR/container-codes.pid`, or under `/tmp` without it.
+pub fn default_pid_file() -> PathBuf {
+    if Path::new(SYSTEM_PID_FILE).exists() {
+        return PathBuf::from(SYSTEM_PID_FILE);
+    }
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("container-codes.pid")
+}
+
+pub async fn start(
+    pid_file: &Path,
+    server: &ServerArgs,
+    foreground: bool,
+    client: &Client,
+) -> anyhow::Result<()> {
+    if let Some(pid) = pidfile::running_pid(pid_file)? {
+        bail!(
+            "server already running with pid {} ({})",
+            pid,
+            pid_file.display()
+        );
+    }
+
+    if foreground {
+        // Only returns if the server could not be executed.
+        let mut command = server_command(pid_file, server)?;
+        let err = command.exec();
+        return Err(err).with_context(|| format!("failed to run {:?}", command.get_program()));
+    }
+
+    let log_file = log_file(pid_file, server);
+    let mut child = spawn_daemon(server_command(pid_file, server)?, &log_file)?;
+    wait_until_running(pid_file, &mut child, &log_file, START_TIMEOUT).await?;
+
+    if wait_until_healthy(client, &mut child, &log_file).await? {
+        println!(
+            "✅ Server running with pid {} at {}",
+            child.id(),
+            client.base_url()
+        );
+    } else {
+        println!(
+            "⚠️ Server running with pid {} but not answering at {} yet",
+            child.id(),
+            client.base_url()
+        );
+    }
+    println!("   Logs: {}", log_file.display());
+    Ok(())
+}
+
+pub async fn stop(pid_file: &Path, timeout: Duration, force: bool) -> anyhow::Result<()> {
+    let Some(pid) = pidfile::running_pid(pid_file)? else {
+        println!("  Server is not running");
+        return Ok(());
+    };
+
+    pidfile::signal(pid, Signal::Terminate)?;
+    println!("  Waiting for pid {} to finish in-flight requests...", pid);
+    if pidfile::wait_for_exit(pid_file, timeout).await? {
+        println!("✅ Server stopped");
+        return Ok(());
+    }
+
+    if !force {
+        bail!(
+            "server (pid {}) is still running after {:?}; pass --force to kill it",
+            pid,
+            timeout
+        );
+    }
+    pidfile::signal(pid, Signal::Kill)?;
+    if !pidfile::wait_for_exit(pid_file, Duration::from_secs(5)).await? {
+        bail!("server (pid {}) survived SIGKILL", pid);
+    }
+    println!("⚠️ Server killed after {:?}", timeout);
+    Ok(())
+}
+
+/// Starts a new server that takes over from the running one once it has
+/// drained, so the listening port never goes away.
+pub async fn restart(pid_file: &Path, server: &ServerArgs, client: &Client) -> anyhow::Result<()> {
+    let Some(old) = pidfile::running_pid(pid_file)? else {
+        println!("  Server is not running, starting it");
+        return start(pid_file, server, false, client).await;
+    };
+
+    let log_file = log_file(pid_file, server);
+    let mut command = server_command(pid_file, server)?;
+    command.arg("--takeover");
+    let mut child = spawn_daemon(command, &log_file)?;
+    println!(
+        "  Waiting for pid {} to hand over to pid {}...",
+        old,
+        child.id()
+    );
+    wait_until_running(pid_file, &mut child, &log_file, RESTART_TIMEOUT).await?;
+
+    if wait_until_healthy(client, &mut child, &log_file).await? {
+        println!("✅ Restarted: pid {} → {}", old, child.id());
+    } else {
+        println!(
+            "⚠️ Restarted as pid {} but not answering at {} yet",
+            child.id(),
+            client.base_url()
+        );
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct StatusReport {
+    /// Absent for a remote server, whose process cannot be checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    running: Option<bool>,
+    pid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pid_file: Option<PathBuf>,
+    url: String,
+    health: Option<HealthStatus>,
+    system: Option<SystemInfo>,
+    error: Option<String>,
+}
+
+/// Prints the process and API status; false if the API did not answer.
+/// Without a PID file only the API is checked.
+pub async fn status(pid_file: Option<&Path>, client: &Client, json: bool) -> anyhow::Result<bool> {
+    let pid = pid_file.map(pidfile::running_pid).transpose()?.flatten();
+    let api = async {
+        Ok::<_, container_codes_client::ClientError>((
+            client.health().await?,
+            client.system_info().await?,
+        ))
+    };
+    let (health, system, error) = match api.await {
+        Ok((health, system)) => (Some(health), Some(system), None),
+        Err(e) => (None, None, Some(e.to_string())),
+    };
+    let report = StatusReport {
+        running: pid_file.map(|_| pid.is_some()),
+        pid,
+        pid_file: pid_file.map(Path::to_path_buf),
+        url: client.base_url().to_string(),
+        health,
+        system,
+        error,
+    };
+    let reachable = report.error.is_none();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(reachable);
+    }
+
+    println!("📊 Container Codes server status:");
+    match (report.pid, pid_file) {
+        (Some(pid), _) => println!("  Process: running (pid {})", pid),
+        (None, Some(pid_file)) => println!("  Process: not running ({})", pid_file.display()),
+        (None, None) => {}
+    }
+    if let Some(error) = &report.error {
+        println!("  Server:  {} unreachable: {}", report.url, error);
+    }
+    if let (Some(health), Some(info)) = (&report.health, &report.system) {
+        println!("  Server:  {} (v{})", report.url, info.version);
+        println!("  Status:  {}", health.status);
+        println!("  Uptime:  {}s", info.uptime);
+        let mut checks: Vec<_> = health.checks.iter().collect();
+        checks.sort();
+        for (name, status) in checks {
+            println!("  {:<10}{}", format!("{}:", name), status);
+        }
+    }
+    Ok(reachable)
+}
+
+/// `explicit`, else container-codes-server next to this binary, else the
+/// bare name for `$PATH` lookup.
+pub fn server_binary(explicit: Option<&Path>) -> PathBuf {
+    if let Some(bin) = explicit {
+        return bin.to_path_buf();
+    }
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(SERVER_BIN))
+        .filter(|bin| bin.is_file())
+        .unwrap_or_else(|| PathBuf::from(SERVER_BIN))
+}
+
+fn server_command(pid_file: &Path, server: &ServerArgs) -> anyhow::Result<Command> {
+    let mut command = Command::new(server_binary(server.server_bin.as_deref()));
+    // Absolute, so `stop` from another directory finds the same file.
+    command
+        .arg("--pid-file")
+        .arg(std::path::absolute(pid_file)?);
+    if let Some(config) = &server.config {
+        command.env("CONTAINER_CODES_CONFIG", std::path::absolute(config)?);
+    }
+    Ok(command)
+}
+
+fn log_file(pid_file: &Path, server: &ServerArgs) -> PathBuf {
+    server
+        .log_file
+        .clone()
+        .unwrap_or_else(|| pid_file.with_extension("log"))
+}
+
+/// Runs the server detached from the terminal, in its own process group so
+/// Ctrl-C in this shell does not reach it.
+fn spawn_daemon(mut command: Command, log_file: &Path) -> anyhow::Result<Child> {
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .with_context(|| format!("failed to open {}", log_file.display()))?;
+    command
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .process_group(0);
+    command
+        .spawn()
+        .with_context(|| format!("failed to run {:?}", command.get_program()))
+}
+
+/// Waits until `child` holds the PID file lock.
+async fn wait_until_running(
+    pid_file: &Path,
+    child: &mut Child,
+    log_file: &Path,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        check_alive(child, log_file)?;
+        if pidfile::running_pid(pid_file)? == Some(child.id()) {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "server (pid {}) did not take {} within {:?}, see {}",
+                child.id(),
+                pid_file.display(),
+                timeout,
+                log_file.display()
+            );
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Waits for `/api/health` to answer; false if it did not within [`START_TIMEOUT`].
+async fn wait_until_healthy(
+    client: &Client,
+    child: &mut Child,
+    log_file: &Path,
+) -> anyhow::Result<bool> {
+    let deadline = Instant::now() + START_TIMEOUT;
+    loop {
+        check_alive(child, log_file)?;
+        if client.health().await.is_ok() {
+            return Ok(true);
+        }
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn check_alive(child: &mut Child, log_file: &Path) -> anyhow::Result<()> {
+    let Some(status) = child.try_wait()? else {
+        return Ok(());
+    };
+    let output = std::fs::read_to_string(log_file).unwrap_or_default();
+    let tail: Vec<_> = output.lines().rev().take(20).collect();
+    for line in tail.iter().rev() {
+        eprintln!("  {}", line);
+    }
+    bail!(
+        "server exited with {} (full output in {})",
+        status,
+        log_file.display()
+    );
+}
@@ -0,0 +1,74 @@
+//! Request specs for `jobs submit` and `containers create`: a YAML or JSON
+//! file, with flags applied on top.
+
+use anyhow::Context;
+use container_codes_shared::types::ResourceLimits;
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, path::Path};
+
+/// Reads a request from a `.json` file, or YAML otherwise; `-` reads stdin.
+pub fn read<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let content = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?
+    };
+
+    let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&content).map_err(anyhow::Error::from)
+    } else {
+        serde_yaml::from_str(&content).map_err(anyhow::Error::from)
+    };
+    parsed.with_context(|| format!("invalid request in {}", path.display()))
+}
+
+/// Parses `KEY=VALUE` flags such as `--env`.
+pub fn key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))
+}
+
+/// Parses `SOURCE:DESTINATION` flags such as `--publish` and `--volume`.
+pub fn mapping(s: &str) -> Result<(String, String), String> {
+    s.split_once(':')
+        .filter(|(source, destination)| !source.is_empty() && !destination.is_empty())
+        .map(|(source, destination)| (source.to_string(), destination.to_string()))
+        .ok_or_else(|| format!("expected SOURCE:DESTINATION, got '{}'", s))
+}
+
+/// Adds `pairs` to the map in `field`, overriding keys from the file.
+pub fn extend(field: &mut Option<HashMap<String, String>>, pairs: &[(String, String)]) {
+    if !pairs.is_empty() {
+        field
+            .get_or_insert_with(HashMap::new)
+            .extend(pairs.iter().cloned());
+    }
+}
+
+/// Overrides the limits given as flags, keeping the others from the file.
+pub fn limits(
+    field: &mut Option<ResourceLimits>,
+    cpu: &Option<String>,
+    memory: &Option<String>,
+    disk: &Option<String>,
+) {
+    if cpu.is_none() && memory.is_none() && disk.is_none() {
+        return;
+    }
+    let limits = field.get_or_insert(ResourceLimits {
+        cpu_limit: None,
+        memory_limit: None,
+        disk_limit: None,
+    });
+    for (limit, flag) in [
+        (&mut limits.cpu_limit, cpu),
+        (&mut limits.memory_limit, memory),
+        (&mut limits.disk_limit, disk),
+    ] {
+        if flag.is_some() {
+            limit.clone_from(flag);
+        }
+    }
+}
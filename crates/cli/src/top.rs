@@ -0,0 +1,788 @@
+//! `top`: a live dashboard over `/api/ws/updates`.
+//!
+//! Events update the panes as they arrive; a REST snapshot every few seconds
+//! fills in what events do not carry (resource usage, new jobs) and repairs
+//! anything missed while the stream was reconnecting.
+
+use crate::output;
+use container_codes_client::{Client, ListQuery, Result as ClientResult};
+use container_codes_shared::types::{
+    ContainerInfo, ContainerStatus, JobInfo, JobStatus, ProxyStats, SystemInfo, UpstreamServer,
+    WebSocketMessage,
+};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Sparkline, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+const REFRESH: Duration = Duration::from_secs(5);
+const RECONNECT: Duration = Duration::from_secs(2);
+/// CPU samples kept for the sparkline.
+const HISTORY: usize = 200;
+/// Containers and jobs fetched per snapshot.
+const SNAPSHOT_LIMIT: usize = 500;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Pane {
+    Upstreams,
+    Containers,
+    Jobs,
+}
+
+impl Pane {
+    const ALL: [Pane; 3] = [Pane::Upstreams, Pane::Containers, Pane::Jobs];
+
+    fn index(self) -> usize {
+        Pane::ALL.iter().position(|pane| *pane == self).unwrap_or(0)
+    }
+}
+
+/// A change that needs confirming before it is sent.
+#[derive(Debug, PartialEq)]
+enum Action {
+    CancelJob(String),
+    /// Drains the server, or undrains it when `undrain` is set.
+    DrainServer {
+        upstream: String,
+        address: String,
+        undrain: bool,
+    },
+    StopContainer(String),
+}
+
+impl Action {
+    fn prompt(&self) -> String {
+        match self {
+            Action::CancelJob(id) => format!("Cancel job {}?", id),
+            Action::DrainServer {
+                upstream,
+                address,
+                undrain,
+            } => format!(
+                "{} {} in {}?",
+                if *undrain { "Undrain" } else { "Drain" },
+                address,
+                upstream
+            ),
+            Action::StopContainer(id) => format!("Stop container {}?", output::short_id(id)),
+        }
+    }
+}
+
+/// What a key press asks the event loop to do.
+enum Control {
+    Quit,
+    Refresh,
+    Run(Action),
+}
+
+/// A resource an event mentioned that is not known yet.
+#[derive(Debug, PartialEq)]
+enum Fetch {
+    Job(String),
+    Container(String),
+}
+
+#[derive(Default)]
+struct Dashboard {
+    cpu_history: VecDeque<u64>,
+    cpu_usage: f64,
+    memory_usage: u64,
+    system: Option<SystemInfo>,
+    proxy: Option<ProxyStats>,
+    containers: BTreeMap<String, ContainerInfo>,
+    /// Queued and running jobs only.
+    jobs: BTreeMap<String, JobInfo>,
+    focus: Option<Pane>,
+    tables: [TableState; 3],
+    pending: Option<Action>,
+    status: String,
+    connected: bool,
+}
+
+pub async fn top(client: &Client) -> anyhow::Result<()> {
+    let mut dashboard = Dashboard::default();
+    dashboard.refresh(client).await;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, client, &mut dashboard).await;
+    ratatui::restore();
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: &Client,
+    dashboard: &mut Dashboard,
+) -> anyhow::Result<()> {
+    let mut keys = EventStream::new();
+    let mut events: Option<BoxStream<'_, ClientResult<WebSocketMessage>>> = None;
+    let mut refresh = tokio::time::interval(REFRESH);
+    let mut reconnect = tokio::time::interval(RECONNECT);
+    // Cleared when the server has no event stream; the panes are then polled.
+    let mut live = true;
+
+    loop {
+        terminal.draw(|frame| dashboard.render(frame))?;
+
+        tokio::select! {
+            key = keys.next() => match key {
+                Some(Ok(Event::Key(key))) => match dashboard.key(key) {
+                    Some(Control::Quit) => return Ok(()),
+                    Some(Control::Refresh) => dashboard.refresh(client).await,
+                    Some(Control::Run(action)) => dashboard.execute(client, action).await,
+                    None => {}
+                },
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(()),
+            },
+            message = next(&mut events) => match message {
+                Some(Ok(message)) => {
+                    for fetch in dashboard.apply(message) {
+                        dashboard.fetch(client, fetch).await;
+                    }
+                }
+                Some(Err(e)) => {
+                    events = None;
+                    dashboard.disconnected(&e.to_string());
+                }
+                None => {
+                    events = None;
+                    dashboard.disconnected("stream closed");
+                }
+            },
+            _ = refresh.tick() => dashboard.refresh(client).await,
+            _ = reconnect.tick(), if live && events.is_none() => match client.events().await {
+                Ok(stream) => {
+                    events = Some(stream.boxed());
+                    dashboard.connected = true;
+                }
+                Err(e) if e.is_not_supported() => {
+                    live = false;
+                    dashboard.status =
+                        format!("No event stream on this server, refreshing every {:?}", REFRESH);
+                }
+                Err(e) => dashboard.disconnected(&e.to_string()),
+            },
+        }
+    }
+}
+
+/// The next event, or never while disconnected.
+async fn next(
+    events: &mut Option<BoxStream<'_, ClientResult<WebSocketMessage>>>,
+) -> Option<ClientResult<WebSocketMessage>> {
+    match events {
+        Some(events) => events.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn is_finished(status: &JobStatus) -> bool {
+    !matches!(status, JobStatus::Queued | JobStatus::Running)
+}
+
+fn is_live(status: &ContainerStatus) -> bool {
+    matches!(
+        status,
+        ContainerStatus::Running | ContainerStatus::Paused | ContainerStatus::Restarting
+    )
+}
+
+impl Dashboard {
+    /// Updates the panes from one event; returns resources to fetch.
+    fn apply(&mut self, message: WebSocketMessage) -> Vec<Fetch> {
+        match message {
+            WebSocketMessage::JobStatus {
+                job_id,
+                status,
+                progress,
+            } => {
+                if is_finished(&status) {
+                    self.jobs.remove(&job_id);
+                } else if let Some(job) = self.jobs.get_mut(&job_id) {
+                    job.status = status;
+                    job.progress = progress.or(job.progress);
+                } else {
+                    return vec![Fetch::Job(job_id)];
+                }
+            }
+            WebSocketMessage::ContainerEvent {
+                container_id,
+                event,
+                ..
+            } => {
+                if matches!(
+                    event.as_str(),
+                    "die" | "stop" | "kill" | "destroy" | "remove"
+                ) {
+                    self.containers.remove(&container_id);
+                } else {
+                    return vec![Fetch::Container(container_id)];
+                }
+            }
+            WebSocketMessage::SystemMetric {
+                cpu_usage,
+                memory_usage,
+                ..
+            } => self.sample(cpu_usage, memory_usage),
+            WebSocketMessage::ProxyStats { stats } => self.proxy = Some(stats),
+            WebSocketMessage::LogEntry { .. } => {}
+        }
+        vec![]
+    }
+
+    fn sample(&mut self, cpu_usage: f64, memory_usage: u64) {
+        self.cpu_usage = cpu_usage;
+        self.memory_usage = memory_usage;
+        if self.cpu_history.len() == HISTORY {
+            self.cpu_history.pop_front();
+        }
+        self.cpu_history.push_back((cpu_usage * 10.0) as u64);
+    }
+
+    async fn fetch(&mut self, client: &Client, fetch: Fetch) {
+        match fetch {
+            Fetch::Job(id) => match client.get_job(&id).await {
+                Ok(job) if !is_finished(&job.status) => {
+                    self.jobs.insert(id, job);
+                }
+                Ok(_) => {}
+                Err(e) => self.status = format!("job {}: {}", id, e),
+            },
+            Fetch::Container(id) => match client.get_container(&id).await {
+                Ok(container) if is_live(&container.status) => {
+                    self.containers.insert(id, container);
+                }
+                Ok(_) => {
+                    self.containers.remove(&id);
+                }
+                Err(e) => self.status = format!("container {}: {}", output::short_id(&id), e),
+            },
+        }
+    }
+
+    /// Replaces every pane with the server's current state. The proxy may be
+    /// disabled, so its stats are optional.
+    async fn refresh(&mut self, client: &Client) {
+        match client.system_info().await {
+            Ok(system) => {
+                self.sample(system.cpu_usage, system.memory_usage);
+                self.system = Some(system);
+            }
+            Err(e) => self.status = format!("system info: {}", e),
+        }
+        if let Ok(stats) = client.proxy_stats().await {
+            self.proxy = Some(stats);
+        }
+
+        let containers: ClientResult<Vec<ContainerInfo>> = client
+            .list_containers(ListQuery::new())
+            .take(SNAPSHOT_LIMIT)
+            .try_collect()
+            .await;
+        match containers {
+            Ok(containers) => {
+                self.containers = containers
+                    .into_iter()
+                    .filter(|container| is_live(&container.status))
+                    .map(|container| (container.id.clone(), container))
+                    .collect();
+            }
+            Err(e) => self.status = format!("containers: {}", e),
+        }
+
+        let jobs: ClientResult<Vec<JobInfo>> = client
+            .list_jobs(jobs_query())
+            .take(SNAPSHOT_LIMIT)
+            .try_collect()
+            .await;
+        match jobs {
+            Ok(jobs) => {
+                self.jobs = jobs
+                    .into_iter()
+                    .map(|job| (job.job_id.clone(), job))
+                    .collect()
+            }
+            Err(e) => self.status = format!("jobs: {}", e),
+        }
+    }
+
+    async fn execute(&mut self, client: &Client, action: Action) {
+        self.status = match &action {
+            Action::CancelJob(id) => match client.cancel_job(id).await {
+                Ok(_) => {
+                    self.jobs.remove(id);
+                    format!("Cancelled job {}", id)
+                }
+                Err(e) => format!("Cancelling job {} failed: {}", id, e),
+            },
+            Action::DrainServer {
+                upstream,
+                address,
+                undrain,
+            } => {
+                let result = if *undrain {
+                    client.undrain_server(upstream, address).await
+                } else {
+                    client.drain_server(upstream, address).await
+                };
+                match result {
+                    Ok(server) => {
+                        let status = server.status.clone();
+                        if let Some(entry) = self.server_mut(upstream, address) {
+                            *entry = server;
+                        }
+                        format!("{} in {} is {}", address, upstream, status)
+                    }
+                    Err(e) => format!(
+                        "{} {} failed: {}",
+                        if *undrain { "Undraining" } else { "Draining" },
+                        address,
+                        e
+                    ),
+                }
+            }
+            Action::StopContainer(id) => match client.stop_container(id).await {
+                Ok(_) => {
+                    self.containers.remove(id);
+                    format!("Stopped container {}", output::short_id(id))
+                }
+                Err(e) => format!("Stopping container {} failed: {}", output::short_id(id), e),
+            },
+        };
+    }
+
+    fn disconnected(&mut self, reason: &str) {
+        self.connected = false;
+        self.status = format!("Event stream disconnected ({}), reconnecting", reason);
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Option<Control> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Control::Quit);
+        }
+
+        if let Some(action) = self.pending.take() {
+            return match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => Some(Control::Run(action)),
+                _ => {
+                    self.status = "Cancelled".to_string();
+                    None
+                }
+            };
+        }
+
+        let focus = self.focus.unwrap_or(Pane::Upstreams);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Control::Quit),
+            KeyCode::Char('r') => return Some(Control::Refresh),
+            KeyCode::Tab => self.focus = Some(Pane::ALL[(focus.index() + 1) % Pane::ALL.len()]),
+            KeyCode::BackTab => {
+                self.focus =
+                    Some(Pane::ALL[(focus.index() + Pane::ALL.len() - 1) % Pane::ALL.len()])
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.tables[focus.index()].select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.tables[focus.index()].select_previous(),
+            KeyCode::Char('c') if focus == Pane::Jobs => {
+                self.pending = self
+                    .selected_job()
+                    .map(|job| Action::CancelJob(job.job_id.clone()));
+            }
+            KeyCode::Char('s') if focus == Pane::Containers => {
+                self.pending = self
+                    .selected_container()
+                    .map(|container| Action::StopContainer(container.id.clone()));
+            }
+            KeyCode::Char('d') if focus == Pane::Upstreams => {
+                self.pending =
+                    self.selected_server()
+                        .map(|(upstream, server)| Action::DrainServer {
+                            upstream: upstream.to_string(),
+                            address: server.address.clone(),
+                            undrain: server.status == "draining",
+                        });
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Servers of every upstream, ordered by upstream name.
+    fn servers(&self) -> Vec<(&str, &UpstreamServer)> {
+        let Some(proxy) = &self.proxy else {
+            return vec![];
+        };
+        let mut upstreams: Vec<_> = proxy.upstreams.iter().collect();
+        upstreams.sort_by_key(|(name, _)| *name);
+        upstreams
+            .into_iter()
+            .flat_map(|(name, stats)| {
+                stats
+                    .servers
+                    .iter()
+                    .map(move |server| (name.as_str(), server))
+            })
+            .collect()
+    }
+
+    fn server_mut(&mut self, upstream: &str, address: &str) -> Option<&mut UpstreamServer> {
+        let stats = self.proxy.as_mut()?.upstreams.get_mut(upstream)?;
+        stats
+            .servers
+            .iter_mut()
+            .find(|server| server.address == address)
+    }
+
+    fn selected_server(&self) -> Option<(&str, &UpstreamServer)> {
+        let index = self.tables[Pane::Upstreams.index()].selected()?;
+        self.servers().get(index).copied()
+    }
+
+    fn selected_container(&self) -> Option<&ContainerInfo> {
+        self.containers
+            .values()
+            .nth(self.tables[Pane::Containers.index()].selected()?)
+    }
+
+    fn selected_job(&self) -> Option<&JobInfo> {
+        self.jobs
+            .values()
+            .nth(self.tables[Pane::Jobs.index()].selected()?)
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [top, upstreams, bottom, footer] = Layout::vertical([
+            Constraint::Length(6),
+            Constraint::Percentage(35),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [system, proxy] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(top);
+        let [containers, jobs] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Fill(1)]).areas(bottom);
+
+        self.render_system(frame, system);
+        self.render_proxy(frame, proxy);
+        self.render_upstreams(frame, upstreams);
+        self.render_containers(frame, containers);
+        self.render_jobs(frame, jobs);
+        self.render_footer(frame, footer);
+    }
+
+    fn block(&self, title: &str, pane: Option<Pane>) -> Block<'static> {
+        let block = Block::bordered().title(format!(" {} ", title));
+        if pane.is_some() && pane == self.focus.or(Some(Pane::Upstreams)) {
+            block.border_style(Style::new().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+
+    fn render_system(&self, frame: &mut Frame, area: Rect) {
+        let connection = if self.connected {
+            "live".green()
+        } else {
+            "offline".red()
+        };
+        let block = self
+            .block("System", None)
+            .title_top(Line::from(connection).right_aligned());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [summary, history] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner);
+        let mut lines = vec![Line::from(format!(
+            "CPU {}   Memory {}",
+            output::percent(self.cpu_usage),
+            output::bytes(self.memory_usage)
+        ))];
+        if let Some(system) = &self.system {
+            lines.push(Line::from(format!(
+                "v{}   up {}s   {} connections",
+                system.version, system.uptime, system.active_connections
+            )));
+        }
+        frame.render_widget(Paragraph::new(lines), summary);
+
+        let samples: Vec<u64> = self
+            .cpu_history
+            .iter()
+            .rev()
+            .take(history.width as usize)
+            .rev()
+            .copied()
+            .collect();
+        frame.render_widget(
+            Sparkline::default()
+                .data(&samples)
+                .max(1000)
+                .style(Style::new().fg(Color::Green)),
+            history,
+        );
+    }
+
+    fn render_proxy(&self, frame: &mut Frame, area: Rect) {
+        let lines = match &self.proxy {
+            Some(stats) => vec![
+                Line::from(format!(
+                    "{:.1} req/s   {} total",
+                    stats.requests_per_second, stats.requests_total
+                )),
+                Line::from(format!(
+                    "errors {}",
+                    output::percent(stats.error_rate * 100.0)
+                )),
+                Line::from(format!(
+                    "latency avg {:.1}ms  p50 {:.1}ms  p95 {:.1}ms  p99 {:.1}ms",
+                    stats.response_times.avg,
+                    stats.response_times.p50,
+                    stats.response_times.p95,
+                    stats.response_times.p99
+                )),
+            ],
+            None => vec![Line::from("No proxy statistics").dim()],
+        };
+        frame.render_widget(Paragraph::new(lines).block(self.block("Proxy", None)), area);
+    }
+
+    fn render_upstreams(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .servers()
+            .into_iter()
+            .map(|(upstream, server)| {
+                let status = match server.status.as_str() {
+                    "healthy" => server.status.clone().green(),
+                    "draining" => server.status.clone().yellow(),
+                    _ => server.status.clone().red(),
+                };
+                Row::new(vec![
+                    upstream.to_string().into(),
+                    server.address.clone().into(),
+                    Line::from(status),
+                    server.weight.to_string().into(),
+                    server.active_connections.to_string().into(),
+                    server.total_requests.to_string().into(),
+                    format!("{:.1}ms", server.response_time).into(),
+                    output::percent(server.error_rate * 100.0).into(),
+                ])
+            })
+            .collect();
+        let header = [
+            "UPSTREAM", "SERVER", "STATUS", "WEIGHT", "ACTIVE", "REQUESTS", "LATENCY", "ERRORS",
+        ];
+        let widths = [20, 22, 10, 7, 7, 10, 10, 7].map(Constraint::Min);
+        self.render_table(
+            frame,
+            area,
+            Pane::Upstreams,
+            "Upstreams",
+            &header,
+            rows,
+            &widths,
+        );
+    }
+
+    fn render_containers(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .containers
+            .values()
+            .map(|container| {
+                let usage = container.resource_usage.as_ref();
+                Row::new(vec![
+                    output::short_id(&container.id),
+                    container.name.clone(),
+                    output::name(&container.status),
+                    output::or_dash(usage.map(|usage| output::percent(usage.cpu_usage))),
+                    output::or_dash(usage.map(|usage| output::bytes(usage.memory_usage))),
+                ])
+            })
+            .collect();
+        let header = ["ID", "NAME", "STATUS", "CPU", "MEMORY"];
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Fill(1),
+            Constraint::Length(10),
+            Constraint::Length(7),
+            Constraint::Length(10),
+        ];
+        let title = format!("Containers ({})", self.containers.len());
+        self.render_table(
+            frame,
+            area,
+            Pane::Containers,
+            &title,
+            &header,
+            rows,
+            &widths,
+        );
+    }
+
+    fn render_jobs(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .jobs
+            .values()
+            .map(|job| {
+                Row::new(vec![
+                    job.job_id.clone(),
+                    job.name.clone(),
+                    output::name(&job.status),
+                    output::or_dash(job.progress.map(output::percent)),
+                ])
+            })
+            .collect();
+        let header = ["ID", "NAME", "STATUS", "PROGRESS"];
+        let widths = [
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ];
+        let queued = self
+            .jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Queued)
+            .count();
+        let title = format!(
+            "Jobs ({} running, {} queued)",
+            self.jobs.len() - queued,
+            queued
+        );
+        self.render_table(frame, area, Pane::Jobs, &title, &header, rows, &widths);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_table(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        pane: Pane,
+        title: &str,
+        header: &[&str],
+        rows: Vec<Row>,
+        widths: &[Constraint],
+    ) {
+        let state = &mut self.tables[pane.index()];
+        match state.selected() {
+            Some(selected) if selected >= rows.len() => state.select(rows.len().checked_sub(1)),
+            None if !rows.is_empty() => state.select(Some(0)),
+            _ => {}
+        }
+
+        let header = Row::new(header.to_vec()).style(Style::new().add_modifier(Modifier::BOLD));
+        let table = Table::new(rows, widths.to_vec())
+            .header(header)
+            .block(self.block(title, Some(pane)))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.tables[pane.index()]);
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let line = if let Some(action) = &self.pending {
+            Line::from(format!("{} [y/n]", action.prompt()))
+                .yellow()
+                .bold()
+        } else {
+            let keys = match self.focus.unwrap_or(Pane::Upstreams) {
+                Pane::Upstreams => "d drain/undrain",
+                Pane::Containers => "s stop",
+                Pane::Jobs => "c cancel",
+            };
+            let help = format!("q quit  tab pane  ↑↓ select  {}  r refresh", keys);
+            if self.status.is_empty() {
+                Line::from(help).dim()
+            } else {
+                Line::from(format!("{}  │  {}", self.status, help))
+            }
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+}
+
+/// The jobs pane: queued and running jobs, oldest first.
+fn jobs_query() -> ListQuery {
+    ListQuery::new()
+        .filter_op("status", "in", "queued|running")
+        .sort("created_at")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use container_codes_shared::pagination::FilterOp;
+
+    fn job(id: &str, status: JobStatus) -> JobInfo {
+        JobInfo {
+            job_id: id.to_string(),
+            name: "build".to_string(),
+            status,
+            progress: None,
+            created_at: chrono::Utc::now(),
+            started_at: None,
+            finished_at: None,
+            estimated_completion: None,
+            container_id: None,
+            resource_usage: None,
+            output_files: vec![],
+            error_message: None,
+        }
+    }
+
+    #[test]
+    fn test_events_update_job_queue() {
+        let mut dashboard = Dashboard::default();
+        dashboard
+            .jobs
+            .insert("j1".into(), job("j1", JobStatus::Queued));
+
+        let event = |id: &str, status| WebSocketMessage::JobStatus {
+            job_id: id.into(),
+            status,
+            progress: Some(40.0),
+        };
+        assert!(dashboard.apply(event("j1", JobStatus::Running)).is_empty());
+        assert_eq!(dashboard.jobs["j1"].status, JobStatus::Running);
+        assert_eq!(dashboard.jobs["j1"].progress, Some(40.0));
+
+        assert_eq!(
+            dashboard.apply(event("j2", JobStatus::Queued)),
+            vec![Fetch::Job("j2".into())]
+        );
+        assert!(dashboard
+            .apply(event("j1", JobStatus::Completed))
+            .is_empty());
+        assert!(dashboard.jobs.is_empty());
+    }
+
+    #[test]
+    fn test_jobs_query_selects_queued_and_running() {
+        let query: String = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(jobs_query().params())
+            .finish();
+        let parsed = container_codes_shared::pagination::ListQuery::parse(&query).unwrap();
+        let values: Vec<&str> = parsed
+            .filters
+            .iter()
+            .filter(|filter| filter.field == "status" && filter.op == FilterOp::In)
+            .flat_map(|filter| filter.value.split('|'))
+            .collect();
+        assert_eq!(values, ["queued", "running"]);
+        assert_eq!(parsed.sort.len(), 1);
+        assert!(!parsed.sort[0].descending);
+    }
+}
@@ -0,0 +1,123 @@
+use container_codes_shared::types::{
+    ContainerCreateRequest, ContainerExecRequest, ContainerExecResult, ContainerInfo, ResourceUsage,
+};
+use futures::{Stream, TryStreamExt};
+use reqwest::Method;
+
+use crate::{stream::lines, Client, ListQuery, Result};
+
+/// Options for container and job log requests.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Keep the connection open and yield new lines as they are written.
+    pub follow: bool,
+    /// Only the last `tail` lines.
+    pub tail: Option<u32>,
+    /// Only lines after this RFC 3339 timestamp.
+    pub since: Option<String>,
+    pub timestamps: bool,
+}
+
+impl LogOptions {
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("follow", self.follow.to_string()),
+            ("timestamps", self.timestamps.to_string()),
+        ];
+        if let Some(tail) = self.tail {
+            query.push(("tail", tail.to_string()));
+        }
+        if let Some(since) = &self.since {
+            query.push(("since", since.clone()));
+        }
+        query
+    }
+}
+
+impl Client {
+    pub fn list_containers(
+        &self,
+        query: ListQuery,
+    ) -> impl Stream<Item = Result<ContainerInfo>> + '_ {
+        self.paginate("api/containers", query)
+    }
+
+    pub async fn get_container(&self, id: &str) -> Result<ContainerInfo> {
+        self.get(&format!("api/containers/{}", id)).await
+    }
+
+    pub async fn create_container(
+        &self,
+        request: &ContainerCreateRequest,
+    ) -> Result<ContainerInfo> {
+        self.post("api/containers", request).await
+    }
+
+    pub async fn start_container(&self, id: &str) -> Result<ContainerInfo> {
+        self.container_action(id, "start").await
+    }
+
+    pub async fn stop_container(&self, id: &str) -> Result<ContainerInfo> {
+        self.container_action(id, "stop").await
+    }
+
+    pub async fn restart_container(&self, id: &str) -> Result<ContainerInfo> {
+        self.container_action(id, "restart").await
+    }
+
+    pub async fn pause_container(&self, id: &str) -> Result<ContainerInfo> {
+        self.container_action(id, "pause").await
+    }
+
+    pub async fn unpause_container(&self, id: &str) -> Result<ContainerInfo> {
+        self.container_action(id, "unpause").await
+    }
+
+    pub async fn remove_container(&self, id: &str) -> Result<()> {
+        self.execute(
+            Method::DELETE,
+            &format!("api/containers/{}", id),
+            false,
+            |r| r,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn container_stats(&self, id: &str) -> Result<ResourceUsage> {
+        self.get(&format!("api/containers/{}/stats", id)).await
+    }
+
+    /// Runs a command in a running container and waits for it to exit.
+    pub async fn exec_container(
+        &self,
+        id: &str,
+        request: &ContainerExecRequest,
+    ) -> Result<ContainerExecResult> {
+        self.post(&format!("api/containers/{}/exec", id), request)
+            .await
+    }
+
+    /// Log lines of a container; with `follow` the stream stays open.
+    pub async fn container_logs(
+        &self,
+        id: &str,
+        options: &LogOptions,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        let query = options.query();
+        let path = format!("api/containers/{}/logs", id);
+        let response = self
+            .execute(Method::GET, &path, options.follow, |r| r.query(&query))
+            .await?;
+        Ok(lines(response).into_stream())
+    }
+
+    async fn container_action(&self, id: &str, action: &str) -> Result<ContainerInfo> {
+        self.call(
+            Method::POST,
+            &format!("api/containers/{}/{}", id, action),
+            |r| r,
+        )
+        .await
+    }
+}
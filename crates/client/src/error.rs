@@ -0,0 +1,160 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::time::Duration;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("{}: {}", .0.code, .0.message)]
+    Api(Box<ApiFailure>),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+
+    #[error("Invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("TLS configuration error: {0}")]
+    Tls(String),
+
+    #[error("Unexpected response: {0}")]
+    Decode(String),
+}
+
+/// An error response from the API.
+#[derive(Debug)]
+pub struct ApiFailure {
+    pub status: StatusCode,
+    pub kind: ErrorKind,
+    /// `ApiError.code`, or `HTTP_<status>` when the body was not an `ApiResponse`.
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+    pub request_id: Option<String>,
+    pub retry_after: Option<Duration>,
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        ClientError::WebSocket(Box::new(error))
+    }
+}
+
+impl ClientError {
+    /// The API failure, if the server answered with an error status.
+    pub fn api(&self) -> Option<&ApiFailure> {
+        match self {
+            ClientError::Api(failure) => Some(failure),
+            _ => None,
+        }
+    }
+
+    /// The API error category, if the server answered with an error status.
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        self.api().map(|failure| &failure.kind)
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.kind(), Some(ErrorKind::NotFound))
+    }
+
+    pub fn is_not_supported(&self) -> bool {
+        matches!(self.kind(), Some(ErrorKind::NotSupported))
+    }
+
+    /// The id the server logged this request under.
+    pub fn request_id(&self) -> Option<&str> {
+        self.api()?.request_id.as_deref()
+    }
+}
+
+/// Category of an `ApiError.code`, see `docs/api-specification.md`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidRequest,
+    Unauthorized,
+    Forbidden,
+    /// `NOT_FOUND` and the resource-specific `*_NOT_FOUND` codes.
+    NotFound,
+    Conflict,
+    InvalidConfig,
+    RateLimited,
+    Internal,
+    Unavailable,
+    /// The server does not implement the endpoint.
+    NotSupported,
+    /// A code this client version does not know about.
+    Other(String),
+}
+
+impl ErrorKind {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "INVALID_REQUEST" => Self::InvalidRequest,
+            "UNAUTHORIZED" => Self::Unauthorized,
+            "FORBIDDEN" => Self::Forbidden,
+            "CONFLICT" => Self::Conflict,
+            "INVALID_CONFIG" => Self::InvalidConfig,
+            "RATE_LIMITED" => Self::RateLimited,
+            "INTERNAL_ERROR" => Self::Internal,
+            "SERVICE_UNAVAILABLE" => Self::Unavailable,
+            "NOT_SUPPORTED" => Self::NotSupported,
+            code if code == "NOT_FOUND" || code.ends_with("_NOT_FOUND") => Self::NotFound,
+            code => Self::Other(code.to_string()),
+        }
+    }
+
+    /// Used when an error response has no JSON body, e.g. from a proxy in between.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Self::InvalidRequest,
+            StatusCode::UNAUTHORIZED => Self::Unauthorized,
+            StatusCode::FORBIDDEN => Self::Forbidden,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::CONFLICT => Self::Conflict,
+            StatusCode::UNPROCESSABLE_ENTITY => Self::InvalidConfig,
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+            StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => Self::Unavailable,
+            StatusCode::NOT_IMPLEMENTED => Self::NotSupported,
+            _ if status.is_server_error() => Self::Internal,
+            _ => Self::Other(status.as_u16().to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind_from_code() {
+        assert_eq!(
+            ErrorKind::from_code("CONTAINER_NOT_FOUND"),
+            ErrorKind::NotFound
+        );
+        assert_eq!(ErrorKind::from_code("RATE_LIMITED"), ErrorKind::RateLimited);
+        assert_eq!(
+            ErrorKind::from_code("TEAPOT"),
+            ErrorKind::Other("TEAPOT".to_string())
+        );
+        assert_eq!(
+            ErrorKind::from_status(StatusCode::GATEWAY_TIMEOUT),
+            ErrorKind::Unavailable
+        );
+        assert_eq!(
+            ErrorKind::from_code("NOT_SUPPORTED"),
+            ErrorKind::NotSupported
+        );
+        assert_eq!(
+            ErrorKind::from_status(StatusCode::NOT_IMPLEMENTED),
+            ErrorKind::NotSupported
+        );
+    }
+}
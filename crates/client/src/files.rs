@@ -0,0 +1,28 @@
+use bytes::Bytes;
+use container_codes_shared::types::FileInfo;
+use reqwest::{multipart, Method};
+
+use crate::{Client, Result};
+
+impl Client {
+    /// Uploads `contents` as `file_name` into the server's upload directory.
+    pub async fn upload_file(&self, file_name: &str, contents: impl Into<Bytes>) -> Result<String> {
+        let contents = contents.into();
+        self.call(Method::POST, "api/files/upload", |r| {
+            let part = multipart::Part::stream(contents.clone()).file_name(file_name.to_string());
+            r.multipart(multipart::Form::new().part("file", part))
+        })
+        .await
+    }
+
+    pub async fn download_file(&self, path: &str) -> Result<Bytes> {
+        let path = format!("api/files/download/{}", path.trim_start_matches('/'));
+        let response = self.execute(Method::GET, &path, false, |r| r).await?;
+        Ok(response.bytes().await?)
+    }
+
+    pub async fn file_info(&self, path: &str) -> Result<FileInfo> {
+        self.get(&format!("api/files/info/{}", path.trim_start_matches('/')))
+            .await
+    }
+}
@@ -0,0 +1,47 @@
+use container_codes_shared::types::{JobCreateRequest, JobInfo};
+use futures::{Stream, TryStreamExt};
+use reqwest::Method;
+
+use crate::{stream::lines, Client, ListQuery, LogOptions, Result};
+
+impl Client {
+    pub async fn submit_job(&self, request: &JobCreateRequest) -> Result<JobInfo> {
+        self.post("api/jobs", request).await
+    }
+
+    pub async fn get_job(&self, id: &str) -> Result<JobInfo> {
+        self.get(&format!("api/jobs/{}", id)).await
+    }
+
+    pub fn list_jobs(&self, query: ListQuery) -> impl Stream<Item = Result<JobInfo>> + '_ {
+        self.paginate("api/jobs", query)
+    }
+
+    pub async fn cancel_job(&self, id: &str) -> Result<JobInfo> {
+        self.call(Method::POST, &format!("api/jobs/{}/cancel", id), |r| r)
+            .await
+    }
+
+    pub async fn retry_job(&self, id: &str) -> Result<JobInfo> {
+        self.call(Method::POST, &format!("api/jobs/{}/retry", id), |r| r)
+            .await
+    }
+
+    /// Log lines of a job's container; with `follow` the stream stays open.
+    pub async fn job_logs(
+        &self,
+        id: &str,
+        options: &LogOptions,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        let query = options.query();
+        let path = format!("api/jobs/{}/logs", id);
+        let response = self
+            .execute(Method::GET, &path, options.follow, |r| r.query(&query))
+            .await?;
+        Ok(lines(response).into_stream())
+    }
+
+    pub async fn job_outputs(&self, id: &str) -> Result<Vec<String>> {
+        self.get(&format!("api/jobs/{}/output", id)).await
+    }
+}
@@ -0,0 +1,380 @@
+//! Typed async client for the Container Codes management API.
+//!
+//! ```no_run
+//! # async fn run() -> container_codes_client::Result<()> {
+//! use container_codes_client::{Auth, Client};
+//!
+//! let client = Client::builder("http://127.0.0.1:8080")
+//!     .auth(Auth::ApiKey("cc_0123456789abcdef".into()))
+//!     .build()?;
+//! let health = client.health().await?;
+//! println!("{}", health.status);
+//! # Ok(())
+//! # }
+//! ```
+
+mod containers;
+mod error;
+mod files;
+mod jobs;
+mod pagination;
+mod proxy;
+mod retry;
+mod stream;
+mod system;
+
+pub use containers::LogOptions;
+pub use error::{ApiFailure, ClientError, ErrorKind, Result};
+pub use pagination::ListQuery;
+pub use retry::RetryPolicy;
+
+use container_codes_shared::{
+    request_id::{RequestId, REQUEST_ID_HEADER},
+    types::ApiResponse,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, RETRY_AFTER},
+    Method, RequestBuilder, Response, StatusCode,
+};
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer},
+    ClientConfig, RootCertStore,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{sync::Arc, time::Duration};
+use tracing::debug;
+use url::Url;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Credentials sent with every request.
+#[derive(Debug, Clone)]
+pub enum Auth {
+    /// Sent as `X-API-Key`.
+    ApiKey(String),
+    /// A JWT, sent as `Authorization: Bearer`.
+    Bearer(String),
+}
+
+impl Auth {
+    fn header(&self) -> Result<(HeaderName, HeaderValue)> {
+        let (name, value) = match self {
+            Auth::ApiKey(key) => (HeaderName::from_static(API_KEY_HEADER), key.clone()),
+            Auth::Bearer(token) => (AUTHORIZATION, format!("Bearer {}", token)),
+        };
+        let value = HeaderValue::from_str(&value).map_err(|_| {
+            ClientError::Decode("credentials contain invalid header characters".into())
+        })?;
+        Ok((name, value))
+    }
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    auth: Option<Auth>,
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    ca_cert: Option<Vec<u8>>,
+}
+
+impl ClientBuilder {
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Per-request timeout; streaming calls are not limited.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Also trusts the certificates in this PEM bundle, for servers with a
+    /// private CA. Applies to WebSocket connections too.
+    pub fn ca_cert(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca_cert = Some(pem.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let mut base = Url::parse(&self.base_url)?;
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+
+        let tls = self.ca_cert.as_deref().map(tls_config).transpose()?;
+        let mut http = reqwest::Client::builder().user_agent(concat!(
+            "container-codes-client/",
+            env!("CARGO_PKG_VERSION")
+        ));
+        if let Some(tls) = &tls {
+            http = http.use_preconfigured_tls(ClientConfig::clone(tls));
+        }
+
+        Ok(Client {
+            http: http.build()?,
+            base,
+            auth: self.auth.map(|a| a.header()).transpose()?,
+            retry: self.retry,
+            timeout: self.timeout,
+            tls,
+        })
+    }
+}
+
+/// The public web roots plus the certificates in `pem`.
+fn tls_config(pem: &[u8]) -> Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    let certs = CertificateDer::pem_slice_iter(pem)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| ClientError::Tls(format!("invalid CA certificate: {}", e)))?;
+    if certs.is_empty() {
+        return Err(ClientError::Tls("no certificates in CA bundle".into()));
+    }
+    for cert in certs {
+        roots
+            .add(cert)
+            .map_err(|e| ClientError::Tls(format!("invalid CA certificate: {}", e)))?;
+    }
+
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| ClientError::Tls(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base: Url,
+    auth: Option<(HeaderName, HeaderValue)>,
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    /// Set when a CA certificate was given.
+    tls: Option<Arc<ClientConfig>>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.into(),
+            auth: None,
+            retry: RetryPolicy::default(),
+            timeout: Some(Duration::from_secs(30)),
+            ca_cert: None,
+        }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        Ok(self.base.join(path.trim_start_matches('/'))?)
+    }
+
+    /// Sends a request built by `build`, retrying per the [`RetryPolicy`].
+    /// Every attempt carries the same `x-request-id`.
+    async fn execute(
+        &self,
+        method: Method,
+        path: &str,
+        streaming: bool,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response> {
+        let url = self.url(path)?;
+        let request_id = RequestId::new();
+        let mut attempt = 0;
+
+        loop {
+            let mut request = self
+                .http
+                .request(method.clone(), url.clone())
+                .header(REQUEST_ID_HEADER, request_id.header_value());
+            if let Some((name, value)) = &self.auth {
+                request = request.header(name, value);
+            }
+            if let (Some(timeout), false) = (self.timeout, streaming) {
+                request = request.timeout(timeout);
+            }
+
+            let retry_after = match build(request).send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response)
+                    if RetryPolicy::is_retryable(response.status())
+                        && self.retry.should_retry(&method, attempt) =>
+                {
+                    retry_after(response.headers())
+                }
+                Ok(response) => return Err(api_error(response).await),
+                Err(e)
+                    if (e.is_connect() || e.is_timeout())
+                        && self.retry.should_retry(&method, attempt) =>
+                {
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let delay = self.retry.delay(attempt, retry_after);
+            debug!(%method, %url, attempt, ?delay, "Retrying request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request and unwraps the `data` of its `ApiResponse<T>` body.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<T> {
+        let response = self.execute(method, path, false, build).await?;
+        let body: ApiResponse<T> = response.json().await?;
+        body.data
+            .ok_or_else(|| ClientError::Decode(format!("{} returned no data", path)))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.call(Method::GET, path, |r| r).await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T> {
+        self.call(Method::POST, path, |r| r.json(body)).await
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+async fn api_error(response: Response) -> ClientError {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = response.bytes().await.unwrap_or_default();
+    decode_error(status, &headers, &bytes)
+}
+
+/// Turns an error response into [`ClientError::Api`], falling back to the
+/// status code when the body is not an `ApiResponse`.
+pub(crate) fn decode_error(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> ClientError {
+    let retry_after = retry_after(headers);
+    let header_id = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    match serde_json::from_slice::<ApiResponse<serde_json::Value>>(body) {
+        Ok(ApiResponse {
+            error: Some(error),
+            request_id,
+            ..
+        }) => ClientError::Api(Box::new(ApiFailure {
+            status,
+            kind: ErrorKind::from_code(&error.code),
+            code: error.code,
+            message: error.message,
+            details: error.details,
+            request_id: Some(request_id),
+            retry_after,
+        })),
+        _ => ClientError::Api(Box::new(ApiFailure {
+            status,
+            kind: ErrorKind::from_status(status),
+            code: format!("HTTP_{}", status.as_u16()),
+            message: status
+                .canonical_reason()
+                .unwrap_or("Unknown error")
+                .to_string(),
+            details: None,
+            request_id: header_id,
+            retry_after,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Json, Router};
+    use container_codes_shared::{types::HealthStatus, Error};
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    async fn serve(router: Router) -> Client {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        Client::builder(format!("http://{}", addr))
+            .retry(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_decodes() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let client = serve(Router::new().route(
+            "/api/health",
+            get(move || {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 2 {
+                        return Err(StatusCode::SERVICE_UNAVAILABLE);
+                    }
+                    Ok(Json(ApiResponse::success(HealthStatus {
+                        status: "healthy".into(),
+                        timestamp: chrono::Utc::now(),
+                        checks: Default::default(),
+                        uptime: 1,
+                    })))
+                }
+            }),
+        ))
+        .await;
+
+        assert_eq!(client.health().await.unwrap().status, "healthy");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_typed_api_errors() {
+        let client = serve(Router::new().route(
+            "/api/jobs/:id",
+            get(|| async { Error::not_found("job", "j1") }),
+        ))
+        .await;
+
+        let err = client.get_job("j1").await.unwrap_err();
+        assert!(err.is_not_found(), "{:?}", err);
+        assert_eq!(err.api().unwrap().code, "JOB_NOT_FOUND");
+    }
+}
@@ -0,0 +1,119 @@
+use container_codes_shared::types::PaginatedResponse;
+use futures::{stream, Stream, TryStreamExt};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+
+use crate::{Client, Result};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// Filters, sort order and page size for list endpoints.
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    page_size: u32,
+    filters: Vec<(String, String)>,
+}
+
+/// Where the next page starts.
+enum Page {
+    Offset(u32),
+    Cursor(String),
+}
+
+impl ListQuery {
+    pub fn new() -> Self {
+        Self {
+            page_size: DEFAULT_PAGE_SIZE,
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Adds a query parameter such as `status=running`.
+    pub fn filter(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.filters.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds a `field:op:value` filter expression, e.g. `("created_at", "gte", "2024-01-01T00:00:00Z")`.
+    pub fn filter_op(self, field: &str, op: &str, value: &str) -> Self {
+        self.filter("filter", format!("{}:{}:{}", field, op, value))
+    }
+
+    /// Sort fields, `-` prefixed for descending, e.g. `-created_at`.
+    pub fn sort(self, fields: &str) -> Self {
+        self.filter("sort", fields)
+    }
+
+    /// The filter and sort parameters, as sent with every page.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.filters
+    }
+}
+
+impl Default for ListQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Fetches `path` page by page until the server reports no more,
+    /// following `next_cursor` when the endpoint returns one.
+    pub fn pages<T: DeserializeOwned + 'static>(
+        &self,
+        path: &str,
+        query: ListQuery,
+    ) -> impl Stream<Item = Result<PaginatedResponse<T>>> + '_ {
+        let path = path.to_string();
+
+        stream::try_unfold(Some(Page::Offset(0)), move |next| {
+            let path = path.clone();
+            let query = query.clone();
+            async move {
+                let Some(next) = next else {
+                    return Ok(None);
+                };
+
+                let mut params = query.filters;
+                params.push(("limit".into(), query.page_size.to_string()));
+                let offset = match next {
+                    Page::Offset(offset) => {
+                        params.push(("offset".into(), offset.to_string()));
+                        offset
+                    }
+                    Page::Cursor(cursor) => {
+                        params.push(("cursor".into(), cursor));
+                        0
+                    }
+                };
+
+                let page: PaginatedResponse<T> =
+                    self.call(Method::GET, &path, |r| r.query(&params)).await?;
+
+                // An empty page would otherwise loop forever on a buggy `has_next`.
+                let next = match &page.next_cursor {
+                    _ if !page.has_next || page.items.is_empty() => None,
+                    Some(cursor) => Some(Page::Cursor(cursor.clone())),
+                    None => Some(Page::Offset(offset + page.items.len() as u32)),
+                };
+                Ok(Some((page, next)))
+            }
+        })
+    }
+
+    /// Every item of a paginated list, fetching pages lazily.
+    pub fn paginate<T: DeserializeOwned + 'static>(
+        &self,
+        path: &str,
+        query: ListQuery,
+    ) -> impl Stream<Item = Result<T>> + '_ {
+        self.pages(path, query)
+            .map_ok(|page| stream::iter(page.items.into_iter().map(Ok)))
+            .try_flatten()
+    }
+}
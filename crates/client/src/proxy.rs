@@ -0,0 +1,23 @@
+use container_codes_shared::types::{ProxyStats, UpstreamServer};
+use reqwest::Method;
+
+use crate::{Client, Result};
+
+impl Client {
+    pub async fn proxy_stats(&self) -> Result<ProxyStats> {
+        self.get("api/proxy/stats").await
+    }
+
+    /// Stops sending new requests to one server of an upstream; requests in
+    /// flight are allowed to finish.
+    pub async fn drain_server(&self, upstream: &str, address: &str) -> Result<UpstreamServer> {
+        let path = format!("api/proxy/upstreams/{}/servers/{}/drain", upstream, address);
+        self.call(Method::POST, &path, |r| r).await
+    }
+
+    /// Puts a drained server back into rotation.
+    pub async fn undrain_server(&self, upstream: &str, address: &str) -> Result<UpstreamServer> {
+        let path = format!("api/proxy/upstreams/{}/servers/{}/drain", upstream, address);
+        self.call(Method::DELETE, &path, |r| r).await
+    }
+}
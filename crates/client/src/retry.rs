@@ -0,0 +1,79 @@
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+
+/// Retries idempotent requests that failed in transit or with 429/502/503/504.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn should_retry(&self, method: &Method, attempt: u32) -> bool {
+        attempt < self.max_retries
+            && matches!(
+                *method,
+                Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+            )
+    }
+
+    pub(crate) fn is_retryable(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    /// Exponential backoff from `base_delay`, capped at `max_delay`. A server
+    /// `Retry-After` wins when it is longer.
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        retry_after.map_or(backoff, |after| after.max(backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0, None), Duration::from_millis(200));
+        assert_eq!(policy.delay(2, None), Duration::from_millis(800));
+        assert_eq!(policy.delay(10, None), Duration::from_secs(5));
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+
+        assert!(policy.should_retry(&Method::GET, 0));
+        assert!(!policy.should_retry(&Method::POST, 0));
+        assert!(!policy.should_retry(&Method::GET, 3));
+    }
+}
@@ -0,0 +1,85 @@
+use bytes::BytesMut;
+use container_codes_shared::types::WebSocketMessage;
+use futures::{stream, Stream, StreamExt};
+use reqwest::Response;
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Error, Message},
+    Connector,
+};
+
+use crate::{decode_error, Client, ClientError, Result};
+
+/// Splits a streamed response body into lines, without the trailing newline.
+pub(crate) fn lines(response: Response) -> impl Stream<Item = Result<String>> {
+    let body = response.bytes_stream().boxed();
+
+    stream::try_unfold(
+        (body, BytesMut::new(), false),
+        |(mut body, mut buffer, mut done)| async move {
+            loop {
+                if let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                    let line = buffer.split_to(end + 1);
+                    let line = String::from_utf8_lossy(&line[..end])
+                        .trim_end_matches('\r')
+                        .to_string();
+                    return Ok(Some((line, (body, buffer, done))));
+                }
+                if done {
+                    if buffer.is_empty() {
+                        return Ok(None);
+                    }
+                    let line = String::from_utf8_lossy(&buffer).to_string();
+                    buffer.clear();
+                    return Ok(Some((line, (body, buffer, done))));
+                }
+
+                match body.next().await {
+                    Some(chunk) => buffer.extend_from_slice(&chunk.map_err(ClientError::from)?),
+                    None => done = true,
+                }
+            }
+        },
+    )
+}
+
+impl Client {
+    /// Live events from `/api/ws/updates`.
+    pub async fn events(&self) -> Result<impl Stream<Item = Result<WebSocketMessage>>> {
+        let mut url = self.url("api/ws/updates")?;
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme)
+            .map_err(|_| ClientError::Decode(format!("cannot use {} for WebSockets", url)))?;
+
+        let mut request = url.as_str().into_client_request()?;
+        if let Some((name, value)) = &self.auth {
+            request.headers_mut().insert(name, value.clone());
+        }
+
+        let connector = self.tls.clone().map(Connector::Rustls);
+        let (socket, _) =
+            tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector)
+                .await
+                .map_err(|e| match e {
+                    // The server refused the upgrade with an API error.
+                    Error::Http(response) => decode_error(
+                        response.status(),
+                        response.headers(),
+                        response.body().as_deref().unwrap_or_default(),
+                    ),
+                    e => e.into(),
+                })?;
+
+        Ok(socket.filter_map(|message| async move {
+            match message {
+                Ok(Message::Text(text)) => Some(decode(text.as_bytes())),
+                Ok(Message::Binary(bytes)) => Some(decode(&bytes)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        }))
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<WebSocketMessage> {
+    serde_json::from_slice(bytes).map_err(|e| ClientError::Decode(format!("invalid event: {}", e)))
+}
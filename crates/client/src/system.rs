@@ -0,0 +1,35 @@
+use container_codes_shared::{
+    reload::ReloadReport,
+    types::{HealthStatus, SystemInfo},
+};
+use reqwest::Method;
+
+use crate::{Client, Result};
+
+impl Client {
+    pub async fn health(&self) -> Result<HealthStatus> {
+        self.get("api/health").await
+    }
+
+    pub async fn system_info(&self) -> Result<SystemInfo> {
+        self.get("api/system/info").await
+    }
+
+    /// The configuration in effect on the server, secrets redacted.
+    pub async fn get_config(&self) -> Result<serde_json::Value> {
+        self.get("api/config").await
+    }
+
+    /// Asks the server to re-read its config file.
+    pub async fn reload_config(&self) -> Result<ReloadReport> {
+        self.call(Method::POST, "api/config/reload", |r| r).await
+    }
+
+    /// The server's OpenAPI document.
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        let response = self
+            .execute(Method::GET, "api/openapi.json", false, |r| r)
+            .await?;
+        Ok(response.json().await?)
+    }
+}
@@ -1,10 +1,4 @@
 pub mod docker;
 pub mod lifecycle;
 pub mod network;
-pub mod security;
-
-// Placeholder implementations
-pub use docker::*;
-pub use lifecycle::*;
-pub use network::*;
-pub use security::*;
\ No newline at end of file
+pub mod security;
\ No newline at end of file
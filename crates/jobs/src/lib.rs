@@ -1,10 +1,4 @@
 pub mod queue;
 pub mod worker;
 pub mod sandbox;
-pub mod storage;
-
-// Placeholder implementations
-pub use queue::*;
-pub use worker::*;
-pub use sandbox::*;
-pub use storage::*;
\ No newline at end of file
+pub mod storage;
\ No newline at end of file
@@ -1 +1,433 @@
-// Placeholder for load balancer implementation
\ No newline at end of file
+//! Strategies for spreading an upstream's requests across its servers.
+
+use crate::upstream::Server;
+use container_codes_shared::config::{BalancingStrategy, HashKey};
+use hyper::header::{self, HeaderMap};
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Virtual nodes per unit of weight on a `ring_hash` ring.
+const RING_POINTS_PER_WEIGHT: u64 = 100;
+/// Most points on a ring; heavier upstreams get fewer per unit of weight.
+const MAX_RING_POINTS: u64 = 100_000;
+/// Slots in a Maglev lookup table; prime, and much larger than any upstream.
+const MAGLEV_TABLE_SIZE: usize = 65537;
+
+/// What a balancer can see of the request it places.
+pub struct Context<'a> {
+    pub client: IpAddr,
+    pub path: &'a str,
+    pub headers: &'a HeaderMap,
+}
+
+/// Chooses a server for each request. A balancer is built for one list of
+/// servers and is given that same list on every call. Picks go by
+/// `Server::effective_weight`, and servers where it is 0 must not be chosen.
+pub trait Balancer: Send + Sync {
+    /// The index in `servers` of the server that takes `request`.
+    fn pick(&self, servers: &[Arc<Server>], request: &Context) -> Option<usize>;
+}
+
+/// The balancer for `strategy`. `key` applies to the hashing strategies
+/// and defaults to the client IP.
+pub fn new(
+    strategy: BalancingStrategy,
+    key: Option<&HashKey>,
+    servers: &[Arc<Server>],
+) -> Box<dyn Balancer> {
+    let key = key.cloned().unwrap_or(HashKey::Ip);
+    match strategy {
+        BalancingStrategy::RoundRobin => Box::new(RoundRobin::default()),
+        BalancingStrategy::LeastConnections => Box::new(LeastRequests::default()),
+        BalancingStrategy::Random => Box::new(Random),
+        BalancingStrategy::P2cEwma => Box::new(P2cEwma),
+        BalancingStrategy::IpHash => Box::new(RingHash::new(HashKey::Ip, servers)),
+        BalancingStrategy::RingHash => Box::new(RingHash::new(key, servers)),
+        BalancingStrategy::Maglev => Box::new(Maglev::new(key, servers)),
+    }
+}
+
+/// Smooth weighted round robin, as in nginx: every pick raises each
+/// server's current weight by its weight and lowers the winner's by the
+/// total, so heavier servers are interleaved rather than picked in runs.
+#[derive(Default)]
+pub struct RoundRobin {
+    current: Mutex<Vec<i64>>,
+}
+
+impl Balancer for RoundRobin {
+    fn pick(&self, servers: &[Arc<Server>], _: &Context) -> Option<usize> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        current.resize(servers.len(), 0);
+
+        let mut total = 0;
+        let mut best: Option<usize> = None;
+        for (i, server) in servers.iter().enumerate() {
+            let weight = i64::from(server.effective_weight());
+            if weight == 0 {
+                current[i] = 0;
+                continue;
+            }
+            current[i] += weight;
+            total += weight;
+            if best.is_none_or(|best| current[i] > current[best]) {
+                best = Some(i);
+            }
+        }
+        let best = best?;
+        current[best] -= total;
+        Some(best)
+    }
+}
+
+/// Fewest requests in flight per unit of weight. Ties go to the first
+/// server after the previous pick, so an idle upstream still rotates.
+#[derive(Default)]
+pub struct LeastRequests {
+    next: AtomicUsize,
+}
+
+impl Balancer for LeastRequests {
+    fn pick(&self, servers: &[Arc<Server>], _: &Context) -> Option<usize> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut best: Option<(usize, f64)> = None;
+        for k in 0..servers.len() {
+            let i = (start + k) % servers.len();
+            let weight = servers[i].effective_weight();
+            if weight == 0 {
+                continue;
+            }
+            let load = f64::from(servers[i].active() + 1) / f64::from(weight);
+            if best.is_none_or(|(_, least)| load < least) {
+                best = Some((i, load));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+}
+
+/// A server drawn at random in proportion to its weight.
+pub struct Random;
+
+impl Balancer for Random {
+    fn pick(&self, servers: &[Arc<Server>], _: &Context) -> Option<usize> {
+        weighted_random(servers, None)
+    }
+}
+
+/// Power of two choices: two servers drawn by weight, keeping the one with
+/// the lower average response time times requests in flight.
+pub struct P2cEwma;
+
+impl Balancer for P2cEwma {
+    fn pick(&self, servers: &[Arc<Server>], _: &Context) -> Option<usize> {
+        let first = weighted_random(servers, None)?;
+        let Some(second) = weighted_random(servers, Some(first)) else {
+            return Some(first);
+        };
+        // The 1ms floor keeps servers without samples from all costing 0.
+        let cost = |i: usize| {
+            let server = &servers[i];
+            (server.latency() + 1.0) * f64::from(server.active() + 1)
+                / f64::from(server.effective_weight())
+        };
+        Some(if cost(second) < cost(first) {
+            second
+        } else {
+            first
+        })
+    }
+}
+
+/// Consistent hashing on a ring with `RING_POINTS_PER_WEIGHT` points per
+/// unit of weight, scaled down to at most `MAX_RING_POINTS` in all. A request goes to the owner of the first point at or
+/// after its hash, walking on past servers that cannot take it.
+pub struct RingHash {
+    key: HashKey,
+    /// Point hash and server index, by hash.
+    ring: Vec<(u64, usize)>,
+}
+
+impl RingHash {
+    pub fn new(key: HashKey, servers: &[Arc<Server>]) -> Self {
+        let total: u64 = servers
+            .iter()
+            .map(|server| u64::from(server.weight()) * RING_POINTS_PER_WEIGHT)
+            .sum();
+        let scale = (MAX_RING_POINTS as f64 / total.max(1) as f64).min(1.0);
+        let mut ring = Vec::new();
+        for (i, server) in servers.iter().enumerate() {
+            let points = u64::from(server.weight()) * RING_POINTS_PER_WEIGHT;
+            // Every server with a weight keeps at least one point.
+            let points = ((points as f64 * scale) as u64).max(points.min(1));
+            for point in 0..points {
+                ring.push((
+                    hash(format!("{}#{}", server.address(), point).as_bytes()),
+                    i,
+                ));
+            }
+        }
+        ring.sort_unstable();
+        Self { key, ring }
+    }
+}
+
+impl Balancer for RingHash {
+    fn pick(&self, servers: &[Arc<Server>], request: &Context) -> Option<usize> {
+        let hash = request.hash(&self.key);
+        let start = self.ring.partition_point(|(point, _)| *point < hash);
+        (0..self.ring.len())
+            .map(|k| self.ring[(start + k) % self.ring.len()].1)
+            .find(|&i| servers[i].effective_weight() > 0)
+    }
+}
+
+/// Maglev consistent hashing (Eisenbud et al., 2016): each server fills
+/// slots of a lookup table in its own pseudo-random order, taking as many
+/// turns per round as its weight. Lookups are a single index, and losing a
+/// server only moves the keys that were on it. Requests whose slot is
+/// unavailable walk forward through the table.
+pub struct Maglev {
+    key: HashKey,
+    table: Vec<usize>,
+}
+
+impl Maglev {
+    pub fn new(key: HashKey, servers: &[Arc<Server>]) -> Self {
+        let size = MAGLEV_TABLE_SIZE;
+        if servers.iter().all(|server| server.weight() == 0) {
+            return Self { key, table: vec![] };
+        }
+
+        let permutations: Vec<(usize, usize)> = servers
+            .iter()
+            .map(|server| {
+                let offset = hash(server.address().as_bytes()) as usize % size;
+                let skip =
+                    hash(format!("{}#skip", server.address()).as_bytes()) as usize % (size - 1) + 1;
+                (offset, skip)
+            })
+            .collect();
+        let mut next = vec![0; servers.len()];
+        let mut table = vec![usize::MAX; size];
+        let mut filled = 0;
+        loop {
+            for (i, server) in servers.iter().enumerate() {
+                let (offset, skip) = permutations[i];
+                for _ in 0..server.weight() {
+                    let slot = loop {
+                        let slot = (offset + next[i] * skip) % size;
+                        next[i] += 1;
+                        if table[slot] == usize::MAX {
+                            break slot;
+                        }
+                    };
+                    table[slot] = i;
+                    filled += 1;
+                    if filled == size {
+                        return Self { key, table };
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Balancer for Maglev {
+    fn pick(&self, servers: &[Arc<Server>], request: &Context) -> Option<usize> {
+        if self.table.is_empty() {
+            return None;
+        }
+        let start = request.hash(&self.key) as usize % self.table.len();
+        (0..self.table.len())
+            .map(|k| self.table[(start + k) % self.table.len()])
+            .find(|&i| servers[i].effective_weight() > 0)
+    }
+}
+
+impl Context<'_> {
+    /// Hashes the part of the request named by `key`; requests without that
+    /// header or cookie hash on the client IP instead.
+    fn hash(&self, key: &HashKey) -> u64 {
+        let value = match key {
+            HashKey::Ip => None,
+            HashKey::Path => Some(self.path),
+            HashKey::Header(name) => self
+                .headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok()),
+            HashKey::Cookie(name) => cookie(self.headers, name),
+        };
+        match value {
+            Some(value) => hash(value.as_bytes()),
+            None => hash(self.client.to_canonical().to_string().as_bytes()),
+        }
+    }
+}
+
+/// The value of cookie `name` in the request's `Cookie` headers.
+pub(crate) fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// A server drawn in proportion to weight, other than `except`.
+fn weighted_random(servers: &[Arc<Server>], except: Option<usize>) -> Option<usize> {
+    let weight = |i: usize| {
+        if Some(i) == except {
+            0
+        } else {
+            u64::from(servers[i].effective_weight())
+        }
+    };
+    let total: u64 = (0..servers.len()).map(weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut ticket = random() % total;
+    (0..servers.len()).find(|&i| {
+        let weight = weight(i);
+        if ticket < weight {
+            return true;
+        }
+        ticket -= weight;
+        false
+    })
+}
+
+/// A fast thread-local xorshift64* generator; not for anything secret.
+pub(crate) fn random() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(std::thread::current().id()) | 1);
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+/// FNV-1a followed by a splitmix64 finaliser: stable across builds and
+/// processes, so replicas agree on where a key belongs.
+pub(crate) fn hash(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h ^= u64::from(b);
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servers(weights: &[u32]) -> Vec<Arc<Server>> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| {
+                Arc::new(Server::new(&format!("10.0.0.{}:80", i + 1), *weight).unwrap())
+            })
+            .collect()
+    }
+
+    fn request<'a>(headers: &'a HeaderMap, path: &'a str) -> Context<'a> {
+        Context {
+            client: "192.0.2.1".parse().unwrap(),
+            path,
+            headers,
+        }
+    }
+
+    #[test]
+    fn test_smooth_weighted_round_robin() {
+        let servers = servers(&[5, 1, 1]);
+        let balancer = RoundRobin::default();
+        let headers = HeaderMap::new();
+        let picks: Vec<usize> = (0..7)
+            .map(|_| balancer.pick(&servers, &request(&headers, "/")).unwrap())
+            .collect();
+        assert_eq!(picks, [0, 0, 1, 0, 2, 0, 0]);
+    }
+
+    #[test]
+    fn test_least_requests_prefers_idle_servers() {
+        let servers = servers(&[1, 1, 2]);
+        let balancer = LeastRequests::default();
+        let headers = HeaderMap::new();
+        let busy = [
+            servers[0].start(),
+            servers[0].start(),
+            servers[2].start(),
+            servers[2].start(),
+        ];
+        assert_eq!(balancer.pick(&servers, &request(&headers, "/")), Some(1));
+        // Per unit of weight, two in flight on server 2 beat one on server 1.
+        let _more = servers[1].start();
+        assert_eq!(balancer.pick(&servers, &request(&headers, "/")), Some(2));
+        drop(busy);
+        assert_eq!(balancer.pick(&servers, &request(&headers, "/")), Some(2));
+    }
+
+    #[test]
+    fn test_consistent_hashing_is_sticky_and_weighted() {
+        let servers = servers(&[1, 1, 2]);
+        for balancer in [
+            Box::new(RingHash::new(HashKey::Cookie("sid".into()), &servers)) as Box<dyn Balancer>,
+            Box::new(Maglev::new(HashKey::Cookie("sid".into()), &servers)),
+        ] {
+            let mut counts = [0; 3];
+            for n in 0..4000 {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    header::COOKIE,
+                    format!("theme=dark; sid={}", n).parse().unwrap(),
+                );
+                let pick = balancer.pick(&servers, &request(&headers, "/")).unwrap();
+                assert_eq!(
+                    balancer.pick(&servers, &request(&headers, "/other")),
+                    Some(pick)
+                );
+                counts[pick] += 1;
+            }
+            // The weight-2 server takes about half.
+            assert!((1700..2300).contains(&counts[2]), "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_heavy_weights_stay_bounded() {
+        let servers = servers(&[u32::MAX, 1, 10_000]);
+        assert_eq!(servers[0].effective_weight(), u32::MAX);
+
+        let ring = RingHash::new(HashKey::Ip, &servers);
+        assert!(ring.ring.len() as u64 <= MAX_RING_POINTS + 2);
+        for i in 0..3 {
+            assert!(ring.ring.iter().any(|&(_, owner)| owner == i));
+        }
+        let headers = HeaderMap::new();
+        assert!(ring.pick(&servers, &request(&headers, "/")).is_some());
+    }
+}
@@ -0,0 +1,176 @@
+//! Per-upstream circuit breaking.
+
+use container_codes_shared::config::CircuitBreakerConfig;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Counts an upstream's attempts over a fixed window and stops admitting
+/// them for `cooldown` once too many fail. After that one trial attempt is
+/// let through: if it succeeds the breaker closes, otherwise it opens again.
+pub struct Breaker {
+    upstream: String,
+    error_rate: f64,
+    min_requests: u32,
+    window: Duration,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+enum State {
+    Closed {
+        started: Instant,
+        requests: u32,
+        errors: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// Cooled down; `trial` is set while the trial attempt is in flight.
+    HalfOpen {
+        trial: bool,
+    },
+}
+
+/// Leave to make one attempt. Dropping it without a verdict frees the
+/// trial slot of a half-open breaker without closing or reopening it.
+pub struct Permit<'a> {
+    breaker: &'a Breaker,
+    recorded: bool,
+}
+
+impl Breaker {
+    pub fn new(upstream: &str, config: &CircuitBreakerConfig) -> Self {
+        Self {
+            upstream: upstream.to_string(),
+            error_rate: config.error_rate,
+            min_requests: config.min_requests,
+            window: config.window.into(),
+            cooldown: config.cooldown.into(),
+            state: Mutex::new(State::Closed {
+                started: Instant::now(),
+                requests: 0,
+                errors: 0,
+            }),
+        }
+    }
+
+    /// `None` while the breaker is open, or half-open with its trial in flight.
+    pub fn admit(&self) -> Option<Permit<'_>> {
+        let mut state = self.lock();
+        match &mut *state {
+            State::Closed { .. } => {}
+            State::Open { until } if Instant::now() < *until => return None,
+            State::Open { .. } => *state = State::HalfOpen { trial: true },
+            State::HalfOpen { trial: true } => return None,
+            State::HalfOpen { trial } => *trial = true,
+        }
+        Some(Permit {
+            breaker: self,
+            recorded: false,
+        })
+    }
+
+    fn record(&self, ok: bool) {
+        let now = Instant::now();
+        let mut state = self.lock();
+        match &mut *state {
+            State::Closed {
+                started,
+                requests,
+                errors,
+            } => {
+                if now.duration_since(*started) > self.window {
+                    (*started, *requests, *errors) = (now, 0, 0);
+                }
+                *requests += 1;
+                *errors += u32::from(!ok);
+                let rate = f64::from(*errors) / f64::from(*requests);
+                if !ok && *requests >= self.min_requests && rate >= self.error_rate {
+                    warn!(upstream = %self.upstream, "Circuit opened: {} of {} requests failed", errors, requests);
+                    *state = State::Open {
+                        until: now + self.cooldown,
+                    };
+                }
+            }
+            // Attempts admitted before the breaker opened.
+            State::Open { .. } => {}
+            State::HalfOpen { .. } if ok => {
+                info!(upstream = %self.upstream, "Circuit closed after a successful trial request");
+                *state = State::Closed {
+                    started: now,
+                    requests: 0,
+                    errors: 0,
+                };
+            }
+            State::HalfOpen { .. } => {
+                warn!(upstream = %self.upstream, "Circuit reopened after a failed trial request");
+                *state = State::Open {
+                    until: now + self.cooldown,
+                };
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Permit<'_> {
+    /// Records how the attempt went: `ok` unless it got no response or a 5xx.
+    pub fn record(mut self, ok: bool) {
+        self.recorded = true;
+        self.breaker.record(ok);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.recorded {
+            return;
+        }
+        if let State::HalfOpen { trial } = &mut *self.breaker.lock() {
+            *trial = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_on_error_rate_and_half_opens_after_cooldown() {
+        let breaker = Breaker::new(
+            "api",
+            &CircuitBreakerConfig {
+                error_rate: 0.5,
+                min_requests: 4,
+                window: "1m".parse().unwrap(),
+                cooldown: "50ms".parse().unwrap(),
+            },
+        );
+        for ok in [false, true, false] {
+            breaker.admit().unwrap().record(ok);
+        }
+        // Too few requests to judge yet.
+        assert!(breaker.admit().is_some());
+        breaker.admit().unwrap().record(false);
+        assert!(breaker.admit().is_none());
+
+        std::thread::sleep(Duration::from_millis(60));
+        let trial = breaker.admit().unwrap();
+        assert!(breaker.admit().is_none());
+        drop(trial);
+        let trial = breaker.admit().unwrap();
+        trial.record(false);
+        assert!(breaker.admit().is_none());
+
+        std::thread::sleep(Duration::from_millis(60));
+        breaker.admit().unwrap().record(true);
+        assert!(breaker.admit().is_some() && breaker.admit().is_some());
+    }
+}
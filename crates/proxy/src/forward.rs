@@ -0,0 +1,238 @@
+//! Header rules for passing a request or response across the proxy.
+
+use container_codes_shared::request_id::{RequestId, REQUEST_ID_HEADER};
+use hyper::header::{self, AsHeaderName, HeaderMap, HeaderName, HeaderValue};
+use std::net::IpAddr;
+
+/// Headers that describe one connection rather than the message (RFC 9110
+/// section 7.6.1), besides those named in `Connection`.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// Removes hop-by-hop headers. `TE: trailers` is kept since h2 upstreams
+/// (gRPC in particular) need it end to end.
+pub fn remove_hop_by_hop(headers: &mut HeaderMap) {
+    for name in connection_options(headers) {
+        headers.remove(name);
+    }
+
+    let trailers = headers
+        .get(header::TE)
+        .is_some_and(|te| te.as_bytes().eq_ignore_ascii_case(b"trailers"));
+    for name in HOP_BY_HOP {
+        headers.remove(*name);
+    }
+    if trailers {
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+    }
+}
+
+/// Whether an HTTP/1.1 message asks to switch protocols: `Connection`
+/// lists `upgrade` and an `Upgrade` header names the protocol.
+pub fn is_upgrade(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::UPGRADE) && connection_options(headers).contains(&header::UPGRADE)
+}
+
+/// Like `remove_hop_by_hop`, but keeps what an upgrade needs end to end:
+/// `Upgrade`, `Connection: upgrade` and other headers `Connection` lists,
+/// such as `HTTP2-Settings` for h2c.
+pub fn remove_hop_by_hop_for_upgrade(headers: &mut HeaderMap) {
+    let upgrade = headers.get(header::UPGRADE).cloned();
+    let kept: Vec<(HeaderName, Vec<HeaderValue>)> = connection_options(headers)
+        .into_iter()
+        .filter(|name| !HOP_BY_HOP.contains(&name.as_str()))
+        .map(|name| {
+            let values = headers.get_all(&name).iter().cloned().collect();
+            (name, values)
+        })
+        .collect();
+
+    remove_hop_by_hop(headers);
+    let mut connection = vec!["upgrade"];
+    for (name, values) in &kept {
+        for value in values {
+            headers.append(name, value.clone());
+        }
+        connection.push(name.as_str());
+    }
+    set(headers, header::CONNECTION, &connection.join(", "));
+    if let Some(upgrade) = upgrade {
+        headers.insert(header::UPGRADE, upgrade);
+    }
+}
+
+/// Records this hop: `client` is appended to `X-Forwarded-For` and
+/// `Forwarded`, while `X-Forwarded-Proto` and `X-Forwarded-Host` describe
+/// the request as the proxy received it.
+pub fn add_forwarded(headers: &mut HeaderMap, client: IpAddr, proto: &str, host: Option<&str>) {
+    let client = client.to_canonical();
+
+    let chain = join(headers, X_FORWARDED_FOR);
+    let value = match chain {
+        Some(chain) => format!("{}, {}", chain, client),
+        None => client.to_string(),
+    };
+    set(headers, HeaderName::from_static(X_FORWARDED_FOR), &value);
+    set(headers, HeaderName::from_static(X_FORWARDED_PROTO), proto);
+    match host {
+        Some(host) => set(headers, HeaderName::from_static(X_FORWARDED_HOST), host),
+        None => {
+            headers.remove(X_FORWARDED_HOST);
+        }
+    }
+
+    let node = match client {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("\"[{}]\"", ip),
+    };
+    let mut element = format!("for={}", node);
+    if let Some(host) = host {
+        element.push_str(";host=");
+        element.push_str(&quote(host));
+    }
+    element.push_str(";proto=");
+    element.push_str(proto);
+    let value = match join(headers, header::FORWARDED) {
+        Some(previous) => format!("{}, {}", previous, element),
+        None => element,
+    };
+    set(headers, header::FORWARDED, &value);
+}
+
+/// Passes the client's `x-request-id` on, replacing a missing or invalid one
+/// with a generated id.
+pub fn add_request_id(headers: &mut HeaderMap) -> RequestId {
+    let request_id = RequestId::from_header(headers.get(REQUEST_ID_HEADER));
+    headers.insert(REQUEST_ID_HEADER, request_id.header_value());
+    request_id
+}
+
+/// The header names listed in `Connection`, lowercased.
+fn connection_options(headers: &HeaderMap) -> Vec<HeaderName> {
+    headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect()
+}
+
+/// All values of a list header as one comma-separated string.
+fn join(headers: &HeaderMap, name: impl AsHeaderName) -> Option<String> {
+    let values: Vec<&str> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// Replaces `name`; values that are not valid header text are dropped.
+fn set(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    match HeaderValue::from_str(value) {
+        Ok(value) => {
+            headers.insert(name, value);
+        }
+        Err(_) => {
+            headers.remove(name);
+        }
+    }
+}
+
+/// A `Forwarded` parameter value: a token as-is, anything else quoted.
+fn quote(value: &str) -> String {
+    let token = !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+    if token {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hop_by_hop_and_forwarded_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONNECTION,
+            HeaderValue::from_static("keep-alive, X-Session"),
+        );
+        headers.insert("x-session", HeaderValue::from_static("abc"));
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        headers.append(X_FORWARDED_FOR, HeaderValue::from_static("203.0.113.7"));
+        headers.insert(
+            header::FORWARDED,
+            HeaderValue::from_static("for=203.0.113.7"),
+        );
+
+        remove_hop_by_hop(&mut headers);
+        add_forwarded(
+            &mut headers,
+            "::1".parse().unwrap(),
+            "http",
+            Some("example.com:8080"),
+        );
+
+        assert!(headers.get("x-session").is_none());
+        assert!(headers.get(header::CONNECTION).is_none());
+        assert!(headers.get(header::UPGRADE).is_none());
+        assert_eq!(headers[header::TE], "trailers");
+        assert_eq!(headers[header::ACCEPT], "*/*");
+        assert_eq!(headers[X_FORWARDED_FOR], "203.0.113.7, ::1");
+        assert_eq!(headers[X_FORWARDED_PROTO], "http");
+        assert_eq!(headers[X_FORWARDED_HOST], "example.com:8080");
+        assert_eq!(
+            headers[header::FORWARDED],
+            "for=203.0.113.7, for=\"[::1]\";host=\"example.com:8080\";proto=http"
+        );
+
+        add_forwarded(
+            &mut headers,
+            "::ffff:10.0.0.1".parse().unwrap(),
+            "https",
+            None,
+        );
+        assert_eq!(headers[X_FORWARDED_FOR], "203.0.113.7, ::1, 10.0.0.1");
+        assert!(headers.get(X_FORWARDED_HOST).is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONNECTION,
+            HeaderValue::from_static("Upgrade, HTTP2-Settings, keep-alive"),
+        );
+        headers.insert(header::UPGRADE, HeaderValue::from_static("h2c"));
+        headers.insert(
+            "http2-settings",
+            HeaderValue::from_static("AAMAAABkAAQAAP__"),
+        );
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        assert!(is_upgrade(&headers));
+        remove_hop_by_hop_for_upgrade(&mut headers);
+        assert_eq!(headers[header::CONNECTION], "upgrade, http2-settings");
+        assert_eq!(headers[header::UPGRADE], "h2c");
+        assert_eq!(headers["http2-settings"], "AAMAAABkAAQAAP__");
+        assert!(headers.get("keep-alive").is_none());
+    }
+}
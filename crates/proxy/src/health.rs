@@ -1 +1,453 @@
-// Placeholder for health check implementation
\ No newline at end of file
+//! Active health checks and passive outlier ejection for upstream servers.
+//!
+//! A server is out of rotation while `proxy.health` probes fail, after too
+//! many failed requests in a row, or while drained through the API; either
+//! way it comes back gradually when `slow_start` is set.
+
+use crate::{
+    router::Router,
+    server::chain,
+    upstream::{Server, Upstream},
+};
+use container_codes_shared::{
+    config::{HealthCheckType, HealthConfig, StatusRange},
+    Error, Result,
+};
+use http_body_util::{BodyExt, Empty};
+use hyper::{header, http::uri::PathAndQuery, Request};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, sync::broadcast, task::JoinSet, time::MissedTickBehavior};
+use tracing::{info, warn};
+
+/// Health events kept for slow subscribers.
+const EVENT_BUFFER: usize = 64;
+/// Most of a check response body that is searched for `expected_body`.
+const MAX_CHECK_BODY: usize = 64 * 1024;
+/// Share of its weight a server gets at the start of slow start.
+const SLOW_START_FLOOR: f64 = 0.1;
+
+/// Whether a server takes requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Status {
+    #[default]
+    Healthy,
+    /// Failing active health checks.
+    Unhealthy,
+    /// Out for `fail_timeout` after `max_fails` failed requests in a row.
+    Ejected,
+    /// Taken out of rotation through the API; requests in flight finish.
+    Draining,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Unhealthy => "unhealthy",
+            Self::Ejected => "ejected",
+            Self::Draining => "draining",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A server changing status.
+#[derive(Debug, Clone)]
+pub struct HealthEvent {
+    pub upstream: String,
+    pub address: String,
+    pub status: Status,
+    pub reason: String,
+}
+
+/// Where health events are published; clones share subscribers.
+#[derive(Clone)]
+pub struct Events(broadcast::Sender<HealthEvent>);
+
+impl Default for Events {
+    fn default() -> Self {
+        Self(broadcast::channel(EVENT_BUFFER).0)
+    }
+}
+
+impl Events {
+    pub fn subscribe(&self) -> broadcast::Receiver<HealthEvent> {
+        self.0.subscribe()
+    }
+
+    pub(crate) fn publish(&self, event: HealthEvent) {
+        match event.status {
+            Status::Healthy => info!(upstream = %event.upstream, server = %event.address,
+                "Upstream server is healthy: {}", event.reason),
+            status => warn!(upstream = %event.upstream, server = %event.address,
+                "Upstream server is {}: {}", status, event.reason),
+        }
+        // Nobody listening is fine.
+        let _ = self.0.send(event);
+    }
+}
+
+/// A status change and what caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub status: Status,
+    pub reason: String,
+}
+
+/// One server's health. It survives reloads that keep the server.
+#[derive(Default)]
+pub struct Health {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The status last reported.
+    reported: Status,
+    /// When the server last came back, for slow start.
+    recovered: Option<Instant>,
+    /// Set once `unhealthy_threshold` checks fail in a row, cleared after
+    /// `healthy_threshold` pass.
+    down: bool,
+    passes: u32,
+    fails: u32,
+    /// Failed requests in a row, and when the first of them happened.
+    failures: u32,
+    first_failure: Option<Instant>,
+    ejected_until: Option<Instant>,
+    /// Set while drained, whatever the checks say.
+    draining: bool,
+}
+
+impl Health {
+    /// The server's status, and its share of its weight in `0.0..=1.0`.
+    pub fn current(&self, slow_start: Duration, now: Instant) -> (Status, f64, Option<Change>) {
+        let mut state = self.lock();
+        let change = state.settle(now, || "ejection ended".to_string());
+        let status = state.reported;
+        let share = match (status, state.recovered) {
+            (Status::Healthy, Some(recovered)) if !slow_start.is_zero() => {
+                let ramp = now.saturating_duration_since(recovered).as_secs_f64()
+                    / slow_start.as_secs_f64();
+                ramp.clamp(SLOW_START_FLOOR, 1.0)
+            }
+            (Status::Healthy, _) => 1.0,
+            _ => 0.0,
+        };
+        (status, share, change)
+    }
+
+    /// Records a proxied request. `max_fails` failures in a row, none more
+    /// than `fail_timeout` after the first, eject the server for `fail_timeout`.
+    pub fn responded(
+        &self,
+        ok: bool,
+        max_fails: u32,
+        fail_timeout: Duration,
+        now: Instant,
+    ) -> Option<Change> {
+        let mut state = self.lock();
+        if ok {
+            state.failures = 0;
+            state.first_failure = None;
+            return None;
+        }
+        match state.first_failure {
+            Some(first) if now.saturating_duration_since(first) <= fail_timeout => {
+                state.failures += 1
+            }
+            _ => {
+                state.first_failure = Some(now);
+                state.failures = 1;
+            }
+        }
+        if max_fails == 0 || state.failures < max_fails {
+            return None;
+        }
+        let failures = state.failures;
+        state.failures = 0;
+        state.first_failure = None;
+        state.ejected_until = Some(now + fail_timeout);
+        state.settle(now, || format!("{} failed requests in a row", failures))
+    }
+
+    /// Records the result of an active check.
+    pub fn probed(
+        &self,
+        result: &std::result::Result<(), String>,
+        checks: &Checks,
+        now: Instant,
+    ) -> Option<Change> {
+        let mut state = self.lock();
+        match result {
+            Ok(()) => {
+                state.passes += 1;
+                state.fails = 0;
+                if state.down && state.passes >= checks.healthy_threshold {
+                    state.down = false;
+                }
+                let passes = state.passes;
+                state.settle(now, || format!("passed {} health checks in a row", passes))
+            }
+            Err(e) => {
+                state.fails += 1;
+                state.passes = 0;
+                if !state.down && state.fails >= checks.unhealthy_threshold {
+                    state.down = true;
+                }
+                state.settle(now, || format!("health check failed: {}", e))
+            }
+        }
+    }
+
+    /// Takes the server out of rotation until undrained.
+    pub fn drain(&self, draining: bool, now: Instant) -> Option<Change> {
+        let mut state = self.lock();
+        state.draining = draining;
+        state.settle(now, || {
+            if draining { "drained" } else { "undrained" }.to_string()
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl State {
+    /// Brings `reported` up to date, returning the change if there was one.
+    fn settle(&mut self, now: Instant, reason: impl FnOnce() -> String) -> Option<Change> {
+        if self.ejected_until.is_some_and(|until| until <= now) {
+            self.ejected_until = None;
+        }
+        let status = if self.draining {
+            Status::Draining
+        } else if self.down {
+            Status::Unhealthy
+        } else if self.ejected_until.is_some() {
+            Status::Ejected
+        } else {
+            Status::Healthy
+        };
+        if status == self.reported {
+            return None;
+        }
+        self.reported = status;
+        if status == Status::Healthy {
+            self.recovered = Some(now);
+        }
+        Some(Change {
+            status,
+            reason: reason(),
+        })
+    }
+}
+
+/// Active checks as configured by `proxy.health`.
+pub struct Checks {
+    kind: HealthCheckType,
+    path: PathAndQuery,
+    interval: Duration,
+    timeout: Duration,
+    healthy_threshold: u32,
+    unhealthy_threshold: u32,
+    expected_status: Vec<StatusRange>,
+    expected_body: Option<String>,
+}
+
+impl Checks {
+    /// `None` unless `config.enabled` is set.
+    pub fn new(config: &HealthConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let path = config
+            .check_path
+            .parse()
+            .map_err(|_| Error::config_invalid("proxy.health.check_path", &config.check_path))?;
+        Ok(Some(Self {
+            kind: config.check_type.unwrap_or(HealthCheckType::Http),
+            path,
+            interval: config.interval.into(),
+            timeout: config.timeout.into(),
+            healthy_threshold: config.healthy_threshold,
+            unhealthy_threshold: config.unhealthy_threshold,
+            expected_status: config.expected_status.clone().unwrap_or_else(|| {
+                vec![
+                    StatusRange {
+                        first: 200,
+                        last: 299,
+                    },
+                    StatusRange {
+                        first: 300,
+                        last: 399,
+                    },
+                ]
+            }),
+            expected_body: config.expected_body.clone(),
+        }))
+    }
+
+    /// Checks every server of the upstreams in `router()` each interval.
+    /// Runs until dropped.
+    pub async fn run(self: Arc<Self>, router: impl Fn() -> Arc<Router>) {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let mut probes = JoinSet::new();
+            for upstream in router().upstreams() {
+                for server in upstream.servers() {
+                    let (checks, upstream, server) =
+                        (self.clone(), upstream.clone(), server.clone());
+                    probes.spawn(async move {
+                        let result = checks.probe(&upstream, &server).await;
+                        server.probed(&result, &checks);
+                    });
+                }
+            }
+            while probes.join_next().await.is_some() {}
+        }
+    }
+
+    async fn probe(&self, upstream: &Upstream, server: &Server) -> std::result::Result<(), String> {
+        match tokio::time::timeout(self.timeout, self.attempt(upstream, server)).await {
+            Ok(result) => result,
+            Err(_) => Err(format!("no answer within {:?}", self.timeout)),
+        }
+    }
+
+    async fn attempt(
+        &self,
+        upstream: &Upstream,
+        server: &Server,
+    ) -> std::result::Result<(), String> {
+        if self.kind == HealthCheckType::Tcp {
+            return TcpStream::connect(server.address())
+                .await
+                .map(drop)
+                .map_err(|e| e.to_string());
+        }
+
+        let request = Request::get(self.path.as_str())
+            .header(header::HOST, server.address())
+            .header(header::USER_AGENT, "container-codes-health-check")
+            .body(Empty::new().map_err(|e| match e {}).boxed())
+            .map_err(|e| e.to_string())?;
+        let response = upstream
+            .send(server, request, None)
+            .await
+            .map_err(|e| chain(&e))?;
+        let status = response.status().as_u16();
+        if !self
+            .expected_status
+            .iter()
+            .any(|range| range.contains(status))
+        {
+            return Err(format!("unexpected status {}", status));
+        }
+        let Some(expected) = &self.expected_body else {
+            return Ok(());
+        };
+        // Only the start of a long body is searched.
+        let mut body = response.into_body();
+        let mut seen = Vec::new();
+        while seen.len() < MAX_CHECK_BODY {
+            match body.frame().await {
+                Some(frame) => {
+                    if let Ok(data) = frame.map_err(|e| e.to_string())?.into_data() {
+                        seen.extend_from_slice(&data);
+                    }
+                }
+                None => break,
+            }
+        }
+        if String::from_utf8_lossy(&seen).contains(expected.as_str()) {
+            Ok(())
+        } else {
+            Err(format!("body does not contain {:?}", expected))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ejection_checks_and_slow_start() {
+        let health = Health::default();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let slow_start = Duration::from_secs(10);
+        let timeout = Duration::from_secs(30);
+
+        // Failures further apart than fail_timeout do not add up.
+        assert_eq!(health.responded(false, 2, timeout, at(0)), None);
+        assert_eq!(health.responded(false, 2, timeout, at(31)), None);
+        let change = health.responded(false, 2, timeout, at(32)).unwrap();
+        assert_eq!(change.status, Status::Ejected);
+        assert_eq!(health.current(slow_start, at(40)).0, Status::Ejected);
+
+        let (status, share, change) = health.current(slow_start, at(62));
+        assert_eq!((status, share), (Status::Healthy, SLOW_START_FLOOR));
+        assert_eq!(change.unwrap().reason, "ejection ended");
+        assert_eq!(health.current(slow_start, at(67)).1, 0.5);
+        assert_eq!(
+            health.current(slow_start, at(80)),
+            (Status::Healthy, 1.0, None)
+        );
+
+        let checks = Checks::new(&HealthConfig {
+            enabled: true,
+            interval: "10s".parse().unwrap(),
+            timeout: "1s".parse().unwrap(),
+            healthy_threshold: 2,
+            unhealthy_threshold: 2,
+            check_path: "/health".into(),
+            check_type: None,
+            expected_status: None,
+            expected_body: None,
+            slow_start: None,
+        })
+        .unwrap()
+        .unwrap();
+        let fail = Err("unexpected status 500".to_string());
+        assert_eq!(health.probed(&fail, &checks, at(90)), None);
+        assert_eq!(
+            health.probed(&fail, &checks, at(100)).unwrap().status,
+            Status::Unhealthy
+        );
+        assert_eq!(health.probed(&Ok(()), &checks, at(110)), None);
+        let change = health.probed(&Ok(()), &checks, at(120)).unwrap();
+        assert_eq!(
+            change,
+            Change {
+                status: Status::Healthy,
+                reason: "passed 2 health checks in a row".into()
+            }
+        );
+
+        // Draining outranks the checks and lasts until undrained.
+        assert_eq!(
+            health.drain(true, at(130)).unwrap().status,
+            Status::Draining
+        );
+        assert_eq!(health.probed(&Ok(()), &checks, at(140)), None);
+        assert_eq!(
+            health.current(slow_start, at(150)),
+            (Status::Draining, 0.0, None)
+        );
+        assert_eq!(health.drain(false, at(160)).unwrap().reason, "undrained");
+        assert_eq!(health.current(slow_start, at(160)).1, SLOW_START_FLOOR);
+    }
+}
@@ -1,8 +1,53 @@
+//! Reverse proxy driven by the `[proxy]` config section.
+
 pub mod balancer;
+mod breaker;
+mod forward;
 pub mod health;
 pub mod middleware;
+mod retry;
+mod router;
+mod server;
+mod stats;
+mod upgrade;
+mod upstream;
 
 // Placeholder implementations
-pub use balancer::*;
-pub use health::*;
-pub use middleware::*;
\ No newline at end of file
+pub use health::{HealthEvent, Status};
+#[allow(unused_imports)]
+pub use middleware::*;
+pub use server::Proxy;
+pub use upstream::Server;
+
+#[cfg(test)]
+mod tests {
+    use container_codes_shared::config::{Config, ProxyConfig};
+
+    /// A proxy config with `web` and `api` upstreams, plus `extra` TOML.
+    pub fn config(extra: &str) -> ProxyConfig {
+        let source = format!(
+            r#"
+[proxy]
+enabled = true
+bind_address = "127.0.0.1:0"
+https_redirect = false
+ssl = {{ enabled = false, bind_address = "0.0.0.0:443", cert_dir = "", key_dir = "" }}
+balancing = {{ strategy = "round_robin", session_affinity = false, session_cookie = "SID" }}
+health = {{ enabled = false, interval = "30s", timeout = "5s", healthy_threshold = 2, unhealthy_threshold = 3, check_path = "/health" }}
+middleware = {{ rate_limit_enabled = false, rate_limit_requests = 0, rate_limit_window = "1m", rate_limit_key = "ip", compression_enabled = false, compression_level = 6, compression_types = [] }}
+
+[[proxy.upstreams]]
+name = "web"
+strategy = "round_robin"
+servers = [{{ address = "127.0.0.1:8001", weight = 1, max_fails = 3, fail_timeout = "30s" }}]
+
+[[proxy.upstreams]]
+name = "api"
+strategy = "round_robin"
+servers = [{{ address = "127.0.0.1:3001", weight = 1, max_fails = 3, fail_timeout = "30s" }}]
+{}"#,
+            extra
+        );
+        Config::parse(&source).unwrap().proxy.unwrap()
+    }
+}
@@ -1 +1 @@
-// Placeholder for proxy middleware implementation
\ No newline at end of file
+// Placeholder for proxy middleware implementation
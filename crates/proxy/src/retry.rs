@@ -0,0 +1,199 @@
+//! Per-route timeouts and retries, and the per-upstream retry budget.
+
+use crate::{balancer, upstream::Body};
+use bytes::Bytes;
+use container_codes_shared::config::{RetryCondition, RouteConfig};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, Method};
+use std::{sync::Mutex, time::Duration};
+
+/// Largest request body buffered so that it can be sent again.
+const MAX_REPLAY_BODY: u64 = 64 * 1024;
+/// Retry tokens an upstream earns per request; retries cost one each, so
+/// they stay around a fifth of its traffic.
+const BUDGET_RATIO: f64 = 0.2;
+/// Tokens an upstream starts with, so that a quiet one can still retry.
+const BUDGET_RESERVE: f64 = 10.0;
+const BUDGET_MAX: f64 = 100.0;
+/// Backoff before the first retry, doubling for each one after.
+const BACKOFF_BASE: Duration = Duration::from_millis(25);
+const BACKOFF_MAX: Duration = Duration::from_millis(250);
+
+/// How one attempt went wrong, in the terms of `retry_on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// No connection could be opened, so the server never saw the request.
+    Connect,
+    /// The connection failed after the request was sent.
+    Reset,
+    /// `header_timeout` or `timeout` ran out.
+    Timeout,
+    /// The server answered 502, 503 or 504.
+    Status(u16),
+}
+
+/// A route's timeouts and retry rules.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub header_timeout: Option<Duration>,
+    pub retries: u32,
+    retry_on: Vec<RetryCondition>,
+}
+
+impl Policy {
+    pub fn new(config: &RouteConfig) -> Self {
+        Self {
+            timeout: config.timeout.map(Into::into),
+            connect_timeout: config.connect_timeout.map(Into::into),
+            header_timeout: config.header_timeout.map(Into::into),
+            retries: config.retries.unwrap_or(0),
+            retry_on: config.retry_on.clone().unwrap_or_else(|| {
+                vec![
+                    RetryCondition::ConnectError,
+                    RetryCondition::Reset,
+                    RetryCondition::BadGateway,
+                    RetryCondition::ServiceUnavailable,
+                    RetryCondition::GatewayTimeout,
+                ]
+            }),
+        }
+    }
+
+    /// Whether `failure` may be retried for a `method` request. Requests
+    /// that may have reached the server are only retried when idempotent.
+    pub fn retries_on(&self, failure: Failure, method: &Method) -> bool {
+        let condition = match failure {
+            Failure::Connect => RetryCondition::ConnectError,
+            Failure::Reset => RetryCondition::Reset,
+            Failure::Timeout | Failure::Status(504) => RetryCondition::GatewayTimeout,
+            Failure::Status(502) => RetryCondition::BadGateway,
+            Failure::Status(503) => RetryCondition::ServiceUnavailable,
+            Failure::Status(_) => return false,
+        };
+        self.retry_on.contains(&condition) && (failure == Failure::Connect || idempotent(method))
+    }
+}
+
+/// Whether `status` is a response a retry could replace.
+pub fn retryable_status(status: u16) -> Option<Failure> {
+    matches!(status, 502..=504).then_some(Failure::Status(status))
+}
+
+/// Idempotent methods per RFC 9110 section 9.2.2.
+fn idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+/// Sleep before retry number `retry` (from 1): random, up to an
+/// exponentially growing cap.
+pub fn backoff(retry: u32) -> Duration {
+    let cap = BACKOFF_BASE
+        .saturating_mul(1 << retry.saturating_sub(1).min(8))
+        .min(BACKOFF_MAX);
+    cap.mul_f64((balancer::random() % 1000) as f64 / 1000.0)
+}
+
+/// A request body that can be sent more than once when it is small enough
+/// to buffer.
+pub enum Replay {
+    Once(Option<Incoming>),
+    Buffered(Bytes),
+}
+
+impl Replay {
+    /// Buffers `body` when `retry` is set and its length is known to fit.
+    pub async fn new(body: Incoming, retry: bool) -> Result<Self, hyper::Error> {
+        use hyper::body::Body as _;
+        let fits = body
+            .size_hint()
+            .upper()
+            .is_some_and(|upper| upper <= MAX_REPLAY_BODY);
+        if !retry || !fits {
+            return Ok(Self::Once(Some(body)));
+        }
+        Ok(Self::Buffered(body.collect().await?.to_bytes()))
+    }
+
+    /// The body for the next attempt; `None` once a streamed body is used up.
+    pub fn next(&mut self) -> Option<Body> {
+        match self {
+            Self::Once(body) => body.take().map(|body| body.boxed()),
+            Self::Buffered(bytes) => Some(Full::new(bytes.clone()).map_err(|e| match e {}).boxed()),
+        }
+    }
+
+    pub fn replayable(&self) -> bool {
+        matches!(self, Self::Buffered(_))
+    }
+}
+
+/// Limits an upstream's retries to a share of its requests.
+pub struct Budget {
+    tokens: Mutex<f64>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            tokens: Mutex::new(BUDGET_RESERVE),
+        }
+    }
+}
+
+impl Budget {
+    /// Credits a first attempt.
+    pub fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        *tokens = (*tokens + BUDGET_RATIO).min(BUDGET_MAX);
+    }
+
+    /// Takes a token for a retry; false when there are none left.
+    pub fn withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_rules_and_budget() {
+        let mut route: RouteConfig = toml::from_str(
+            r#"
+path = "/"
+method = "ANY"
+upstream = "api"
+retries = 2
+retry_on = ["connect_error", "503"]
+"#,
+        )
+        .unwrap();
+        let policy = Policy::new(&route);
+        assert!(policy.retries_on(Failure::Connect, &Method::POST));
+        assert!(policy.retries_on(Failure::Status(503), &Method::GET));
+        assert!(!policy.retries_on(Failure::Status(503), &Method::POST));
+        assert!(!policy.retries_on(Failure::Status(502), &Method::GET));
+        assert!(!policy.retries_on(Failure::Timeout, &Method::GET));
+        route.retry_on = None;
+        assert!(Policy::new(&route).retries_on(Failure::Timeout, &Method::PUT));
+
+        let budget = Budget::default();
+        assert_eq!((0..20).filter(|_| budget.withdraw()).count(), 10);
+        for _ in 0..11 {
+            budget.deposit();
+        }
+        assert_eq!((0..20).filter(|_| budget.withdraw()).count(), 2);
+        assert!(backoff(1) <= BACKOFF_BASE && backoff(10) <= BACKOFF_MAX);
+    }
+}
@@ -0,0 +1,235 @@
+//! Matching requests to `[[proxy.routes]]` and the upstreams they name.
+
+use crate::{health::Events, retry::Policy, upgrade::Upgrades, upstream::Upstream};
+use container_codes_shared::{
+    config::{ProxyConfig, RouteConfig},
+    Error, Result,
+};
+use hyper::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, Uri,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// The routing table built from one version of the proxy config.
+pub struct Router {
+    /// Longest prefix first, then in config order.
+    routes: Vec<Route>,
+    upstreams: Vec<Arc<Upstream>>,
+}
+
+pub struct Route {
+    /// The route path without a trailing `/*` or `/`, so `/` becomes empty.
+    prefix: String,
+    /// `None` for ANY.
+    method: Option<Method>,
+    strip_prefix: Option<String>,
+    add_headers: HeaderMap,
+    pub policy: Policy,
+    /// `None` unless the route passes upgrades through.
+    pub upgrades: Option<Upgrades>,
+    pub upstream: Arc<Upstream>,
+}
+
+impl Router {
+    /// Builds the table, carrying over pools, counters and health from
+    /// `previous`. Health changes are published to `events`.
+    pub fn new(config: &ProxyConfig, events: &Events, previous: Option<&Router>) -> Result<Self> {
+        let upstreams = config
+            .upstreams
+            .iter()
+            .map(|upstream| {
+                let kept = previous.and_then(|previous| previous.upstream(&upstream.name));
+                let built =
+                    Upstream::new(upstream, &config.balancing, &config.health, events, kept)?;
+                Ok((upstream.name.as_str(), Arc::new(built)))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let mut routes = config
+            .routes
+            .iter()
+            .enumerate()
+            .map(|(i, route)| Route::new(i, route, &upstreams, previous))
+            .collect::<Result<Vec<_>>>()?;
+        routes.sort_by_key(|route| std::cmp::Reverse(route.prefix.len()));
+        let mut upstreams: Vec<_> = upstreams.into_values().collect();
+        upstreams.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self { routes, upstreams })
+    }
+
+    pub fn upstreams(&self) -> &[Arc<Upstream>] {
+        &self.upstreams
+    }
+
+    fn upstream(&self, name: &str) -> Option<&Upstream> {
+        self.upstreams
+            .iter()
+            .find(|upstream| upstream.name == name)
+            .map(Arc::as_ref)
+    }
+
+    /// The most specific route for the request. Routes for GET also take HEAD.
+    pub fn route(&self, method: &Method, path: &str) -> Option<&Route> {
+        self.routes.iter().find(|route| {
+            let method_matches = match &route.method {
+                None => true,
+                Some(m) => m == method || (*m == Method::GET && *method == Method::HEAD),
+            };
+            method_matches && under(path, &route.prefix)
+        })
+    }
+}
+
+impl Route {
+    fn new(
+        index: usize,
+        config: &RouteConfig,
+        upstreams: &HashMap<&str, Arc<Upstream>>,
+        previous: Option<&Router>,
+    ) -> Result<Self> {
+        let key = |field: &str| format!("proxy.routes[{}].{}", index, field);
+
+        let upstream = upstreams
+            .get(config.upstream.as_str())
+            .ok_or_else(|| Error::config_invalid(key("upstream"), &config.upstream))?;
+        let method = match config.method.as_str() {
+            "ANY" => None,
+            method => Some(
+                method
+                    .parse()
+                    .map_err(|_| Error::config_invalid(key("method"), method))?,
+            ),
+        };
+
+        let mut add_headers = HeaderMap::new();
+        for (name, value) in config.add_headers.iter().flatten() {
+            let name: HeaderName = name
+                .parse()
+                .map_err(|_| Error::config_invalid(key("add_headers"), name))?;
+            let value: HeaderValue = value
+                .parse()
+                .map_err(|_| Error::config_invalid(key("add_headers"), value))?;
+            add_headers.insert(name, value);
+        }
+
+        let prefix = trim_prefix(&config.path).to_string();
+        // Upgraded connections still open on the route this one replaces count against it.
+        let open = previous
+            .and_then(|previous| {
+                previous
+                    .routes
+                    .iter()
+                    .find(|route| route.prefix == prefix && route.method == method)
+            })
+            .and_then(|route| route.upgrades.as_ref())
+            .map(|upgrades| upgrades.open().clone());
+
+        Ok(Self {
+            upgrades: Upgrades::new(config, open),
+            prefix,
+            method,
+            // `rewrite = false` forwards the original path even with a strip_prefix.
+            strip_prefix: config
+                .strip_prefix
+                .as_deref()
+                .filter(|_| config.rewrite != Some(false))
+                .map(|prefix| trim_prefix(prefix).to_string()),
+            add_headers,
+            policy: Policy::new(config),
+            upstream: upstream.clone(),
+        })
+    }
+
+    /// The path and query to send upstream.
+    pub fn rewrite(&self, uri: &Uri) -> String {
+        let path = uri.path();
+        let path = match &self.strip_prefix {
+            Some(prefix) if under(path, prefix) => &path[prefix.len()..],
+            _ => path,
+        };
+        let path = if path.is_empty() { "/" } else { path };
+        match uri.query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        }
+    }
+
+    /// Sets the route's `add_headers`, replacing any the client sent.
+    pub fn add_headers(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.add_headers {
+            headers.insert(name, value.clone());
+        }
+    }
+}
+
+/// `/api/*` and `/api/` both mean everything under `/api`.
+fn trim_prefix(path: &str) -> &str {
+    path.strip_suffix("/*")
+        .unwrap_or(path)
+        .trim_end_matches('/')
+}
+
+/// True when `path` is `prefix` itself or below it, on a segment boundary.
+fn under(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_and_rewrite() {
+        let config = crate::tests::config(
+            r#"
+[[proxy.routes]]
+path = "/"
+method = "GET"
+upstream = "web"
+
+[[proxy.routes]]
+path = "/api/*"
+method = "ANY"
+upstream = "api"
+strip_prefix = "/api"
+
+[[proxy.routes]]
+path = "/api/raw"
+method = "POST"
+upstream = "api"
+strip_prefix = "/api"
+rewrite = false
+"#,
+        );
+        let router = Router::new(&config, &Events::default(), None).unwrap();
+        let route = |method: Method, uri: &str| {
+            let uri: Uri = uri.parse().unwrap();
+            router
+                .route(&method, uri.path())
+                .map(|route| (route.upstream.name.as_str(), route.rewrite(&uri)))
+        };
+
+        assert_eq!(
+            route(Method::GET, "/index.html"),
+            Some(("web", "/index.html".into()))
+        );
+        assert_eq!(route(Method::HEAD, "/"), Some(("web", "/".into())));
+        assert_eq!(route(Method::POST, "/"), None);
+        assert_eq!(route(Method::GET, "/apis"), Some(("web", "/apis".into())));
+        assert_eq!(
+            route(Method::DELETE, "/api/users/1?force=true"),
+            Some(("api", "/users/1?force=true".into()))
+        );
+        assert_eq!(route(Method::GET, "/api"), Some(("api", "/".into())));
+        assert_eq!(
+            route(Method::POST, "/api/raw/x"),
+            Some(("api", "/api/raw/x".into()))
+        );
+        assert_eq!(
+            route(Method::GET, "/api/raw/x"),
+            Some(("api", "/raw/x".into()))
+        );
+    }
+}
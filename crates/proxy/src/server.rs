@@ -0,0 +1,1049 @@
+//! The proxy listener: accepts HTTP/1.1 and h2c connections and forwards
+//! each request along its route.
+
+use crate::{
+    balancer::Context,
+    breaker::Breaker,
+    forward,
+    health::{Checks, Events, HealthEvent},
+    retry::{self, backoff, Failure, Replay},
+    router::{Route, Router},
+    stats::Metrics,
+    upgrade,
+    upstream::{Body, Inflight, Tracked},
+};
+use bytes::Bytes;
+use container_codes_shared::{
+    config::{Config, ProxyConfig},
+    reload::ReloadHook,
+    types::{ProxyStats, UpstreamServer},
+    Error, Result,
+};
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::{
+    body::Incoming,
+    header::{self, HeaderValue},
+    http::{request::Parts, uri::Scheme},
+    service::service_fn,
+    upgrade::OnUpgrade,
+    Request, Response, StatusCode, Version,
+};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto, graceful::GracefulShutdown},
+};
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, watch},
+    time::Instant,
+};
+use tracing::{debug, info, warn};
+
+/// A running proxy's routing table, counters and health checks; clones
+/// share them.
+#[derive(Clone)]
+pub struct Proxy {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    router: RwLock<Arc<Router>>,
+    metrics: Metrics,
+    events: Events,
+    /// `None` when `proxy.health.enabled` is off.
+    checks: Option<Arc<Checks>>,
+    /// Set on shutdown to close upgraded connections. Each one holds a
+    /// receiver, so the sender sees when the last has closed.
+    closing: watch::Sender<bool>,
+}
+
+impl Proxy {
+    pub fn new(config: &ProxyConfig) -> Result<Self> {
+        let events = Events::default();
+        Ok(Self {
+            inner: Arc::new(Inner {
+                router: RwLock::new(Arc::new(Router::new(config, &events, None)?)),
+                metrics: Metrics::default(),
+                checks: Checks::new(&config.health)?.map(Arc::new),
+                events,
+                closing: watch::channel(false).0,
+            }),
+        })
+    }
+
+    /// Serves connections from `listener` until `shutdown` resolves, then
+    /// waits for open connections to finish their requests and closes
+    /// upgraded ones.
+    pub async fn serve(self, listener: TcpListener, shutdown: impl Future<Output = ()>) {
+        let builder = auto::Builder::new(TokioExecutor::new());
+        let graceful = GracefulShutdown::new();
+        tokio::pin!(shutdown);
+
+        // What clients reached the proxy over, for `X-Forwarded-Proto` and
+        // `Forwarded`. The listener is plain TCP: validation rejects `proxy.ssl`.
+        let scheme = Scheme::HTTP;
+        let checks = self.inner.checks.clone().map(|checks| {
+            let proxy = self.clone();
+            tokio::spawn(checks.run(move || proxy.router()))
+        });
+
+        loop {
+            let (stream, remote) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Proxy failed to accept a connection: {}", e);
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+
+            let (proxy, scheme) = (self.clone(), scheme.clone());
+            let service = service_fn(move |request| {
+                let (proxy, scheme) = (proxy.clone(), scheme.clone());
+                async move { Ok::<_, Infallible>(proxy.handle(request, remote, &scheme).await) }
+            });
+            let connection = graceful.watch(
+                builder
+                    .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                    .into_owned(),
+            );
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    debug!(%remote, "Proxy connection closed: {}", e);
+                }
+            });
+        }
+
+        drop(listener);
+        if let Some(checks) = checks {
+            checks.abort();
+        }
+        self.inner.closing.send_replace(true);
+        graceful.shutdown().await;
+        self.inner.closing.closed().await;
+        info!("Proxy stopped");
+    }
+
+    /// Swaps in the routes and upstreams from `config`.
+    pub fn update(&self, config: &ProxyConfig) -> Result<()> {
+        let router = Arc::new(Router::new(
+            config,
+            &self.inner.events,
+            Some(&self.router()),
+        )?);
+        *self.inner.router.write().unwrap_or_else(|e| e.into_inner()) = router;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> ProxyStats {
+        let mut stats = self.inner.metrics.snapshot();
+        stats.upstreams = self
+            .router()
+            .upstreams()
+            .iter()
+            .map(|upstream| (upstream.name.clone(), upstream.stats()))
+            .collect();
+        stats
+    }
+
+    /// Drains or undrains the server at `address` in `upstream`. It stays
+    /// drained across reloads that keep it.
+    pub fn drain(&self, upstream: &str, address: &str, draining: bool) -> Result<UpstreamServer> {
+        let router = self.router();
+        let upstream = router
+            .upstreams()
+            .iter()
+            .find(|candidate| candidate.name == upstream)
+            .ok_or_else(|| Error::not_found("upstream", upstream))?;
+        let server = upstream
+            .servers()
+            .iter()
+            .find(|server| server.address() == address)
+            .ok_or_else(|| Error::not_found("upstream server", address))?;
+        server.drain(draining);
+        Ok(server.stats())
+    }
+
+    /// Servers changing status as health checks, failed requests and drains decide.
+    pub fn subscribe(&self) -> broadcast::Receiver<HealthEvent> {
+        self.inner.events.subscribe()
+    }
+
+    fn router(&self) -> Arc<Router> {
+        self.inner
+            .router
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    async fn handle(
+        &self,
+        request: Request<Incoming>,
+        remote: SocketAddr,
+        scheme: &Scheme,
+    ) -> Response<Body> {
+        let started = Instant::now();
+        let response = self.forward(request, remote, scheme).await;
+        self.inner
+            .metrics
+            .record(response.status().as_u16(), started.elapsed());
+        response
+    }
+
+    async fn forward(
+        &self,
+        mut request: Request<Incoming>,
+        remote: SocketAddr,
+        scheme: &Scheme,
+    ) -> Response<Body> {
+        let router = self.router();
+        let Some(route) = router.route(request.method(), request.uri().path()) else {
+            return error(ProxyError::NoRoute);
+        };
+        let policy = &route.policy;
+        let deadline = policy.timeout.map(|timeout| Instant::now() + timeout);
+        // Routes without `websocket` forward upgrade requests as plain ones,
+        // with the `Upgrade` header removed.
+        let upgrade = (route.upgrades.is_some()
+            && request.version() == Version::HTTP_11
+            && forward::is_upgrade(request.headers()))
+        .then(|| hyper::upgrade::on(&mut request));
+
+        let (mut parts, body) = request.into_parts();
+        // Balancers see the request as the client sent it.
+        let client_headers = parts.headers.clone();
+        let client_path = parts.uri.path().to_string();
+        let context = Context {
+            client: remote.ip(),
+            path: &client_path,
+            headers: &client_headers,
+        };
+
+        // HTTP/2 clients send the host as the URI authority instead.
+        let host = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| parts.uri.authority().map(|authority| authority.as_str()))
+            .map(str::to_string);
+
+        if upgrade.is_some() {
+            forward::remove_hop_by_hop_for_upgrade(&mut parts.headers);
+        } else {
+            forward::remove_hop_by_hop(&mut parts.headers);
+        }
+        forward::add_forwarded(
+            &mut parts.headers,
+            remote.ip(),
+            scheme.as_str(),
+            host.as_deref(),
+        );
+        forward::add_request_id(&mut parts.headers);
+        route.add_headers(&mut parts.headers);
+        if let Some(host) = host
+            .as_deref()
+            .and_then(|host| HeaderValue::from_str(host).ok())
+        {
+            parts.headers.insert(header::HOST, host);
+        }
+        parts.uri = match route.rewrite(&parts.uri).parse() {
+            Ok(uri) => uri,
+            Err(_) => return error(ProxyError::InvalidPath),
+        };
+        if let Some(client) = upgrade {
+            return self
+                .upgrade(route, &context, &parts, body, client, deadline)
+                .await;
+        }
+
+        let mut body = match Replay::new(body, policy.retries > 0).await {
+            Ok(body) => body,
+            Err(_) => return error(ProxyError::InvalidBody),
+        };
+        route.upstream.budget.deposit();
+
+        let mut retries = 0;
+        loop {
+            // Only replayable bodies are retried, so there is always one here.
+            let Some(attempt_body) = body.next() else {
+                return error(ProxyError::InvalidBody);
+            };
+            let (failure, response) =
+                match attempt(route, &context, &parts, attempt_body, deadline, retries > 0).await {
+                    Attempt::Response(response, inflight) => {
+                        let response = finish(response, inflight);
+                        match retry::retryable_status(response.status().as_u16()) {
+                            Some(failure) => (failure, response),
+                            None => return response,
+                        }
+                    }
+                    Attempt::Failed(failure, e) => (failure, error(e)),
+                    Attempt::Refused(e) => return error(e),
+                };
+
+            let pause = backoff(retries + 1);
+            let retry = retries < policy.retries
+                && body.replayable()
+                && policy.retries_on(failure, &parts.method)
+                && deadline.is_none_or(|deadline| Instant::now() + pause < deadline)
+                && route.upstream.budget.withdraw();
+            if !retry {
+                return response;
+            }
+            retries += 1;
+            debug!(method = %parts.method, path = %parts.uri, upstream = %route.upstream.name,
+                "Retrying after {:?} (retry {} of {})", failure, retries, policy.retries);
+            drop(response);
+            tokio::time::sleep(pause).await;
+        }
+    }
+
+    /// Forwards an upgrade request once, without retries. If the upstream
+    /// switches protocols, the client gets its 101 and both connections are
+    /// spliced in the background; any other answer is passed on as usual.
+    async fn upgrade(
+        &self,
+        route: &Route,
+        context: &Context<'_>,
+        parts: &Parts,
+        body: Incoming,
+        client: OnUpgrade,
+        deadline: Option<Instant>,
+    ) -> Response<Body> {
+        let Some(slot) = route
+            .upgrades
+            .as_ref()
+            .and_then(|upgrades| upgrades.acquire())
+        else {
+            return error(ProxyError::TooManyUpgrades);
+        };
+        let (mut response, inflight) =
+            match attempt(route, context, parts, body.boxed(), deadline, false).await {
+                Attempt::Response(response, inflight) => (response, inflight),
+                Attempt::Failed(_, e) | Attempt::Refused(e) => return error(e),
+            };
+        if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return finish(response, inflight);
+        }
+
+        let upstream = hyper::upgrade::on(&mut response);
+        let closing = self.inner.closing.subscribe();
+        tokio::spawn(upgrade::splice(client, upstream, closing, inflight, slot));
+        let (mut parts, _) = response.into_parts();
+        forward::remove_hop_by_hop_for_upgrade(&mut parts.headers);
+        Response::from_parts(parts, Empty::new().map_err(|e| match e {}).boxed())
+    }
+}
+
+enum Attempt {
+    /// The upstream's response, which may still be worth retrying, and the
+    /// request it keeps active until it is done with.
+    Response(Response<Incoming>, Inflight),
+    Failed(Failure, ProxyError),
+    /// Nothing was sent, and retrying would not help.
+    Refused(ProxyError),
+}
+
+/// Sends the request to a server of `route`'s upstream once.
+async fn attempt(
+    route: &Route,
+    context: &Context<'_>,
+    parts: &Parts,
+    body: Body,
+    deadline: Option<Instant>,
+    retry: bool,
+) -> Attempt {
+    let upstream = &route.upstream;
+    let policy = &route.policy;
+    let permit = match upstream.breaker.as_ref().map(Breaker::admit) {
+        Some(None) => return Attempt::Refused(ProxyError::CircuitOpen),
+        Some(permit) => permit,
+        None => None,
+    };
+    let Some(server) = upstream.pick(context, retry) else {
+        return Attempt::Refused(ProxyError::NoServer);
+    };
+
+    let mut request = Request::new(body);
+    *request.method_mut() = parts.method.clone();
+    *request.uri_mut() = parts.uri.clone();
+    *request.headers_mut() = parts.headers.clone();
+
+    let limit = match (
+        policy
+            .header_timeout
+            .map(|timeout| Instant::now() + timeout),
+        deadline,
+    ) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    let inflight = server.start();
+    let sent = upstream.send(&server, request, policy.connect_timeout);
+    let result = match limit {
+        Some(limit) => tokio::time::timeout_at(limit, sent).await.map_err(|_| ()),
+        None => Ok(sent.await),
+    };
+
+    let (method, path) = (&parts.method, &parts.uri);
+    match result {
+        Ok(Ok(mut response)) => {
+            upstream.pin(context, &server, response.headers_mut());
+            let status = response.status().as_u16();
+            debug!(%method, %path, upstream = %upstream.name, server = %server.address(), status, "Proxied request");
+            inflight.responded(status);
+            if let Some(permit) = permit {
+                permit.record(status < 500);
+            }
+            Attempt::Response(response, inflight)
+        }
+        Ok(Err(e)) => {
+            inflight.failed();
+            if let Some(permit) = permit {
+                permit.record(false);
+            }
+            warn!(%method, %path, upstream = %upstream.name, server = %server.address(),
+                "Upstream request failed: {}", chain(&e));
+            if e.is_connect() {
+                Attempt::Failed(Failure::Connect, ProxyError::ConnectFailed)
+            } else {
+                Attempt::Failed(Failure::Reset, ProxyError::Reset)
+            }
+        }
+        Err(()) => {
+            inflight.failed();
+            if let Some(permit) = permit {
+                permit.record(false);
+            }
+            warn!(%method, %path, upstream = %upstream.name, server = %server.address(),
+                "Upstream request timed out");
+            Attempt::Failed(Failure::Timeout, ProxyError::Timeout)
+        }
+    }
+}
+
+/// Readies an upstream response for the client; the request stays active
+/// until its body has been sent.
+fn finish(response: Response<Incoming>, inflight: Inflight) -> Response<Body> {
+    let (mut parts, body) = response.into_parts();
+    forward::remove_hop_by_hop(&mut parts.headers);
+    Response::from_parts(parts, Tracked::new(body.boxed(), inflight).boxed())
+}
+
+/// Applies `proxy.routes` and `proxy.upstreams` on reload.
+impl ReloadHook for Proxy {
+    fn name(&self) -> &'static str {
+        "proxy"
+    }
+
+    fn sections(&self) -> &'static [&'static str] {
+        &["proxy.routes", "proxy.upstreams"]
+    }
+
+    fn apply(&self, config: &Config) -> Result<()> {
+        match &config.proxy {
+            Some(proxy) => self.update(proxy),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An error and its sources, e.g. "client error (Connect): tcp connect error: Connection refused".
+pub(crate) fn chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+    message
+}
+
+/// Responses the proxy makes itself; see "Proxy error responses" in
+/// docs/configuration.md.
+#[derive(Debug, Clone, Copy)]
+enum ProxyError {
+    NoRoute,
+    InvalidPath,
+    InvalidBody,
+    ConnectFailed,
+    Reset,
+    NoServer,
+    CircuitOpen,
+    TooManyUpgrades,
+    Timeout,
+}
+
+impl ProxyError {
+    fn status(self) -> StatusCode {
+        match self {
+            Self::NoRoute => StatusCode::NOT_FOUND,
+            Self::InvalidPath | Self::InvalidBody => StatusCode::BAD_REQUEST,
+            Self::ConnectFailed | Self::Reset => StatusCode::BAD_GATEWAY,
+            Self::NoServer | Self::CircuitOpen | Self::TooManyUpgrades => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            Self::NoRoute => "NO_ROUTE",
+            Self::InvalidPath => "INVALID_PATH",
+            Self::InvalidBody => "INVALID_BODY",
+            Self::ConnectFailed => "UPSTREAM_CONNECT_FAILED",
+            Self::Reset => "UPSTREAM_RESET",
+            Self::NoServer => "NO_HEALTHY_UPSTREAM",
+            Self::CircuitOpen => "CIRCUIT_OPEN",
+            Self::TooManyUpgrades => "UPGRADE_LIMIT_REACHED",
+            Self::Timeout => "UPSTREAM_TIMEOUT",
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            Self::NoRoute => "No route matches the request",
+            Self::InvalidPath => "Invalid request path",
+            Self::InvalidBody => "The request body could not be read",
+            Self::ConnectFailed => "The upstream server could not be reached",
+            Self::Reset => "The upstream connection failed before a response arrived",
+            Self::NoServer => "No upstream server is available",
+            Self::CircuitOpen => "The upstream is failing and requests to it are paused",
+            Self::TooManyUpgrades => "The route has as many upgraded connections as it allows",
+            Self::Timeout => "The upstream server did not respond in time",
+        }
+    }
+}
+
+/// A JSON error body, `{"error": {"code": ..., "message": ...}}`.
+fn error(error: ProxyError) -> Response<Body> {
+    // Codes and messages are fixed strings with nothing to escape.
+    let body = format!(
+        r#"{{"error":{{"code":"{}","message":"{}"}}}}"#,
+        error.code(),
+        error.message()
+    );
+    let mut response = Response::new(Full::new(Bytes::from(body)).map_err(|e| match e {}).boxed());
+    *response.status_mut() = error.status();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::get, Json};
+    use container_codes_shared::config::BalancingStrategy;
+    use container_codes_shared::request_id::RequestId;
+    use http_body_util::Empty;
+    use hyper_util::client::legacy::{connect::HttpConnector, Client};
+
+    #[tokio::test]
+    async fn test_forwards_to_upstream() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        let app = axum::Router::new().fallback(get(
+            |uri: axum::http::Uri, headers: HeaderMap| async move {
+                let header = |name: &str| {
+                    headers
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                Json(serde_json::json!({
+                    "uri": uri.to_string(),
+                    "host": header("host"),
+                    "version": header("x-api-version"),
+                    "session": header("x-session"),
+                    "forwarded_for": header("x-forwarded-for"),
+                    "forwarded": header("forwarded"),
+                    "forwarded_proto": header("x-forwarded-proto"),
+                    "request_id": header("x-request-id"),
+                }))
+            },
+        ));
+        tokio::spawn(async move { axum::serve(upstream, app).await.unwrap() });
+
+        let config = crate::tests::config(&format!(
+            r#"
+[[proxy.upstreams]]
+name = "local"
+strategy = "round_robin"
+servers = [{{ address = "{}", weight = 1, max_fails = 3, fail_timeout = "30s" }}]
+
+[[proxy.routes]]
+path = "/api/*"
+method = "GET"
+upstream = "local"
+strip_prefix = "/api"
+add_headers = {{ "X-API-Version" = "v1" }}
+"#,
+            upstream_addr
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Proxy::new(&config)
+                .unwrap()
+                .serve(listener, std::future::pending()),
+        );
+
+        let client =
+            Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpConnector::new());
+        let request = |path: &str| {
+            Request::get(format!("http://{}{}", proxy_addr, path))
+                .header(header::HOST, "example.com")
+                .header(header::CONNECTION, "x-session")
+                .header("x-session", "secret")
+                .header("x-forwarded-proto", "https")
+                .body(Empty::new())
+                .unwrap()
+        };
+        let request_id = |request: Request<Empty<Bytes>>| {
+            let client = client.clone();
+            async move {
+                let response = client.request(request).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(
+                    &response.into_body().collect().await.unwrap().to_bytes(),
+                )
+                .unwrap();
+                body["request_id"].as_str().unwrap().to_string()
+            }
+        };
+
+        let response = client.request(request("/api/users?page=2")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes())
+                .unwrap();
+        assert_eq!(body["uri"], "/users?page=2");
+        assert_eq!(body["host"], "example.com");
+        assert_eq!(body["version"], "v1");
+        assert_eq!(body["session"], serde_json::Value::Null);
+        assert_eq!(body["forwarded_for"], "127.0.0.1");
+        assert_eq!(
+            body["forwarded"],
+            "for=127.0.0.1;host=example.com;proto=http"
+        );
+        // The scheme is the listener's, not what the client claims.
+        assert_eq!(body["forwarded_proto"], "http");
+
+        let mut with_id = request("/api/users");
+        with_id
+            .headers_mut()
+            .insert("x-request-id", HeaderValue::from_static("req-1"));
+        assert_eq!(request_id(with_id).await, "req-1");
+        let generated = request_id(request("/api/users")).await;
+        assert!(RequestId::parse(&generated).is_some());
+        let mut invalid = request("/api/users");
+        invalid
+            .headers_mut()
+            .insert("x-request-id", HeaderValue::from_static("has space"));
+        assert_ne!(request_id(invalid).await, "has space");
+
+        let response = client.request(request("/other")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_ejects_failing_servers() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                upstream,
+                axum::Router::new().fallback(get(|| async { "ok" })),
+            )
+            .await
+        });
+
+        let config = crate::tests::config(&format!(
+            r#"
+[[proxy.upstreams]]
+name = "pair"
+strategy = "round_robin"
+servers = [
+    {{ address = "127.0.0.1:1", weight = 1, max_fails = 1, fail_timeout = "1m" }},
+    {{ address = "{}", weight = 1, max_fails = 1, fail_timeout = "1m" }},
+]
+
+[[proxy.routes]]
+path = "/"
+method = "GET"
+upstream = "pair"
+"#,
+            upstream_addr
+        ));
+        let proxy = Proxy::new(&config).unwrap();
+        let mut events = proxy.subscribe();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(proxy.clone().serve(listener, std::future::pending()));
+
+        let client =
+            Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpConnector::new());
+        let mut statuses = vec![];
+        for _ in 0..4 {
+            let uri = format!("http://{}/", proxy_addr).parse().unwrap();
+            statuses.push(client.get(uri).await.unwrap().status());
+        }
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::BAD_GATEWAY,
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::OK
+            ]
+        );
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(
+            (event.address.as_str(), event.status),
+            ("127.0.0.1:1", crate::Status::Ejected)
+        );
+        let servers = &proxy.stats().upstreams["pair"].servers;
+        assert_eq!(servers[0].status, "ejected");
+        assert_eq!(servers[1].total_requests, 3);
+    }
+
+    #[tokio::test]
+    async fn test_drained_servers_get_no_requests() {
+        let mut addresses = vec![];
+        for name in ["a", "b"] {
+            let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addresses.push(upstream.local_addr().unwrap().to_string());
+            tokio::spawn(async move {
+                axum::serve(
+                    upstream,
+                    axum::Router::new().fallback(get(move || async move { name })),
+                )
+                .await
+            });
+        }
+
+        let config = crate::tests::config(&format!(
+            r#"
+[[proxy.upstreams]]
+name = "pair"
+strategy = "round_robin"
+servers = [
+    {{ address = "{}", weight = 1, max_fails = 0, fail_timeout = "30s" }},
+    {{ address = "{}", weight = 1, max_fails = 0, fail_timeout = "30s" }},
+]
+
+[[proxy.routes]]
+path = "/"
+method = "GET"
+upstream = "pair"
+"#,
+            addresses[0], addresses[1]
+        ));
+        let proxy = Proxy::new(&config).unwrap();
+        let mut events = proxy.subscribe();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(proxy.clone().serve(listener, std::future::pending()));
+
+        let client =
+            Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpConnector::new());
+        let bodies = || async {
+            let mut bodies = vec![];
+            for _ in 0..4 {
+                let uri = format!("http://{}/", proxy_addr).parse().unwrap();
+                let response = client.get(uri).await.unwrap();
+                bodies.push(response.into_body().collect().await.unwrap().to_bytes());
+            }
+            bodies
+        };
+
+        let drained = proxy.drain("pair", &addresses[0], true).unwrap();
+        assert_eq!(drained.status, "draining");
+        assert_eq!(events.try_recv().unwrap().status, crate::Status::Draining);
+        assert!(bodies().await.iter().all(|body| body == "b"));
+
+        // A reload keeps the drain; undraining puts the server back.
+        proxy.update(&config).unwrap();
+        assert!(bodies().await.iter().all(|body| body == "b"));
+        proxy.drain("pair", &addresses[0], false).unwrap();
+        assert!(bodies().await.iter().any(|body| body == "a"));
+
+        assert!(matches!(
+            proxy.drain("pair", "127.0.0.1:1", true),
+            Err(Error::NotFound { .. })
+        ));
+        assert!(matches!(
+            proxy.drain("other", &addresses[0], true),
+            Err(Error::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_balancing_defaults_and_session_affinity() {
+        let mut addresses = vec![];
+        for name in ["a", "b"] {
+            let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addresses.push(upstream.local_addr().unwrap().to_string());
+            tokio::spawn(async move {
+                axum::serve(
+                    upstream,
+                    axum::Router::new().fallback(get(move || async move { name })),
+                )
+                .await
+            });
+        }
+
+        // No strategy of its own, so the upstream hashes on the client IP.
+        let mut config = crate::tests::config(&format!(
+            r#"
+[[proxy.upstreams]]
+name = "pair"
+servers = [
+    {{ address = "{}", weight = 1, max_fails = 0, fail_timeout = "30s" }},
+    {{ address = "{}", weight = 1, max_fails = 0, fail_timeout = "30s" }},
+]
+
+[[proxy.routes]]
+path = "/"
+method = "GET"
+upstream = "pair"
+"#,
+            addresses[0], addresses[1]
+        ));
+        config.balancing.strategy = BalancingStrategy::IpHash;
+        config.balancing.session_affinity = true;
+        let proxy = Proxy::new(&config).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(proxy.clone().serve(listener, std::future::pending()));
+
+        let client =
+            Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpConnector::new());
+        let get = |cookie: Option<String>| {
+            let client = client.clone();
+            async move {
+                let mut request = Request::get(format!("http://{}/", proxy_addr));
+                if let Some(cookie) = cookie {
+                    request = request.header(header::COOKIE, cookie);
+                }
+                let response = client
+                    .request(request.body(Empty::new()).unwrap())
+                    .await
+                    .unwrap();
+                let set_cookie = response
+                    .headers()
+                    .get(header::SET_COOKIE)
+                    .map(|value| value.to_str().unwrap().to_string());
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                (String::from_utf8(body.to_vec()).unwrap(), set_cookie)
+            }
+        };
+
+        let (first, set_cookie) = get(None).await;
+        let set_cookie = set_cookie.unwrap();
+        assert!(set_cookie.starts_with("SID=") && set_cookie.ends_with("; Path=/; HttpOnly"));
+        assert!(!set_cookie.contains(&addresses[0]) && !set_cookie.contains(&addresses[1]));
+        for _ in 0..3 {
+            assert_eq!(get(None).await.0, first);
+        }
+
+        // The cookie wins over the hash, and is not sent again.
+        let cookie = set_cookie.split(';').next().unwrap().to_string();
+        assert_eq!(get(Some(cookie.clone())).await, (first.clone(), None));
+        let (other, other_name) = if first == "a" { (1, "b") } else { (0, "a") };
+        let other_cookie = format!(
+            "SID={:016x}",
+            crate::balancer::hash(addresses[other].as_bytes())
+        );
+        assert_eq!(
+            get(Some(other_cookie)).await,
+            (other_name.to_string(), None)
+        );
+
+        // A client pinned to a server out of rotation is moved and re-pinned.
+        let pinned = if first == "a" { 0 } else { 1 };
+        proxy.drain("pair", &addresses[pinned], true).unwrap();
+        let (moved, set_cookie) = get(Some(cookie.clone())).await;
+        assert_ne!(moved, first);
+        assert_ne!(set_cookie.unwrap().split(';').next().unwrap(), cookie);
+    }
+
+    #[tokio::test]
+    async fn test_retries_and_timeouts() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let counter = calls.clone();
+        let app = axum::Router::new()
+            .route(
+                "/flaky",
+                axum::routing::any(move || {
+                    let calls = counter.clone();
+                    async move {
+                        match calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % 2 {
+                            0 => StatusCode::SERVICE_UNAVAILABLE,
+                            _ => StatusCode::OK,
+                        }
+                    }
+                }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    "late"
+                }),
+            );
+        tokio::spawn(async move { axum::serve(upstream, app).await.unwrap() });
+
+        let config = crate::tests::config(&format!(
+            r#"
+[[proxy.upstreams]]
+name = "local"
+strategy = "round_robin"
+servers = [{{ address = "{}", weight = 1, max_fails = 0, fail_timeout = "30s" }}]
+
+[[proxy.routes]]
+path = "/"
+method = "ANY"
+upstream = "local"
+header_timeout = "100ms"
+retries = 1
+retry_on = ["503"]
+"#,
+            upstream_addr
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Proxy::new(&config)
+                .unwrap()
+                .serve(listener, std::future::pending()),
+        );
+
+        let client =
+            Client::builder(TokioExecutor::new()).build::<_, Full<Bytes>>(HttpConnector::new());
+        let send = |method: &str, path: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri(format!("http://{}{}", proxy_addr, path))
+                .body(Full::new(Bytes::from_static(b"{}")))
+                .unwrap();
+            client.request(request)
+        };
+
+        assert_eq!(
+            send("GET", "/flaky").await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 2);
+        // POST may have been acted on, so the 503 stands.
+        assert_eq!(
+            send("POST", "/flaky").await.unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 3);
+
+        let response = send("GET", "/slow").await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes())
+                .unwrap();
+        assert_eq!(body["error"]["code"], "UPSTREAM_TIMEOUT");
+    }
+
+    #[tokio::test]
+    async fn test_splices_upgraded_connections() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Switches every connection to an echo protocol.
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = upstream.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut request = vec![];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        let mut byte = [0];
+                        stream.read_exact(&mut byte).await.unwrap();
+                        request.push(byte[0]);
+                    }
+                    let response = "HTTP/1.1 101 Switching Protocols\r\nUpgrade: echo\r\nConnection: upgrade\r\n\r\n";
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                    let (mut reader, mut writer) = stream.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+
+        let config = crate::tests::config(&format!(
+            r#"
+[[proxy.upstreams]]
+name = "echo"
+strategy = "round_robin"
+servers = [{{ address = "{}", weight = 1, max_fails = 0, fail_timeout = "30s" }}]
+
+[[proxy.routes]]
+path = "/"
+method = "GET"
+upstream = "echo"
+websocket = true
+max_upgraded_connections = 1
+"#,
+            upstream_addr
+        ));
+        let proxy = Proxy::new(&config).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        tokio::spawn(proxy.clone().serve(listener, std::future::pending()));
+
+        let upgrade = || async {
+            let mut stream = tokio::net::TcpStream::connect(proxy_addr).await.unwrap();
+            let request = "GET / HTTP/1.1\r\nHost: example.com\r\nConnection: upgrade\r\nUpgrade: echo\r\n\r\n";
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut head = vec![];
+            while !head.ends_with(b"\r\n\r\n") {
+                let mut byte = [0];
+                stream.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            (stream, String::from_utf8(head).unwrap())
+        };
+
+        let (mut stream, head) = upgrade().await;
+        assert!(head.starts_with("HTTP/1.1 101"), "{}", head);
+        assert!(head.to_lowercase().contains("upgrade: echo"), "{}", head);
+        stream.write_all(b"hello").await.unwrap();
+        let mut echoed = [0; 5];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"hello");
+        assert_eq!(proxy.stats().upstreams["echo"].active_connections, 1);
+
+        let (_, head) = upgrade().await;
+        assert!(head.starts_with("HTTP/1.1 503"), "{}", head);
+
+        drop(stream);
+        for _ in 0..50 {
+            if proxy.stats().upstreams["echo"].active_connections == 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(proxy.stats().upstreams["echo"].active_connections, 0);
+    }
+}
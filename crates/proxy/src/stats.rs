@@ -0,0 +1,85 @@
+//! Proxy-wide request counters and response time percentiles.
+
+use container_codes_shared::types::{ProxyStats, ResponseTimeStats};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// How far back rates and percentiles look.
+const WINDOW: Duration = Duration::from_secs(60);
+/// Most samples kept within the window; older ones are dropped first.
+const MAX_SAMPLES: usize = 8192;
+
+#[derive(Default)]
+pub struct Metrics {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    /// When each recent request finished and how long it took in milliseconds.
+    recent: Mutex<VecDeque<(Instant, f64)>>,
+}
+
+impl Metrics {
+    /// Records a request the proxy answered; 5xx responses count as errors.
+    pub fn record(&self, status: u16, elapsed: Duration) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if status >= 500 {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == MAX_SAMPLES {
+            recent.pop_front();
+        }
+        recent.push_back((Instant::now(), elapsed.as_secs_f64() * 1000.0));
+    }
+
+    /// Totals since start; the rate and response times cover the last minute.
+    pub fn snapshot(&self) -> ProxyStats {
+        let (mut times, span): (Vec<f64>, Duration) = {
+            let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+            while recent.front().is_some_and(|(at, _)| at.elapsed() > WINDOW) {
+                recent.pop_front();
+            }
+            // A full buffer covers less than the window.
+            let span = match recent.front() {
+                Some((oldest, _)) if recent.len() == MAX_SAMPLES => {
+                    oldest.elapsed().max(Duration::from_secs(1))
+                }
+                _ => WINDOW,
+            };
+            (recent.iter().map(|(_, ms)| *ms).collect(), span)
+        };
+        times.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| match times.len() {
+            0 => 0.0,
+            n => times[((n as f64 * p).ceil() as usize).clamp(1, n) - 1],
+        };
+        let requests = self.requests.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+        ProxyStats {
+            requests_total: requests,
+            requests_per_second: times.len() as f64 / span.as_secs_f64(),
+            response_times: ResponseTimeStats {
+                avg: if times.is_empty() {
+                    0.0
+                } else {
+                    times.iter().sum::<f64>() / times.len() as f64
+                },
+                p50: percentile(0.5),
+                p95: percentile(0.95),
+                p99: percentile(0.99),
+            },
+            error_rate: if requests == 0 {
+                0.0
+            } else {
+                errors as f64 / requests as f64
+            },
+            upstreams: HashMap::new(),
+        }
+    }
+}
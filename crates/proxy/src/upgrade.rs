@@ -0,0 +1,189 @@
+//! Splicing upgraded connections (WebSocket, h2c and the like) between the
+//! client and the upstream server.
+
+use crate::upstream::Inflight;
+use container_codes_shared::config::RouteConfig;
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::TokioIo;
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    sync::watch,
+    time::Instant,
+};
+use tracing::debug;
+
+/// A route's limits on upgraded connections.
+pub struct Upgrades {
+    idle_timeout: Option<Duration>,
+    max: Option<u32>,
+    /// Connections open now; shared with the route this one replaced on reload.
+    open: Arc<AtomicU32>,
+}
+
+/// One of a route's upgraded connections; frees its place when dropped.
+pub struct Slot {
+    open: Arc<AtomicU32>,
+    idle_timeout: Option<Duration>,
+}
+
+impl Upgrades {
+    /// `None` unless the route sets `websocket`. `open` carries the count
+    /// over from the previous version of the route.
+    pub fn new(config: &RouteConfig, open: Option<Arc<AtomicU32>>) -> Option<Self> {
+        (config.websocket == Some(true)).then(|| Self {
+            idle_timeout: config.upgrade_idle_timeout.map(Into::into),
+            max: config.max_upgraded_connections,
+            open: open.unwrap_or_default(),
+        })
+    }
+
+    pub fn open(&self) -> &Arc<AtomicU32> {
+        &self.open
+    }
+
+    /// A place for one more connection, unless the route is full.
+    pub fn acquire(&self) -> Option<Slot> {
+        self.open
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |open| {
+                self.max.is_none_or(|max| open < max).then_some(open + 1)
+            })
+            .ok()?;
+        Some(Slot {
+            open: self.open.clone(),
+            idle_timeout: self.idle_timeout,
+        })
+    }
+}
+
+/// Waits for both sides to finish upgrading, then copies bytes both ways
+/// until either side closes, the connection idles out, or `closing` is set.
+/// The request stays active on its server throughout.
+pub async fn splice(
+    client: OnUpgrade,
+    upstream: OnUpgrade,
+    mut closing: watch::Receiver<bool>,
+    inflight: Inflight,
+    slot: Slot,
+) {
+    let (client, upstream) = match tokio::try_join!(client, upstream) {
+        Ok(upgraded) => upgraded,
+        Err(e) => {
+            debug!("Upgrade failed: {}", e);
+            return;
+        }
+    };
+    let activity = Activity::default();
+    let mut client = Watched {
+        io: TokioIo::new(client),
+        activity: &activity,
+    };
+    let mut upstream = Watched {
+        io: TokioIo::new(upstream),
+        activity: &activity,
+    };
+
+    tokio::select! {
+        copied = tokio::io::copy_bidirectional(&mut client, &mut upstream) => {
+            if let Err(e) = copied {
+                debug!("Upgraded connection closed: {}", e);
+            }
+        }
+        _ = activity.idle(slot.idle_timeout) => debug!("Upgraded connection idled out"),
+        _ = closing.wait_for(|closing| *closing) => debug!("Closing upgraded connection for shutdown"),
+    }
+    let _ = tokio::join!(client.io.shutdown(), upstream.io.shutdown());
+    drop((inflight, slot));
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// When either side of a connection last sent anything.
+struct Activity {
+    start: Instant,
+    /// Milliseconds after `start`.
+    last: AtomicU64,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            last: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Activity {
+    fn touch(&self) {
+        self.last
+            .store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Resolves once nothing has been read for `timeout`; never without one.
+    async fn idle(&self, timeout: Option<Duration>) {
+        let Some(timeout) = timeout else {
+            return std::future::pending().await;
+        };
+        loop {
+            let due =
+                self.start + Duration::from_millis(self.last.load(Ordering::Relaxed)) + timeout;
+            if Instant::now() >= due {
+                return;
+            }
+            tokio::time::sleep_until(due).await;
+        }
+    }
+}
+
+/// An IO stream that records reads in an `Activity`.
+struct Watched<'a, T> {
+    io: T,
+    activity: &'a Activity,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Watched<'_, T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.io).poll_read(cx, buf);
+        if buf.filled().len() > before {
+            self.activity.touch();
+        }
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Watched<'_, T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
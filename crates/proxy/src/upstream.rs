@@ -0,0 +1,446 @@
+//! Named groups of backend servers and the pooled client that reaches them.
+
+use crate::{
+    balancer::{self, Balancer, Context},
+    breaker::Breaker,
+    health::{Change, Checks, Events, Health, HealthEvent, Status},
+    retry::Budget,
+};
+use bytes::Bytes;
+use container_codes_shared::{
+    config::{BalancingConfig, HealthConfig, UpstreamConfig, UpstreamProtocol},
+    types::{UpstreamServer, UpstreamStats},
+    Error, Result,
+};
+use http_body_util::combinators::BoxBody;
+use hyper::{
+    body::{Frame, Incoming, SizeHint},
+    header::{self, HeaderMap, HeaderValue},
+    http::uri::{Authority, PathAndQuery, Scheme},
+    Request, Response, Uri, Version,
+};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{self, Poll},
+    time::{Duration, Instant},
+};
+
+/// Request and response bodies as the proxy passes them along.
+pub type Body = BoxBody<Bytes, hyper::Error>;
+
+/// How long an idle pooled connection is kept open.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Weight of the newest sample in a server's average response time.
+const LATENCY_DECAY: f64 = 0.3;
+/// Effective weights are in hundredths, so slow start can ramp a weight of 1.
+const WEIGHT_SCALE: u32 = 100;
+
+pub struct Upstream {
+    pub name: String,
+    servers: Vec<Arc<Server>>,
+    balancer: Box<dyn Balancer>,
+    pub breaker: Option<Breaker>,
+    pub budget: Budget,
+    protocol: UpstreamProtocol,
+    /// The cookie naming each client's server, with `session_affinity`.
+    affinity: Option<String>,
+    /// Pooled clients by connect timeout, since routes can set their own.
+    clients: Mutex<HashMap<Option<Duration>, Client<HttpConnector, Body>>>,
+}
+
+/// One backend server. Its counters and health survive reloads that keep
+/// its address.
+pub struct Server {
+    address: String,
+    /// What the affinity cookie holds: a hash of the address, so clients
+    /// do not learn it.
+    id: String,
+    authority: Authority,
+    weight: u32,
+    /// The upstream's name, for health events.
+    upstream: String,
+    max_fails: u32,
+    fail_timeout: Duration,
+    slow_start: Duration,
+    events: Events,
+    counters: Arc<Counters>,
+    health: Arc<Health>,
+}
+
+#[derive(Default)]
+struct Counters {
+    total_requests: AtomicU64,
+    failures: AtomicU64,
+    active: AtomicU32,
+    /// Milliseconds as f64 bits.
+    latency: AtomicU64,
+}
+
+impl Upstream {
+    /// Builds the upstream, keeping the connection pool and server state of
+    /// `previous` where the config allows.
+    pub fn new(
+        config: &UpstreamConfig,
+        balancing: &BalancingConfig,
+        health: &HealthConfig,
+        events: &Events,
+        previous: Option<&Upstream>,
+    ) -> Result<Self> {
+        let protocol = config.protocol.unwrap_or(UpstreamProtocol::Http1);
+
+        let servers: Vec<Arc<Server>> = config
+            .servers
+            .iter()
+            .map(|server| {
+                let mut built = Server::new(&server.address, server.weight).ok_or_else(|| {
+                    Error::config_invalid(
+                        format!("proxy.upstreams.{}.servers.address", config.name),
+                        &server.address,
+                    )
+                })?;
+                built.upstream = config.name.clone();
+                built.max_fails = server.max_fails;
+                built.fail_timeout = server.fail_timeout.into();
+                built.slow_start = health.slow_start.map(Into::into).unwrap_or_default();
+                built.events = events.clone();
+                let kept = previous.and_then(|previous| {
+                    previous
+                        .servers
+                        .iter()
+                        .find(|s| s.address == server.address)
+                });
+                if let Some(kept) = kept {
+                    built.counters = kept.counters.clone();
+                    built.health = kept.health.clone();
+                }
+                Ok(Arc::new(built))
+            })
+            .collect::<Result<_>>()?;
+
+        let clients = match previous {
+            Some(previous) if previous.protocol == protocol => previous.lock_clients().clone(),
+            _ => HashMap::new(),
+        };
+
+        Ok(Self {
+            name: config.name.clone(),
+            balancer: balancer::new(
+                config.strategy.unwrap_or(balancing.strategy),
+                config.hash_key.as_ref(),
+                &servers,
+            ),
+            breaker: config
+                .circuit_breaker
+                .as_ref()
+                .map(|breaker| Breaker::new(&config.name, breaker)),
+            budget: Budget::default(),
+            servers,
+            protocol,
+            affinity: balancing
+                .session_affinity
+                .then(|| balancing.session_cookie.clone()),
+            clients: Mutex::new(clients),
+        })
+    }
+
+    pub fn servers(&self) -> &[Arc<Server>] {
+        &self.servers
+    }
+
+    /// The server the balancer chooses for `request`, if any can take it.
+    /// With session affinity, a first attempt goes to the server named by
+    /// the client's cookie while it is in rotation; retries do not.
+    pub fn pick(&self, request: &Context, retry: bool) -> Option<Arc<Server>> {
+        if let Some(server) = self.pinned(request).filter(|_| !retry) {
+            return Some(server);
+        }
+        self.balancer
+            .pick(&self.servers, request)
+            .map(|i| self.servers[i].clone())
+    }
+
+    /// Sets the affinity cookie on a response from `server`, unless the
+    /// client already sent that value.
+    pub fn pin(&self, request: &Context, server: &Server, headers: &mut HeaderMap) {
+        let Some(name) = &self.affinity else {
+            return;
+        };
+        if balancer::cookie(request.headers, name) == Some(server.id.as_str()) {
+            return;
+        }
+        let cookie = format!("{}={}; Path=/; HttpOnly", name, server.id);
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            headers.append(header::SET_COOKIE, value);
+        }
+    }
+
+    fn pinned(&self, request: &Context) -> Option<Arc<Server>> {
+        let id = balancer::cookie(request.headers, self.affinity.as_deref()?)?;
+        self.servers
+            .iter()
+            .find(|server| server.id == id && server.effective_weight() > 0)
+            .cloned()
+    }
+
+    /// Sends `request` to `server` over a pooled connection, giving up on
+    /// connecting after `connect_timeout`. Only the path and query of the
+    /// request URI are used.
+    pub async fn send(
+        &self,
+        server: &Server,
+        mut request: Request<Body>,
+        connect_timeout: Option<Duration>,
+    ) -> std::result::Result<Response<Incoming>, hyper_util::client::legacy::Error> {
+        let mut parts = request.uri().clone().into_parts();
+        parts.scheme = Some(Scheme::HTTP);
+        parts.authority = Some(server.authority.clone());
+        parts
+            .path_and_query
+            .get_or_insert_with(|| PathAndQuery::from_static("/"));
+        *request.uri_mut() =
+            Uri::from_parts(parts).expect("scheme, authority and path are all set");
+        *request.version_mut() = match self.protocol {
+            UpstreamProtocol::Http1 => Version::HTTP_11,
+            UpstreamProtocol::H2 => Version::HTTP_2,
+        };
+        let client = self
+            .lock_clients()
+            .entry(connect_timeout)
+            .or_insert_with(|| {
+                let mut connector = HttpConnector::new();
+                connector.set_nodelay(true);
+                connector.set_connect_timeout(connect_timeout);
+                Client::builder(TokioExecutor::new())
+                    .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+                    .http2_only(self.protocol == UpstreamProtocol::H2)
+                    .build(connector)
+            })
+            .clone();
+        client.request(request).await
+    }
+
+    fn lock_clients(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<Option<Duration>, Client<HttpConnector, Body>>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn stats(&self) -> UpstreamStats {
+        let servers: Vec<UpstreamServer> =
+            self.servers.iter().map(|server| server.stats()).collect();
+        UpstreamStats {
+            total_requests: servers.iter().map(|server| server.total_requests).sum(),
+            active_connections: servers.iter().map(|server| server.active_connections).sum(),
+            servers,
+        }
+    }
+}
+
+impl Server {
+    /// `None` unless `address` is a valid `host:port`. The server is never
+    /// ejected until its upstream sets `max_fails`.
+    pub fn new(address: &str, weight: u32) -> Option<Self> {
+        Some(Self {
+            address: address.to_string(),
+            id: format!("{:016x}", balancer::hash(address.as_bytes())),
+            authority: address.parse().ok()?,
+            weight,
+            upstream: String::new(),
+            max_fails: 0,
+            fail_timeout: Duration::ZERO,
+            slow_start: Duration::ZERO,
+            events: Events::default(),
+            counters: Default::default(),
+            health: Default::default(),
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The configured weight.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// The weight balancers go by, in hundredths of the configured weight:
+    /// 0 while the server is out of rotation and less during slow start.
+    pub fn effective_weight(&self) -> u32 {
+        let (_, share) = self.health();
+        // In f64, since a large weight times the scale overflows u32; the
+        // cast back saturates.
+        (f64::from(self.weight) * f64::from(WEIGHT_SCALE) * share).ceil() as u32
+    }
+
+    pub fn status(&self) -> Status {
+        self.health().0
+    }
+
+    /// Requests sent to the server whose responses have not finished.
+    pub fn active(&self) -> u32 {
+        self.counters.active.load(Ordering::Relaxed)
+    }
+
+    /// Milliseconds to response headers, averaged with recent requests weighing most.
+    pub fn latency(&self) -> f64 {
+        f64::from_bits(self.counters.latency.load(Ordering::Relaxed))
+    }
+
+    /// Counts a request to the server as active until the guard is dropped.
+    pub fn start(self: &Arc<Self>) -> Inflight {
+        self.counters.total_requests.fetch_add(1, Ordering::Relaxed);
+        self.counters.active.fetch_add(1, Ordering::Relaxed);
+        Inflight {
+            server: self.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the result of an active health check.
+    pub fn probed(&self, result: &std::result::Result<(), String>, checks: &Checks) {
+        self.publish(self.health.probed(result, checks, Instant::now()));
+    }
+
+    /// Stops balancers choosing the server, or lets them again.
+    pub fn drain(&self, draining: bool) {
+        self.publish(self.health.drain(draining, Instant::now()));
+    }
+
+    /// Records how a proxied request went, for outlier ejection.
+    fn responded(&self, ok: bool) {
+        self.publish(
+            self.health
+                .responded(ok, self.max_fails, self.fail_timeout, Instant::now()),
+        );
+    }
+
+    fn health(&self) -> (Status, f64) {
+        let (status, share, change) = self.health.current(self.slow_start, Instant::now());
+        self.publish(change);
+        (status, share)
+    }
+
+    fn publish(&self, change: Option<Change>) {
+        if let Some(Change { status, reason }) = change {
+            self.events.publish(HealthEvent {
+                upstream: self.upstream.clone(),
+                address: self.address.clone(),
+                status,
+                reason,
+            });
+        }
+    }
+
+    pub fn stats(&self) -> UpstreamServer {
+        let total_requests = self.counters.total_requests.load(Ordering::Relaxed);
+        let failures = self.counters.failures.load(Ordering::Relaxed);
+        UpstreamServer {
+            address: self.address.clone(),
+            weight: self.weight,
+            status: self.status().to_string(),
+            total_requests,
+            active_connections: self.active(),
+            response_time: self.latency(),
+            error_rate: if total_requests == 0 {
+                0.0
+            } else {
+                failures as f64 / total_requests as f64
+            },
+        }
+    }
+}
+
+/// A request in flight to a server.
+pub struct Inflight {
+    server: Arc<Server>,
+    started: Instant,
+}
+
+impl Inflight {
+    /// Records the time to the response headers; 5xx responses count as failures.
+    pub fn responded(&self, status: u16) {
+        if status >= 500 {
+            self.failed();
+        } else {
+            self.server.responded(true);
+        }
+        let sample = self.started.elapsed().as_secs_f64() * 1000.0;
+        let _ = self.server.counters.latency.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |bits| {
+                let average = f64::from_bits(bits);
+                let average = if average == 0.0 {
+                    sample
+                } else {
+                    average + LATENCY_DECAY * (sample - average)
+                };
+                Some(average.to_bits())
+            },
+        );
+    }
+
+    /// Records a request that got no response.
+    pub fn failed(&self) {
+        self.server
+            .counters
+            .failures
+            .fetch_add(1, Ordering::Relaxed);
+        self.server.responded(false);
+    }
+}
+
+impl Drop for Inflight {
+    fn drop(&mut self) {
+        self.server.counters.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A response body that keeps its request active until the body ends.
+pub struct Tracked {
+    body: Body,
+    inflight: Option<Inflight>,
+}
+
+impl Tracked {
+    pub fn new(body: Body, inflight: Inflight) -> Self {
+        Self {
+            body,
+            inflight: Some(inflight),
+        }
+    }
+}
+
+impl hyper::body::Body for Tracked {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, hyper::Error>>> {
+        let poll = Pin::new(&mut self.body).poll_frame(cx);
+        if let Poll::Ready(None | Some(Err(_))) = poll {
+            self.inflight = None;
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
@@ -0,0 +1,30 @@
+use axum::{extract::State, response::Json};
+use container_codes_shared::{
+    config::redact, reload::ReloadReport, types::ApiResponse, Error, Result,
+};
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::server::AppState;
+
+/// The configuration in effect, with secrets redacted.
+#[instrument(skip(state))]
+pub async fn get_config(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<serde_json::Value>>> {
+    let mut config = serde_json::to_value(&*state.config.current())
+        .map_err(|e| Error::internal(e.to_string()))?;
+    redact(&mut config);
+    Ok(Json(ApiResponse::success(config)))
+}
+
+#[instrument(skip(state))]
+pub async fn reload_config(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<ReloadReport>>> {
+    let report = tokio::task::spawn_blocking(move || state.reloader.reload())
+        .await
+        .map_err(|e| Error::internal(e.to_string()))??;
+
+    Ok(Json(ApiResponse::success(report)))
+}
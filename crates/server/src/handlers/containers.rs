@@ -0,0 +1,32 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use container_codes_shared::{
+    pagination::ListQuery,
+    types::{ApiResponse, ContainerInfo, PaginatedResponse},
+    Result,
+};
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::server::AppState;
+
+/// Containers known to the server, newest first unless `sort` says otherwise.
+#[instrument(skip(state))]
+pub async fn list_containers(
+    State(state): State<Arc<AppState>>,
+    query: ListQuery,
+) -> Result<Json<ApiResponse<PaginatedResponse<ContainerInfo>>>> {
+    let page = state.repositories()?.containers.list(&query).await?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+#[instrument(skip(state))]
+pub async fn get_container(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ContainerInfo>>> {
+    let container = state.repositories()?.containers.get(&id).await?;
+    Ok(Json(ApiResponse::success(container)))
+}
@@ -0,0 +1,22 @@
+use axum::response::{Html, Json};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::{openapi, server::api_routes};
+
+static DOCS_UI: &str = include_str!("../../assets/docs.html");
+
+/// Serves the OpenAPI document generated from the route table.
+pub async fn openapi_json() -> Json<Value> {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    Json(
+        DOCUMENT
+            .get_or_init(|| openapi::document(&api_routes()))
+            .clone(),
+    )
+}
+
+/// Self-contained API reference that renders `/api/openapi.json`.
+pub async fn docs_ui() -> Html<&'static str> {
+    Html(DOCS_UI)
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::OriginalUri,
+    http::{Method, Uri},
+};
+use container_codes_shared::Error;
+
+/// API areas the client library covers that this server does not serve in
+/// full yet; other requests there are answered with `NOT_SUPPORTED`.
+const UNSUPPORTED: &[&str] = &["/jobs", "/containers", "/ws"];
+
+/// Whether `path`, relative to `/api`, lies in an [`UNSUPPORTED`] area.
+pub fn is_unsupported(path: &str) -> bool {
+    UNSUPPORTED.iter().any(|prefix| {
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// Answers `/api` requests no route matched with a JSON error, instead of
+/// handing them to the static file fallback.
+pub async fn api_fallback(method: Method, uri: Uri, OriginalUri(original): OriginalUri) -> Error {
+    if is_unsupported(uri.path()) {
+        Error::not_supported(format!(
+            "{} {} is not supported by this server yet",
+            method,
+            original.path()
+        ))
+    } else {
+        Error::not_found("endpoint", format!("{} {}", method, original.path()))
+    }
+}
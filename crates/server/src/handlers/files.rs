@@ -3,14 +3,15 @@ use axum::{
     http::{header, StatusCode},
     response::{Json, Response},
 };
+use chrono::{DateTime, Utc};
 use container_codes_shared::{
     security::sanitize_filename,
     types::{ApiResponse, FileInfo},
     Result,
 };
-use std::{path::PathBuf, sync::Arc};
-use tracing::{error, info, instrument};
+use std::{os::unix::fs::PermissionsExt, path::PathBuf, sync::Arc};
 use tokio::fs;
+use tracing::{error, info, instrument};
 
 use crate::server::AppState;
 
@@ -19,8 +20,9 @@ pub async fn upload_file(
     State(state): State<Arc<AppState>>,
     mut multipart: Multipart,
 ) -> Result<Json<ApiResponse<String>>> {
-    let upload_dir = PathBuf::from(&state.config.server.static_files.root).join("uploads");
-    
+    let upload_dir =
+        PathBuf::from(&state.config.current().server.static_files.root).join("uploads");
+
     // Ensure upload directory exists
     if let Err(e) = fs::create_dir_all(&upload_dir).await {
         error!("Failed to create upload directory: {}", e);
@@ -30,20 +32,23 @@ pub async fn upload_file(
     }
 
     while let Some(field) = multipart.next_field().await.map_err(|e| {
-        container_codes_shared::Error::http(format!("Failed to read multipart field: {}", e))
+        container_codes_shared::Error::validation(format!("Failed to read multipart field: {}", e))
     })? {
         let name = field.name().unwrap_or("unknown").to_string();
-        
+
         if name == "file" {
             let file_name = field
                 .file_name()
-                .map(|n| sanitize_filename(n))
+                .map(sanitize_filename)
                 .unwrap_or_else(|| format!("upload_{}", uuid::Uuid::new_v4()));
 
             let file_path = upload_dir.join(&file_name);
-            
+
             let data = field.bytes().await.map_err(|e| {
-                container_codes_shared::Error::http(format!("Failed to read file data: {}", e))
+                container_codes_shared::Error::validation(format!(
+                    "Failed to read file data: {}",
+                    e
+                ))
             })?;
 
             if let Err(e) = fs::write(&file_path, &data).await {
@@ -76,7 +81,7 @@ pub async fn download_file(
     State(state): State<Arc<AppState>>,
     Path(file_path): Path<String>,
 ) -> Result<Response> {
-    let static_root = PathBuf::from(&state.config.server.static_files.root);
+    let static_root = PathBuf::from(&state.config.current().server.static_files.root);
     let full_path = static_root.join(&file_path);
 
     // Security check: ensure the path is within the static root
@@ -107,7 +112,7 @@ pub async fn download_file(
                 .body(contents.into())
                 .unwrap())
         }
-        Err(_) => Err(container_codes_shared::Error::http("File not found".to_string())),
+        Err(_) => Err(container_codes_shared::Error::not_found("file", file_path)),
     }
 }
 
@@ -116,7 +121,7 @@ pub async fn file_info(
     State(state): State<Arc<AppState>>,
     Path(file_path): Path<String>,
 ) -> Result<Json<ApiResponse<FileInfo>>> {
-    let static_root = PathBuf::from(&state.config.server.static_files.root);
+    let static_root = PathBuf::from(&state.config.current().server.static_files.root);
     let full_path = static_root.join(&file_path);
 
     // Security check: ensure the path is within the static root
@@ -132,21 +137,18 @@ pub async fn file_info(
                 .first_or_octet_stream()
                 .to_string();
 
-            let created_at = metadata
+            let created_at: DateTime<Utc> = metadata
                 .created()
                 .unwrap_or_else(|_| std::time::SystemTime::now())
                 .into();
 
-            let modified_at = metadata
+            let modified_at: DateTime<Utc> = metadata
                 .modified()
                 .unwrap_or_else(|_| std::time::SystemTime::now())
                 .into();
 
             // Generate simple ETag based on size and modified time
-            let etag = format!(
-                "\"{}\"",
-                format!("{}-{}", metadata.len(), modified_at.timestamp())
-            );
+            let etag = format!("\"{}-{}\"", metadata.len(), modified_at.timestamp());
 
             let file_info = FileInfo {
                 path: file_path,
@@ -155,11 +157,11 @@ pub async fn file_info(
                 created_at,
                 modified_at,
                 etag,
-                permissions: format!("{:o}", metadata.permissions()),
+                permissions: format!("{:o}", metadata.permissions().mode() & 0o777),
             };
 
             Ok(Json(ApiResponse::success(file_info)))
         }
-        Err(_) => Err(container_codes_shared::Error::http("File not found".to_string())),
+        Err(_) => Err(container_codes_shared::Error::not_found("file", file_path)),
     }
-}
\ No newline at end of file
+}
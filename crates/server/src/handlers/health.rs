@@ -1,5 +1,6 @@
 use axum::{extract::State, response::Json};
 use container_codes_shared::{
+    database::DatabaseHealth,
     types::{ApiResponse, HealthStatus},
     Result,
 };
@@ -17,9 +18,13 @@ pub async fn health_check(
     // Check database connectivity
     if let Some(ref db) = state.database {
         match db.health_check().await {
-            Ok(_) => {
+            Ok(DatabaseHealth::Healthy) => {
                 checks.insert("database".to_string(), "healthy".to_string());
             }
+            Ok(DatabaseHealth::Degraded(reason)) => {
+                tracing::warn!("Database is degraded: {}", reason);
+                checks.insert("database".to_string(), "degraded".to_string());
+            }
             Err(e) => {
                 tracing::warn!("Database health check failed: {}", e);
                 checks.insert("database".to_string(), "unhealthy".to_string());
@@ -29,8 +34,19 @@ pub async fn health_check(
         checks.insert("database".to_string(), "disabled".to_string());
     }
 
-    // Check Redis connectivity (placeholder)
-    checks.insert("redis".to_string(), "healthy".to_string());
+    if let Some(ref redis) = state.redis {
+        match redis.health_check().await {
+            Ok(_) => {
+                checks.insert("redis".to_string(), "healthy".to_string());
+            }
+            Err(e) => {
+                tracing::warn!("Redis health check failed: {}", e);
+                checks.insert("redis".to_string(), "unhealthy".to_string());
+            }
+        }
+    } else {
+        checks.insert("redis".to_string(), "disabled".to_string());
+    }
 
     // Check Docker connectivity (placeholder)
     checks.insert("docker".to_string(), "healthy".to_string());
@@ -38,22 +54,15 @@ pub async fn health_check(
     let health_status = HealthStatus {
         status: if checks.values().all(|v| v == "healthy" || v == "disabled") {
             "healthy".to_string()
-        } else {
+        } else if checks.values().any(|v| v == "unhealthy") {
             "unhealthy".to_string()
+        } else {
+            "degraded".to_string()
         },
         timestamp: chrono::Utc::now(),
         checks,
-        uptime: get_uptime(),
+        uptime: state.started_at.elapsed().as_secs(),
     };
 
     Ok(Json(ApiResponse::success(health_status)))
 }
-
-fn get_uptime() -> u64 {
-    // This is a simple implementation - in production you'd track actual start time
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-}
\ No newline at end of file
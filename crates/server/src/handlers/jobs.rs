@@ -0,0 +1,32 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use container_codes_shared::{
+    pagination::ListQuery,
+    types::{ApiResponse, JobInfo, PaginatedResponse},
+    Result,
+};
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::server::AppState;
+
+/// Jobs, newest first unless `sort` says otherwise.
+#[instrument(skip(state))]
+pub async fn list_jobs(
+    State(state): State<Arc<AppState>>,
+    query: ListQuery,
+) -> Result<Json<ApiResponse<PaginatedResponse<JobInfo>>>> {
+    let page = state.repositories()?.jobs.list(&query).await?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+#[instrument(skip(state))]
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<JobInfo>>> {
+    let job = state.repositories()?.jobs.get(&id).await?;
+    Ok(Json(ApiResponse::success(job)))
+}
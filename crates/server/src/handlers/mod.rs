@@ -1,4 +1,10 @@
+pub mod config;
+pub mod containers;
+pub mod docs;
+pub mod fallback;
+pub mod files;
 pub mod health;
+pub mod jobs;
+pub mod proxy;
+pub mod static_files;
 pub mod system;
-pub mod files;
-pub mod static_files;
\ No newline at end of file
@@ -0,0 +1,48 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use container_codes_proxy::Proxy;
+use container_codes_shared::{
+    types::{ApiResponse, ProxyStats, UpstreamServer},
+    Error, Result,
+};
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::server::AppState;
+
+/// Request rates and response times, overall and per upstream server.
+#[instrument(skip(state))]
+pub async fn proxy_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<ProxyStats>>> {
+    Ok(Json(ApiResponse::success(proxy(&state)?.stats())))
+}
+
+/// Stops sending new requests to one upstream server.
+#[instrument(skip(state))]
+pub async fn drain_server(
+    State(state): State<Arc<AppState>>,
+    Path((upstream, server)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<UpstreamServer>>> {
+    let drained = proxy(&state)?.drain(&upstream, &server, true)?;
+    Ok(Json(ApiResponse::success(drained)))
+}
+
+/// Puts a drained upstream server back into rotation.
+#[instrument(skip(state))]
+pub async fn undrain_server(
+    State(state): State<Arc<AppState>>,
+    Path((upstream, server)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<UpstreamServer>>> {
+    let undrained = proxy(&state)?.drain(&upstream, &server, false)?;
+    Ok(Json(ApiResponse::success(undrained)))
+}
+
+fn proxy(state: &AppState) -> Result<&Proxy> {
+    state
+        .proxy
+        .as_ref()
+        .ok_or_else(|| Error::unavailable("The reverse proxy is not enabled"))
+}
@@ -1,44 +1,45 @@
 use axum::{
-    extract::{Request, State},
+    extract::State,
     http::{header, StatusCode, Uri},
-    response::{Html, Response},
+    response::Response,
+};
+use container_codes_shared::{config::Config, Result};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use container_codes_shared::Result;
-use std::{path::PathBuf, sync::Arc};
 use tokio::fs;
 use tracing::{debug, warn};
 
 use crate::server::AppState;
 
-pub async fn serve_static(
-    State(state): State<Arc<AppState>>,
-    uri: Uri,
-    request: Request,
-) -> Response {
-    if !state.config.server.static_files.enabled {
+pub async fn serve_static(State(state): State<Arc<AppState>>, uri: Uri) -> Response {
+    let config = state.config.current();
+
+    if !config.server.static_files.enabled {
         return not_found_response().await;
     }
 
     let path = uri.path().trim_start_matches('/');
-    let static_root = PathBuf::from(&state.config.server.static_files.root);
-    
+    let static_root = PathBuf::from(&config.server.static_files.root);
+
     // If path is empty, try index files
     let file_path = if path.is_empty() {
-        find_index_file(&static_root, &state.config.server.static_files.index_files).await
+        find_index_file(&static_root, &config.server.static_files.index_files).await
     } else {
         let requested_path = static_root.join(path);
-        
+
         // Security check: ensure the path is within the static root
         if !requested_path.starts_with(&static_root) {
             warn!("Attempted path traversal: {}", path);
             return forbidden_response().await;
         }
-        
+
         Some(requested_path)
     };
 
     if let Some(file_path) = file_path {
-        match serve_file(&file_path, &state).await {
+        match serve_file(&file_path, &config).await {
             Ok(response) => {
                 debug!("Served static file: {}", file_path.display());
                 response
@@ -46,8 +47,10 @@ pub async fn serve_static(
             Err(_) => {
                 // If file doesn't exist and this looks like a SPA route, serve index.html
                 if is_spa_route(path) {
-                    if let Some(index_path) = find_index_file(&static_root, &state.config.server.static_files.index_files).await {
-                        match serve_file(&index_path, &state).await {
+                    if let Some(index_path) =
+                        find_index_file(&static_root, &config.server.static_files.index_files).await
+                    {
+                        match serve_file(&index_path, &config).await {
                             Ok(response) => response,
                             Err(_) => not_found_response().await,
                         }
@@ -64,10 +67,8 @@ pub async fn serve_static(
     }
 }
 
-async fn serve_file(file_path: &PathBuf, state: &AppState) -> Result<Response> {
-    let contents = fs::read(file_path).await.map_err(|e| {
-        container_codes_shared::Error::io(e)
-    })?;
+async fn serve_file(file_path: &Path, config: &Config) -> Result<Response> {
+    let contents = fs::read(file_path).await?;
 
     let mime_type = mime_guess::from_path(file_path)
         .first_or_octet_stream()
@@ -78,7 +79,7 @@ async fn serve_file(file_path: &PathBuf, state: &AppState) -> Result<Response> {
         .header(header::CONTENT_TYPE, mime_type.clone());
 
     // Add caching headers if enabled
-    if state.config.server.static_files.etag {
+    if config.server.static_files.etag {
         let etag = generate_etag(&contents);
         response_builder = response_builder.header(header::ETAG, etag);
     }
@@ -86,26 +87,27 @@ async fn serve_file(file_path: &PathBuf, state: &AppState) -> Result<Response> {
     // Add cache control header
     response_builder = response_builder.header(
         header::CACHE_CONTROL,
-        &state.config.server.static_files.cache_control,
+        &config.server.static_files.cache_control,
     );
 
     // Add security headers
-    if state.config.server.security.security_headers {
+    if config.server.security.security_headers {
         response_builder = response_builder
             .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
-            .header(header::X_FRAME_OPTIONS, &state.config.server.security.frame_options);
-        
-        if state.config.server.security.xss_protection {
+            .header(
+                header::X_FRAME_OPTIONS,
+                &config.server.security.frame_options,
+            );
+
+        if config.server.security.xss_protection {
             response_builder = response_builder.header("X-XSS-Protection", "1; mode=block");
         }
     }
 
-    Ok(response_builder
-        .body(contents.into())
-        .unwrap())
+    Ok(response_builder.body(contents.into()).unwrap())
 }
 
-async fn find_index_file(static_root: &PathBuf, index_files: &[String]) -> Option<PathBuf> {
+async fn find_index_file(static_root: &Path, index_files: &[String]) -> Option<PathBuf> {
     for index_file in index_files {
         let index_path = static_root.join(index_file);
         if fs::metadata(&index_path).await.is_ok() {
@@ -124,7 +126,7 @@ fn is_spa_route(path: &str) -> bool {
 fn generate_etag(contents: &[u8]) -> String {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
-    
+
     let mut hasher = DefaultHasher::new();
     contents.hash(&mut hasher);
     format!("\"{}\"", hasher.finish())
@@ -134,7 +136,7 @@ async fn not_found_response() -> Response {
     Response::builder()
         .status(StatusCode::NOT_FOUND)
         .header(header::CONTENT_TYPE, "text/html")
-        .body(Html("<h1>404 Not Found</h1><p>The requested resource was not found.</p>").to_string().into())
+        .body("<h1>404 Not Found</h1><p>The requested resource was not found.</p>".into())
         .unwrap()
 }
 
@@ -142,6 +144,6 @@ async fn forbidden_response() -> Response {
     Response::builder()
         .status(StatusCode::FORBIDDEN)
         .header(header::CONTENT_TYPE, "text/html")
-        .body(Html("<h1>403 Forbidden</h1><p>Access denied.</p>").to_string().into())
+        .body("<h1>403 Forbidden</h1><p>Access denied.</p>".into())
         .unwrap()
-}
\ No newline at end of file
+}
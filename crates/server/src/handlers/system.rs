@@ -14,23 +14,17 @@ pub async fn system_info(
 ) -> Result<Json<ApiResponse<SystemInfo>>> {
     let system_info = SystemInfo {
         version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: get_uptime(),
+        uptime: state.started_at.elapsed().as_secs(),
         memory_usage: get_memory_usage(),
         cpu_usage: get_cpu_usage(),
         active_connections: get_active_connections(),
+        database: state.database.as_ref().map(|db| db.stats()),
+        redis: state.redis.as_ref().map(|redis| redis.stats()),
     };
 
     Ok(Json(ApiResponse::success(system_info)))
 }
 
-fn get_uptime() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-}
-
 fn get_memory_usage() -> u64 {
     // Placeholder implementation - in production use sysinfo or similar
     64 * 1024 * 1024 // 64MB
@@ -44,4 +38,4 @@ fn get_cpu_usage() -> f64 {
 fn get_active_connections() -> u32 {
     // Placeholder implementation - would track actual connections
     42
-}
\ No newline at end of file
+}
@@ -1,24 +1,47 @@
-mod server;
 mod handlers;
 mod middleware;
+mod openapi;
+mod reload;
+mod server;
 mod static_files;
 
 use anyhow::Result;
+use clap::Parser;
 use container_codes_shared::{config::Config, logging::init_logging};
+use std::path::PathBuf;
 use tracing::{error, info};
 
+#[derive(Parser)]
+#[command(name = "container-codes-server", version)]
+struct Args {
+    /// PID file, locked while the server runs so a second one refuses to start
+    #[arg(long, env = "CONTAINER_CODES_PID_FILE")]
+    pid_file: Option<PathBuf>,
+    /// Replace the server holding --pid-file once this one is listening
+    #[arg(long, requires = "pid_file")]
+    takeover: bool,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
     let config = Config::load_from_env()?;
-    
-    init_logging(&config.logging)?;
-    
-    info!("Starting Container Codes Server v{}", env!("CARGO_PKG_VERSION"));
-    
-    if let Err(e) = server::start(config).await {
+
+    let log_handle = init_logging(&config.logging)?;
+
+    info!(
+        "Starting Container Codes Server v{}",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let process = server::ProcessOptions {
+        pid_file: args.pid_file,
+        takeover: args.takeover,
+    };
+    if let Err(e) = server::start(config, log_handle, process).await {
         error!("Server failed to start: {}", e);
         std::process::exit(1);
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
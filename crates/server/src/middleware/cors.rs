@@ -0,0 +1,173 @@
+use axum::{extract::Request, http::Response};
+use container_codes_shared::{
+    config::{Config, SecurityConfig},
+    reload::ReloadHook,
+    Error, Result,
+};
+use std::sync::{Arc, RwLock};
+use tower::{Layer, Service, ServiceExt};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+/// CORS as configured by `server.security`, rebuilt when it is reloaded.
+#[derive(Clone)]
+pub struct Cors {
+    layer: Arc<RwLock<CorsLayer>>,
+}
+
+impl Cors {
+    pub fn new(security: &SecurityConfig) -> Result<Self> {
+        Ok(Self {
+            layer: Arc::new(RwLock::new(build(security)?)),
+        })
+    }
+
+    fn current(&self) -> CorsLayer {
+        self.layer.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+fn build(security: &SecurityConfig) -> Result<CorsLayer> {
+    fn parse<T: std::str::FromStr>(key: &str, values: &[String]) -> Result<Vec<T>> {
+        values
+            .iter()
+            .map(|value| value.parse().map_err(|_| Error::config_invalid(key, value)))
+            .collect()
+    }
+    let any = |values: &[String]| values.iter().any(|value| value == "*");
+
+    let origins = if any(&security.cors_origins) {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(parse(
+            "server.security.cors_origins",
+            &security.cors_origins,
+        )?)
+    };
+    let methods = if any(&security.cors_methods) {
+        AllowMethods::any()
+    } else {
+        AllowMethods::list(parse(
+            "server.security.cors_methods",
+            &security.cors_methods,
+        )?)
+    };
+    let headers = if any(&security.cors_headers) {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(parse(
+            "server.security.cors_headers",
+            &security.cors_headers,
+        )?)
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers))
+}
+
+/// Applies `server.security.cors_*` on reload.
+impl ReloadHook for Cors {
+    fn name(&self) -> &'static str {
+        "cors"
+    }
+
+    fn apply(&self, config: &Config) -> Result<()> {
+        let layer = build(&config.server.security)?;
+        *self.layer.write().unwrap_or_else(|e| e.into_inner()) = layer;
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for Cors {
+    type Service = CorsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CorsService {
+            cors: self.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CorsService<S> {
+    cors: Cors,
+    inner: S,
+}
+
+impl<S, B> Service<Request> for CorsService<S>
+where
+    S: Service<Request, Response = Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = std::result::Result<Self::Response, Self::Error>>
+                + Send,
+        >,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The clone is not ready yet; take the ready one, as tower requires.
+        let inner = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, inner);
+        Box::pin(self.cors.current().layer(inner).oneshot(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header};
+
+    #[tokio::test]
+    async fn test_reload_changes_allowed_origins() {
+        let mut config = Config::default();
+        config.server.security.cors_origins = vec!["https://a.example".to_string()];
+        let cors = Cors::new(&config.server.security).unwrap();
+        let service = cors.layer(tower::service_fn(|_: Request| async {
+            Ok::<_, std::convert::Infallible>(Response::new(Body::empty()))
+        }));
+        let allowed = |origin: &'static str| {
+            let request = Request::get("/")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap();
+            let service = service.clone();
+            async move {
+                let response = service.oneshot(request).await.unwrap();
+                response
+                    .headers()
+                    .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                    .map(|value| value.to_str().unwrap().to_string())
+            }
+        };
+
+        assert_eq!(
+            allowed("https://a.example").await.as_deref(),
+            Some("https://a.example")
+        );
+        assert_eq!(allowed("https://b.example").await, None);
+
+        config.server.security.cors_origins = vec!["https://b.example".to_string()];
+        cors.apply(&config).unwrap();
+        assert_eq!(
+            allowed("https://b.example").await.as_deref(),
+            Some("https://b.example")
+        );
+
+        config.server.security.cors_origins = vec!["not a header\n".to_string()];
+        assert!(cors.apply(&config).is_err());
+    }
+}
@@ -1 +1,2 @@
-pub mod request_id;
\ No newline at end of file
+pub mod cors;
+pub mod request_id;
@@ -1,13 +1,6 @@
-use axum::{
-    extract::Request,
-    http::{header::HeaderValue, HeaderMap},
-    middleware::Next,
-    response::Response,
-};
+use axum::{extract::Request, http::Response};
+use container_codes_shared::request_id::{RequestId, REQUEST_ID_HEADER};
 use tower::{Layer, Service};
-use uuid::Uuid;
-
-const REQUEST_ID_HEADER: &str = "x-request-id";
 
 #[derive(Clone)]
 pub struct RequestIdLayer;
@@ -31,47 +24,41 @@ pub struct RequestIdService<S> {
     inner: S,
 }
 
-impl<S> Service<Request> for RequestIdService<S>
+impl<S, B> Service<Request> for RequestIdService<S>
 where
-    S: Service<Request, Response = Response> + Send + 'static,
+    S: Service<Request, Response = Response<B>> + Send + 'static,
     S::Future: Send + 'static,
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
 
-    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, mut request: Request) -> Self::Future {
-        let request_id = request
-            .headers()
-            .get(REQUEST_ID_HEADER)
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or_else(|| {
-                let id = Uuid::new_v4().to_string();
-                request.headers_mut().insert(
-                    REQUEST_ID_HEADER,
-                    HeaderValue::from_str(&id).unwrap(),
-                );
-                // This is a bit of a hack to get the string back out
-                request.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap()
-            })
-            .to_string();
+        // Client-supplied ids that are too long or contain unsafe characters
+        // are replaced, so handlers and upstreams only ever see valid ones.
+        let request_id = RequestId::from_header(request.headers().get(REQUEST_ID_HEADER));
+        request
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, request_id.header_value());
+        request.extensions_mut().insert(request_id.clone());
 
         let future = self.inner.call(request);
 
         Box::pin(async move {
-            let mut response = future.await?;
-            
-            // Add request ID to response headers
-            response.headers_mut().insert(
-                REQUEST_ID_HEADER,
-                HeaderValue::from_str(&request_id).unwrap(),
-            );
+            let header = request_id.header_value();
+            let mut response = request_id.scope(future).await?;
+            response.headers_mut().insert(REQUEST_ID_HEADER, header);
 
             Ok(response)
         })
     }
-}
\ No newline at end of file
+}
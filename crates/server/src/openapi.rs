@@ -0,0 +1,311 @@
+use axum::{
+    handler::Handler,
+    http::Method,
+    routing::{on, MethodFilter, MethodRouter},
+};
+use container_codes_shared::{
+    pagination::{DEFAULT_LIMIT, MAX_LIMIT},
+    types::ApiResponse,
+};
+use schemars::{generate::SchemaSettings, JsonSchema, Schema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+use crate::server::AppState;
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// Request or response payload of an [`ApiRoute`].
+#[derive(Clone, Copy)]
+enum Payload {
+    /// JSON matching the schema.
+    Json(SchemaFn),
+    /// A multipart form with a single file field of this name.
+    Multipart(&'static str),
+    /// Raw bytes of any content type.
+    Binary,
+}
+
+/// One API endpoint: its handler plus what the OpenAPI document says about it.
+pub struct ApiRoute {
+    pub method: Method,
+    pub path: &'static str,
+    pub handler: MethodRouter<Arc<AppState>>,
+    summary: &'static str,
+    tag: &'static str,
+    request: Option<Payload>,
+    response: Option<Payload>,
+    /// Takes the `ListQuery` parameters.
+    paginated: bool,
+}
+
+impl ApiRoute {
+    fn new(method: Method, path: &'static str, handler: MethodRouter<Arc<AppState>>) -> Self {
+        Self {
+            method,
+            path,
+            handler,
+            summary: "",
+            tag: "",
+            request: None,
+            response: None,
+            paginated: false,
+        }
+    }
+
+    pub fn get<H, T>(path: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, Arc<AppState>>,
+        T: 'static,
+    {
+        Self::new(Method::GET, path, on(MethodFilter::GET, handler))
+    }
+
+    pub fn post<H, T>(path: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, Arc<AppState>>,
+        T: 'static,
+    {
+        Self::new(Method::POST, path, on(MethodFilter::POST, handler))
+    }
+
+    pub fn delete<H, T>(path: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, Arc<AppState>>,
+        T: 'static,
+    {
+        Self::new(Method::DELETE, path, on(MethodFilter::DELETE, handler))
+    }
+
+    pub fn summary(mut self, tag: &'static str, summary: &'static str) -> Self {
+        self.tag = tag;
+        self.summary = summary;
+        self
+    }
+
+    /// The handler reads a `ListQuery`.
+    pub fn paginated(mut self) -> Self {
+        self.paginated = true;
+        self
+    }
+
+    pub fn multipart_body(mut self, field: &'static str) -> Self {
+        self.request = Some(Payload::Multipart(field));
+        self
+    }
+
+    /// The handler answers with `Json<ApiResponse<T>>`.
+    pub fn returns<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(Payload::Json(|g| g.subschema_for::<ApiResponse<T>>()));
+        self
+    }
+
+    /// The handler answers with `Json<T>`, outside the usual envelope.
+    pub fn returns_raw<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(Payload::Json(|g| g.subschema_for::<T>()));
+        self
+    }
+
+    /// The handler answers with raw file contents.
+    pub fn returns_file(mut self) -> Self {
+        self.response = Some(Payload::Binary);
+        self
+    }
+
+    /// `/files/info/*path` becomes `/api/files/info/{path}`.
+    fn openapi_path(&self) -> (String, Vec<&'static str>) {
+        let mut params = Vec::new();
+        let segments: Vec<String> = self
+            .path
+            .split('/')
+            .map(|segment| match segment.strip_prefix([':', '*']) {
+                Some(name) => {
+                    params.push(name);
+                    format!("{{{}}}", name)
+                }
+                None => segment.to_string(),
+            })
+            .collect();
+        (format!("/api{}", segments.join("/")), params)
+    }
+
+    fn operation(&self, generator: &mut SchemaGenerator) -> Value {
+        let (_, params) = self.openapi_path();
+        let mut operation = json!({
+            "summary": self.summary,
+            "tags": [self.tag],
+            "operationId": operation_id(self.method.as_str(), self.path),
+            "responses": {
+                "default": {
+                    "description": "Error, identified by `error.code`",
+                    "content": { "application/json": {
+                        "schema": generator.subschema_for::<ApiResponse<()>>()
+                    } }
+                }
+            }
+        });
+
+        let mut parameters: Vec<Value> = params
+            .iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" }
+                })
+            })
+            .collect();
+        if self.paginated {
+            parameters.extend(list_parameters());
+        }
+        if !parameters.is_empty() {
+            operation["parameters"] = parameters.into();
+        }
+
+        if let Some(request) = self.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": content(request, generator),
+            });
+        }
+
+        let mut ok = json!({ "description": "Success" });
+        if let Some(response) = self.response {
+            ok["content"] = content(response, generator);
+        }
+        operation["responses"]["200"] = ok;
+
+        operation
+    }
+}
+
+fn content(payload: Payload, generator: &mut SchemaGenerator) -> Value {
+    match payload {
+        Payload::Json(schema) => json!({ "application/json": { "schema": schema(generator) } }),
+        Payload::Multipart(field) => json!({
+            "multipart/form-data": { "schema": {
+                "type": "object",
+                "required": [field],
+                "properties": { field: { "type": "string", "contentMediaType": "application/octet-stream" } }
+            } }
+        }),
+        Payload::Binary => json!({
+            "application/octet-stream": { "schema": { "type": "string", "contentMediaType": "application/octet-stream" } }
+        }),
+    }
+}
+
+/// The query parameters `ListQuery::parse` reads. Other `field=value`
+/// parameters are equality filters, which OpenAPI has no way to list.
+fn list_parameters() -> [Value; 5] {
+    let query = |name: &str, description: &str, schema: Value| json!({ "name": name, "in": "query", "description": description, "schema": schema });
+    [
+        query(
+            "limit",
+            "Page size",
+            json!({ "type": "integer", "minimum": 1, "maximum": MAX_LIMIT, "default": DEFAULT_LIMIT }),
+        ),
+        query(
+            "offset",
+            "Rows to skip; ignored when a cursor is given",
+            json!({ "type": "integer", "minimum": 0, "default": 0 }),
+        ),
+        query(
+            "cursor",
+            "The `next_cursor` of the previous page",
+            json!({ "type": "string" }),
+        ),
+        query(
+            "sort",
+            "Comma-separated fields, `-` prefixed for descending",
+            json!({ "type": "string", "examples": ["-created_at,name"] }),
+        ),
+        query(
+            "filter",
+            "`field:op:value` with op `eq`, `ne`, `lt`, `lte`, `gt`, `gte`, `in` or `contains`",
+            json!({ "type": "array", "items": { "type": "string" } }),
+        ),
+    ]
+}
+
+/// `GET /files/info/*path` becomes `getFilesInfoPath`.
+fn operation_id(method: &str, path: &str) -> String {
+    let mut id = method.to_lowercase();
+    for word in path.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            id.push(first.to_ascii_uppercase());
+            id.extend(chars);
+        }
+    }
+    id
+}
+
+/// Builds the OpenAPI 3.1 document for `routes`.
+pub fn document(routes: &[ApiRoute]) -> Value {
+    let mut settings = SchemaSettings::draft2020_12();
+    settings.definitions_path = "/components/schemas".into();
+    settings.meta_schema = None;
+    let mut generator = settings.into_generator();
+
+    let mut paths = Map::new();
+    for route in routes {
+        let (path, _) = route.openapi_path();
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+        item[route.method.as_str().to_lowercase()] = route.operation(&mut generator);
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Container Codes API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": generator.take_definitions(true) },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::api_routes;
+
+    #[test]
+    fn test_committed_spec_matches_routes() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../docs/openapi.json");
+        let generated = document(&api_routes());
+
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            let json = serde_json::to_string_pretty(&generated).unwrap();
+            std::fs::write(path, json + "\n").unwrap();
+        }
+
+        let committed: Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert!(
+            committed == generated,
+            "docs/openapi.json no longer matches the route table, regenerate it with \
+             `UPDATE_OPENAPI=1 cargo test -p container-codes-server openapi`"
+        );
+    }
+
+    #[test]
+    fn test_every_route_is_documented() {
+        for route in api_routes() {
+            assert!(
+                !route.summary.is_empty(),
+                "{} {} has no summary",
+                route.method,
+                route.path
+            );
+            assert!(
+                route.response.is_some(),
+                "{} {} has no response",
+                route.method,
+                route.path
+            );
+        }
+    }
+}
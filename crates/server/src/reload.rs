@@ -0,0 +1,63 @@
+use container_codes_shared::log_error;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+use crate::server::AppState;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Reloads the configuration on SIGHUP and whenever the config file changes.
+pub fn spawn_triggers(state: Arc<AppState>) {
+    tokio::spawn(watch_sighup(state.clone()));
+    tokio::spawn(watch_file(state));
+}
+
+async fn watch_sighup(state: Arc<AppState>) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading configuration");
+        reload(&state).await;
+    }
+}
+
+async fn watch_file(state: Arc<AppState>) {
+    let mut last_modified = modified_time(&state);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified = modified_time(&state);
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            info!("Configuration file changed, reloading");
+            reload(&state).await;
+        }
+    }
+}
+
+async fn reload(state: &Arc<AppState>) {
+    let state = state.clone();
+    match tokio::task::spawn_blocking(move || state.reloader.reload()).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => log_error!(e, "Configuration reload failed"),
+        Err(e) => log_error!(e, "Configuration reload task panicked"),
+    }
+}
+
+fn modified_time(state: &AppState) -> Option<SystemTime> {
+    std::fs::metadata(state.reloader.path())
+        .and_then(|m| m.modified())
+        .ok()
+}
@@ -1,64 +1,373 @@
 use crate::handlers;
-use crate::middleware::request_id::RequestIdLayer;
+use crate::middleware::{cors::Cors, request_id::RequestIdLayer};
+use crate::openapi::ApiRoute;
+use crate::reload;
 use axum::{
-    extract::DefaultBodyLimit,
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Request},
+    routing::get,
     Router,
 };
+use container_codes_proxy::Proxy;
 use container_codes_shared::{
-    config::Config,
+    config::{Config, Environment},
     database::Database,
+    error,
+    logging::LogHandle,
+    pagination,
+    pidfile::PidFile,
+    redis::RedisClient,
+    reload::{ConfigHandle, ConfigReloader, ReloadReport},
+    repository::Repositories,
+    request_id::RequestId,
+    types::{
+        ContainerInfo, FileInfo, HealthStatus, JobInfo, PaginatedResponse, ProxyStats, SystemInfo,
+        UpstreamServer,
+    },
+    Error, Result,
 };
-use std::{net::SocketAddr, sync::Arc};
-use tower::ServiceBuilder;
-use tower_http::{
-    compression::CompressionLayer,
-    cors::CorsLayer,
-    limit::RequestBodyLimitLayer,
-    trace::TraceLayer,
+use std::{
+    collections::HashSet,
+    future::IntoFuture,
+    net::SocketAddr,
+    os::fd::{FromRawFd, RawFd},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::{TcpListener, TcpSocket},
+    signal::unix::{signal, SignalKind},
+    sync::watch,
 };
-use tracing::{info, instrument};
+use tower::ServiceBuilder;
+use tower_http::{compression::CompressionLayer, trace::TraceLayer};
+use tracing::{info, info_span, instrument, warn, Span};
+
+/// Extra time a takeover waits beyond its own drain window for the
+/// previous server to exit.
+const TAKEOVER_MARGIN: Duration = Duration::from_secs(10);
 
 pub struct AppState {
-    pub config: Config,
+    pub config: ConfigHandle,
+    pub reloader: ConfigReloader,
     pub database: Option<Database>,
+    /// Shared by the job queue, rate limiter and cache.
+    pub redis: Option<RedisClient>,
+    /// Present when `proxy.enabled` is set.
+    pub proxy: Option<Proxy>,
+    pub started_at: Instant,
+}
+
+impl AppState {
+    /// Repositories of the configured database.
+    pub fn repositories(&self) -> Result<Repositories> {
+        self.database
+            .as_ref()
+            .map(Database::repositories)
+            .ok_or_else(|| Error::unavailable("No database is configured"))
+    }
+}
+
+/// How this process claims its PID file.
+#[derive(Debug, Default)]
+pub struct ProcessOptions {
+    pub pid_file: Option<PathBuf>,
+    /// Listen alongside the server holding `pid_file`, then ask it to drain
+    /// and take the file over once it exits.
+    pub takeover: bool,
 }
 
-#[instrument(skip(config))]
-pub async fn start(config: Config) -> anyhow::Result<()> {
+#[instrument(skip(config, log_handle))]
+pub async fn start(
+    config: Config,
+    log_handle: LogHandle,
+    process: ProcessOptions,
+) -> anyhow::Result<()> {
+    // Refuse to start next to a running server before touching the database.
+    let pid_file = match &process.pid_file {
+        Some(path) if !process.takeover => Some(PidFile::acquire(path)?),
+        _ => None,
+    };
+
+    error::expose_internal_errors(config.server.environment == Environment::Development);
+    pagination::set_cursor_key(config.server.security.cursor_secret.as_deref());
+
     let database = if !config.database.url.is_empty() {
         Some(Database::new(&config.database).await?)
     } else {
         None
     };
 
-    let state = Arc::new(AppState { config: config.clone(), database });
+    let redis = if !config.redis.url.is_empty() {
+        Some(RedisClient::new(&config.redis)?)
+    } else {
+        None
+    };
+
+    let proxy = match config.proxy.as_ref().filter(|proxy| proxy.enabled) {
+        Some(proxy) => Some((
+            Proxy::new(proxy)?,
+            proxy.bind_address.parse::<SocketAddr>()?,
+        )),
+        None => None,
+    };
 
-    let app = create_router(state.clone());
+    let cors = Cors::new(&config.server.security)?;
+    let handle = ConfigHandle::new(config.clone());
+    let mut reloader = ConfigReloader::new(Config::path_from_env(), handle.clone())
+        .with_hook(log_handle)
+        .with_hook(pagination::CursorKeyHook)
+        .with_hook(cors.clone());
+    if let Some((proxy, _)) = &proxy {
+        reloader = reloader.with_hook(proxy.clone());
+    }
 
-    let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port).parse()?;
-    
-    info!("Server listening on {}", addr);
+    let state = Arc::new(AppState {
+        config: handle,
+        reloader,
+        database,
+        redis,
+        proxy: proxy.as_ref().map(|(proxy, _)| proxy.clone()),
+        started_at: Instant::now(),
+    });
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    reload::spawn_triggers(state.clone());
 
+    let app = create_router(state.clone(), cors);
+
+    let listener = match inherited_listener()? {
+        Some(listener) => {
+            info!(
+                "Server listening on {} (socket activated)",
+                listener.local_addr()?
+            );
+            listener
+        }
+        None => {
+            let addr: SocketAddr =
+                format!("{}:{}", config.server.host, config.server.port).parse()?;
+            let listener = bind(addr)?;
+            info!("Server listening on {}", addr);
+            listener
+        }
+    };
+
+    let drain = config.server.shutdown_timeout.as_duration();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    let proxy = match proxy {
+        Some((proxy, addr)) => {
+            let listener = bind(addr)?;
+            info!("Proxy listening on {}", addr);
+            Some(tokio::spawn(
+                proxy.serve(listener, shutdown_requested(shutdown_rx.clone())),
+            ))
+        }
+        None => None,
+    };
+
+    let serve = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_requested(shutdown_rx.clone()))
+        .into_future();
+    tokio::pin!(serve);
+
+    // Held until the process exits.
+    let _pid_file = match (pid_file, process.pid_file) {
+        (Some(pid_file), _) => Some(pid_file),
+        (None, Some(path)) => tokio::select! {
+            result = &mut serve => return Ok(result?),
+            pid_file = PidFile::take_over(&path, drain + TAKEOVER_MARGIN) => {
+                let pid_file = pid_file?;
+                info!(pid_file = %pid_file.path().display(), "Took over from the previous server");
+                Some(pid_file)
+            }
+        },
+        (None, None) => None,
+    };
+
+    tokio::select! {
+        (result, _) = async {
+            tokio::join!(&mut serve, async {
+                if let Some(proxy) = proxy {
+                    let _ = proxy.await;
+                }
+            })
+        } => result?,
+        _ = async {
+            shutdown_requested(shutdown_rx).await;
+            tokio::time::sleep(drain).await;
+        } => warn!("In-flight requests did not finish within {:?}; closing them", drain),
+    }
+
+    if let Some(database) = &state.database {
+        database.close().await;
+    }
+    info!("Server stopped");
     Ok(())
 }
 
-fn create_router(state: Arc<AppState>) -> Router {
-    let api_routes = Router::new()
-        .route("/health", get(handlers::health::health_check))
-        .route("/system/info", get(handlers::system::system_info))
-        .route("/files/upload", post(handlers::files::upload_file))
-        .route("/files/download/*path", get(handlers::files::download_file))
-        .route("/files/info/*path", get(handlers::files::file_info));
+/// Binds with SO_REUSEPORT so that during a restart the new server can
+/// listen on the same port while the previous one drains.
+fn bind(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    socket.set_reuseport(true)?;
+    socket.bind(addr)?;
+    socket.listen(1024)
+}
+
+/// The listening socket systemd passed us under socket activation, if any.
+fn inherited_listener() -> std::io::Result<Option<TcpListener>> {
+    /// systemd passes sockets starting at this descriptor.
+    const LISTEN_FDS_START: RawFd = 3;
+
+    let for_us = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse().ok())
+        == Some(std::process::id());
+    let count: u32 = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+    if !for_us || count == 0 {
+        return Ok(None);
+    }
+    // SAFETY: with LISTEN_PID naming this process, systemd guarantees fd 3 is
+    // an open listening socket that nothing else in the process owns.
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener).map(Some)
+}
+
+/// Resolves on SIGTERM or Ctrl-C.
+async fn shutdown_signal() {
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    info!("Shutting down; finishing in-flight requests");
+}
+
+async fn shutdown_requested(mut shutdown: watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|requested| *requested).await;
+}
+
+fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(RequestId::as_str)
+        .unwrap_or_default();
+
+    info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+    )
+}
+
+/// Every `/api` endpoint. The router and the OpenAPI document are both built
+/// from this table, so a route cannot exist without being documented.
+pub fn api_routes() -> Vec<ApiRoute> {
+    vec![
+        ApiRoute::get("/health", handlers::health::health_check)
+            .summary("system", "Health of the server and its dependencies")
+            .returns::<HealthStatus>(),
+        ApiRoute::get("/system/info", handlers::system::system_info)
+            .summary("system", "Version and resource usage")
+            .returns::<SystemInfo>(),
+        ApiRoute::get("/config", handlers::config::get_config)
+            .summary("config", "The configuration in effect, secrets redacted")
+            .returns::<serde_json::Value>(),
+        ApiRoute::post("/config/reload", handlers::config::reload_config)
+            .summary("config", "Reload the configuration file")
+            .returns::<ReloadReport>(),
+        ApiRoute::get("/jobs", handlers::jobs::list_jobs)
+            .summary("jobs", "List jobs")
+            .paginated()
+            .returns::<PaginatedResponse<JobInfo>>(),
+        ApiRoute::get("/jobs/:id", handlers::jobs::get_job)
+            .summary("jobs", "Job details")
+            .returns::<JobInfo>(),
+        ApiRoute::get("/containers", handlers::containers::list_containers)
+            .summary("containers", "List containers")
+            .paginated()
+            .returns::<PaginatedResponse<ContainerInfo>>(),
+        ApiRoute::get("/containers/:id", handlers::containers::get_container)
+            .summary("containers", "Container details")
+            .returns::<ContainerInfo>(),
+        ApiRoute::get("/proxy/stats", handlers::proxy::proxy_stats)
+            .summary(
+                "proxy",
+                "Request rates and response times of the reverse proxy",
+            )
+            .returns::<ProxyStats>(),
+        ApiRoute::post(
+            "/proxy/upstreams/:upstream/servers/:server/drain",
+            handlers::proxy::drain_server,
+        )
+        .summary("proxy", "Stop sending new requests to an upstream server")
+        .returns::<UpstreamServer>(),
+        ApiRoute::delete(
+            "/proxy/upstreams/:upstream/servers/:server/drain",
+            handlers::proxy::undrain_server,
+        )
+        .summary("proxy", "Put a drained upstream server back into rotation")
+        .returns::<UpstreamServer>(),
+        ApiRoute::post("/files/upload", handlers::files::upload_file)
+            .summary("files", "Upload a file to the static root")
+            .multipart_body("file")
+            .returns::<String>(),
+        ApiRoute::get("/files/download/*path", handlers::files::download_file)
+            .summary("files", "Download a file")
+            .returns_file(),
+        ApiRoute::get("/files/info/*path", handlers::files::file_info)
+            .summary("files", "File metadata")
+            .returns::<FileInfo>(),
+        ApiRoute::get("/openapi.json", handlers::docs::openapi_json)
+            .summary("docs", "This OpenAPI document")
+            .returns_raw::<serde_json::Value>(),
+    ]
+}
+
+fn create_router(state: Arc<AppState>, cors: Cors) -> Router {
+    let mut api = Router::new();
+    let mut with_fallback = HashSet::new();
+    for route in api_routes() {
+        let mut handler = route.handler;
+        // e.g. `POST /jobs` is not served yet, unlike `GET /jobs`. Each path
+        // gets one fallback, since merged method routers may only have one.
+        if handlers::fallback::is_unsupported(route.path) && with_fallback.insert(route.path) {
+            handler = handler.fallback(handlers::fallback::api_fallback);
+        }
+        api = api.route(route.path, handler);
+    }
+    let api_routes = api
+        .route("/docs", get(handlers::docs::docs_ui))
+        .fallback(handlers::fallback::api_fallback);
 
     let middleware_stack = ServiceBuilder::new()
-        .layer(TraceLayer::new_for_http())
         .layer(RequestIdLayer::new())
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
         .layer(CompressionLayer::new())
-        .layer(CorsLayer::permissive())
+        .layer(cors)
         .layer(DefaultBodyLimit::max(10 * 1024 * 1024)); // 10MB default
 
     Router::new()
@@ -66,4 +375,124 @@ fn create_router(state: Arc<AppState>) -> Router {
         .fallback(handlers::static_files::serve_static)
         .layer(middleware_stack)
         .with_state(state)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Method, StatusCode},
+    };
+    use container_codes_shared::config::DatabaseConfig;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    async fn router() -> Router {
+        let config = Config::default();
+        let database = Database::connect(&DatabaseConfig {
+            url: "sqlite://:memory:".to_string(),
+            ..DatabaseConfig::default()
+        })
+        .await
+        .unwrap();
+        database.migrate().await.unwrap();
+        let job = serde_json::from_value(json!({ "name": "build", "image": "alpine" })).unwrap();
+        database
+            .repositories()
+            .jobs
+            .create("job_1", &job, None)
+            .await
+            .unwrap();
+
+        let handle = ConfigHandle::new(config.clone());
+        let state = Arc::new(AppState {
+            config: handle.clone(),
+            reloader: ConfigReloader::new("server.toml", handle),
+            database: Some(database),
+            redis: None,
+            proxy: None,
+            started_at: Instant::now(),
+        });
+        create_router(state, Cors::new(&config.server.security).unwrap())
+    }
+
+    async fn call(router: &Router, method: Method, path: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_system_info_reports_pool_stats() {
+        let router = router().await;
+
+        let (status, body) = call(&router, Method::GET, "/api/system/info").await;
+        assert_eq!(status, StatusCode::OK);
+        let database = &body["data"]["database"];
+        assert_eq!(database["backend"], "sqlite");
+        assert_eq!(database["primary"]["healthy"], true);
+        assert!(database["primary"]["max_connections"].as_u64().unwrap() > 0);
+        assert!(body["data"].get("redis").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_job_and_container_routes() {
+        let router = router().await;
+
+        let (status, body) = call(&router, Method::GET, "/api/jobs?limit=10").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total"], 1);
+        assert_eq!(body["data"]["items"][0]["job_id"], "job_1");
+
+        let (status, body) = call(&router, Method::GET, "/api/jobs/job_1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["name"], "build");
+
+        let (status, body) = call(&router, Method::GET, "/api/jobs/missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "JOB_NOT_FOUND");
+
+        let (status, body) = call(&router, Method::GET, "/api/containers?sort=name").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total"], 0);
+
+        let (status, body) = call(&router, Method::POST, "/api/jobs").await;
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(body["error"]["code"], "NOT_SUPPORTED");
+        assert_eq!(
+            body["error"]["message"],
+            "POST /api/jobs is not supported by this server yet"
+        );
+        for path in ["/api/jobs/job_1/cancel", "/api/containers/c1/start"] {
+            let (status, _) = call(&router, Method::POST, path).await;
+            assert_eq!(status, StatusCode::NOT_IMPLEMENTED, "{}", path);
+        }
+        let (status, _) = call(&router, Method::GET, "/api/ws/updates").await;
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+
+        let (status, body) = call(&router, Method::GET, "/api/nothing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_drain_routes_need_the_proxy() {
+        let router = router().await;
+
+        let path = "/api/proxy/upstreams/api/servers/127.0.0.1:3001/drain";
+        for method in [Method::POST, Method::DELETE] {
+            let (status, body) = call(&router, method, path).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(body["error"]["code"], "SERVICE_UNAVAILABLE");
+        }
+    }
+}
@@ -1,3 +1,4 @@
 // This module is kept for backwards compatibility
 // The actual implementation is in handlers/static_files.rs
-pub use crate::handlers::static_files::*;
\ No newline at end of file
+#[allow(unused_imports)]
+pub use crate::handlers::static_files::*;
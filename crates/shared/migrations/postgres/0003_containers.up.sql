@@ -0,0 +1,15 @@
+CREATE TABLE containers (
+    id          TEXT PRIMARY KEY,
+    name        TEXT NOT NULL UNIQUE,
+    image       TEXT NOT NULL,
+    status      TEXT NOT NULL,
+    ports       TEXT[] NOT NULL DEFAULT '{}',
+    environment JSONB NOT NULL DEFAULT '{}',
+    labels      JSONB NOT NULL DEFAULT '{}',
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    started_at  TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX containers_created_at_idx ON containers (created_at DESC, id);
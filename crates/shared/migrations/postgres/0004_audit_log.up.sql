@@ -0,0 +1,13 @@
+CREATE TABLE audit_log (
+    id            BIGSERIAL PRIMARY KEY,
+    occurred_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    actor         TEXT,
+    action        TEXT NOT NULL,
+    resource_type TEXT NOT NULL,
+    resource_id   TEXT,
+    request_id    TEXT,
+    details       JSONB NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX audit_log_occurred_at_idx ON audit_log (occurred_at DESC);
+CREATE INDEX audit_log_resource_idx ON audit_log (resource_type, resource_id);
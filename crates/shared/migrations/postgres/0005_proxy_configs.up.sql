@@ -0,0 +1,10 @@
+-- Every saved proxy configuration is kept; at most one is active.
+CREATE TABLE proxy_configs (
+    version    BIGSERIAL PRIMARY KEY,
+    config     JSONB NOT NULL,
+    is_active  BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX proxy_configs_active_idx ON proxy_configs (is_active) WHERE is_active;
@@ -0,0 +1,25 @@
+-- Timestamps are RFC 3339 text in UTC; arrays and maps are JSON text.
+CREATE TABLE users (
+    id            TEXT PRIMARY KEY,
+    username      TEXT NOT NULL UNIQUE,
+    email         TEXT UNIQUE,
+    password_hash TEXT NOT NULL,
+    permissions   TEXT NOT NULL DEFAULT '[]',
+    is_active     BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at    TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at    TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE TABLE api_keys (
+    id          TEXT PRIMARY KEY,
+    name        TEXT NOT NULL,
+    key_hash    TEXT NOT NULL UNIQUE,
+    user_id     TEXT REFERENCES users (id) ON DELETE CASCADE,
+    permissions TEXT NOT NULL DEFAULT '[]',
+    created_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    expires_at  TEXT,
+    last_used   TEXT,
+    is_active   BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
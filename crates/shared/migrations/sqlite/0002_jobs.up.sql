@@ -0,0 +1,33 @@
+CREATE TABLE jobs (
+    id                   TEXT PRIMARY KEY,
+    name                 TEXT NOT NULL,
+    image                TEXT NOT NULL,
+    status               TEXT NOT NULL DEFAULT 'queued',
+    priority             TEXT NOT NULL DEFAULT 'normal',
+    spec                 TEXT NOT NULL,
+    progress             REAL,
+    container_id         TEXT,
+    output_files         TEXT NOT NULL DEFAULT '[]',
+    error_message        TEXT,
+    created_by           TEXT,
+    request_id           TEXT,
+    created_at           TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    started_at           TEXT,
+    finished_at          TEXT,
+    estimated_completion TEXT
+);
+
+CREATE INDEX jobs_status_created_at_idx ON jobs (status, created_at DESC);
+CREATE INDEX jobs_created_at_idx ON jobs (created_at DESC, id);
+
+CREATE TABLE job_attempts (
+    job_id        TEXT NOT NULL REFERENCES jobs (id) ON DELETE CASCADE,
+    attempt       INTEGER NOT NULL,
+    status        TEXT NOT NULL,
+    container_id  TEXT,
+    exit_code     INTEGER,
+    error_message TEXT,
+    started_at    TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    finished_at   TEXT,
+    PRIMARY KEY (job_id, attempt)
+);
@@ -0,0 +1,10 @@
+-- Every saved proxy configuration is kept; at most one is active.
+CREATE TABLE proxy_configs (
+    version    INTEGER PRIMARY KEY AUTOINCREMENT,
+    config     TEXT NOT NULL,
+    is_active  BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE UNIQUE INDEX proxy_configs_active_idx ON proxy_configs (is_active) WHERE is_active;
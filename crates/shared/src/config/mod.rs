@@ -0,0 +1,952 @@
+use crate::error::{ConfigError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub mod schema;
+mod sources;
+mod validation;
+mod values;
+
+pub use sources::{redact, redacted, Source, Sources};
+pub use validation::{Location, ValidationErrors, ValidationIssue};
+pub use values::{
+    BackoffStrategy, BalancingStrategy, ByteSize, CpuQuota, Environment, HashKey, HealthCheckType,
+    HumanDuration, LogFormat, ParseValueError, PullPolicy, RestartPolicy, RetryCondition,
+    StatusRange, UpstreamProtocol,
+};
+
+/// Root of `server.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// HTTP server settings.
+    pub server: ServerConfig,
+    /// Log output and tracing.
+    pub logging: LoggingConfig,
+    /// PostgreSQL connection pool.
+    pub database: DatabaseConfig,
+    /// Redis connection and job queues.
+    pub redis: RedisConfig,
+    /// Reverse proxy. Disabled when the section is absent.
+    pub proxy: Option<ProxyConfig>,
+    /// Docker container management. Disabled when the section is absent.
+    pub containers: Option<ContainerConfig>,
+    /// Sandboxed job execution. Disabled when the section is absent.
+    pub jobs: Option<JobConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address to bind the HTTP server to.
+    pub host: String,
+    /// Port to listen on.
+    pub port: u16,
+    /// Worker threads; 0 uses the number of CPU cores.
+    pub workers: usize,
+    /// How long in-flight requests may take to finish after SIGTERM.
+    pub shutdown_timeout: HumanDuration,
+    /// Deployment mode; `production` redacts internal errors in API responses.
+    pub environment: Environment,
+    /// TLS termination for the HTTP server.
+    pub tls: TlsConfig,
+    /// Static file serving from a local directory.
+    pub static_files: StaticConfig,
+    /// CORS, rate limiting and security headers.
+    pub security: SecurityConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Serve HTTPS instead of HTTP.
+    pub enabled: bool,
+    /// PEM certificate chain, used when `auto_cert` is off.
+    pub cert_file: Option<String>,
+    /// PEM private key, used when `auto_cert` is off.
+    pub key_file: Option<String>,
+    /// Obtain certificates from an ACME CA such as Let's Encrypt.
+    pub auto_cert: bool,
+    /// Domains to request certificates for.
+    pub domains: Vec<String>,
+    /// Contact address registered with the ACME CA.
+    pub acme_email: Option<String>,
+    /// ACME directory URL.
+    pub acme_directory: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct StaticConfig {
+    /// Serve files for requests that match no API route.
+    pub enabled: bool,
+    /// Directory to serve files from.
+    pub root: String,
+    /// Files tried, in order, for directory requests.
+    pub index_files: Vec<String>,
+    /// Compress responses for the listed content types.
+    pub compression: bool,
+    /// Content types eligible for compression.
+    pub compression_types: Vec<String>,
+    /// `Cache-Control` header sent with static files.
+    pub cache_control: String,
+    /// Send `ETag` headers.
+    pub etag: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// Allowed CORS origins; `*` allows any.
+    pub cors_origins: Vec<String>,
+    /// Allowed CORS methods.
+    pub cors_methods: Vec<String>,
+    /// Allowed CORS request headers.
+    pub cors_headers: Vec<String>,
+    /// Limit requests per client.
+    pub rate_limit_enabled: bool,
+    /// Requests allowed per window.
+    pub rate_limit_requests: u32,
+    /// Length of the rate limit window.
+    pub rate_limit_window: HumanDuration,
+    /// Send security headers with responses.
+    pub security_headers: bool,
+    /// `Strict-Transport-Security` max-age in seconds.
+    pub hsts_max_age: u32,
+    /// Send `X-Content-Type-Options: nosniff`.
+    pub content_type_nosniff: bool,
+    /// `X-Frame-Options` value: `DENY` or `SAMEORIGIN`.
+    pub frame_options: String,
+    /// Send `X-XSS-Protection: 1; mode=block`.
+    pub xss_protection: bool,
+    /// Key for signing list pagination cursors; random per process if unset.
+    pub cursor_secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Minimum level: trace, debug, info, warn or error.
+    pub level: String,
+    /// Output format.
+    pub format: LogFormat,
+    /// Where logs go: stdout, stderr or file.
+    pub output: String,
+    /// Log file, required when `output` is file.
+    pub file_path: Option<String>,
+    /// Log file rotation: daily, weekly or size.
+    pub rotation: String,
+    /// Rotated log files to keep.
+    pub max_files: u32,
+    /// Size at which the log file rotates when `rotation` is size.
+    pub max_file_size: ByteSize,
+    /// Distributed tracing export.
+    pub tracing: TracingConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TracingConfig {
+    /// Export spans to a tracing backend.
+    pub enabled: bool,
+    /// Jaeger collector endpoint.
+    pub jaeger_endpoint: Option<String>,
+    /// Service name attached to exported spans.
+    pub service_name: String,
+    /// Fraction of traces to sample, from 0.0 to 1.0.
+    pub sample_rate: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// `postgres://` or `sqlite://` connection URL; empty disables the database.
+    pub url: String,
+    /// Read-only PostgreSQL replicas; lists and lookups are spread across the reachable ones.
+    pub replica_urls: Vec<String>,
+    /// Maximum pooled connections.
+    pub max_connections: u32,
+    /// Connections kept open when idle.
+    pub min_connections: u32,
+    /// How long to wait for a pooled connection.
+    pub connection_timeout: HumanDuration,
+    /// Close connections idle for longer than this.
+    pub idle_timeout: HumanDuration,
+    /// Recycle connections older than this.
+    pub max_lifetime: HumanDuration,
+    /// Connection attempts at startup before giving up.
+    pub connect_attempts: u32,
+    /// Wait before the second connection attempt; doubled for each one after.
+    pub connect_backoff: HumanDuration,
+    /// Cancel statements running longer than this (PostgreSQL only); 0 disables.
+    pub statement_timeout: HumanDuration,
+    /// Log statements slower than this as warnings; 0 disables.
+    pub slow_query_threshold: HumanDuration,
+    /// Run pending migrations on startup.
+    pub auto_migrate: bool,
+    /// Directory of SQL migrations for this backend to run instead of the built-in ones; empty uses the built-in set.
+    pub migration_path: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedisConfig {
+    /// Redis connection URL; empty disables Redis.
+    pub url: String,
+    /// Maximum pooled connections.
+    pub pool_size: u32,
+    /// Timeout for establishing a connection.
+    pub connection_timeout: HumanDuration,
+    /// Timeout for a single command.
+    pub command_timeout: HumanDuration,
+    /// Retries for commands that fail on a connection error or timeout.
+    pub retry_attempts: u32,
+    /// Consecutive failures that open the circuit breaker; 0 disables it.
+    pub breaker_threshold: u32,
+    /// How long an open breaker fails commands fast before trying Redis again.
+    pub breaker_cooldown: HumanDuration,
+    /// Prefix for every key, so several deployments can share one Redis.
+    pub key_prefix: String,
+    /// Job queue names.
+    pub queue: QueueConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueConfig {
+    /// Queue new jobs are pushed to.
+    pub default_queue: String,
+    /// Queue for jobs waiting to be retried.
+    pub retry_queue: String,
+    /// Queue for jobs that exhausted their retries.
+    pub failed_queue: String,
+    /// Attempts before a job is moved to the failed queue.
+    pub max_retries: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Run the reverse proxy.
+    pub enabled: bool,
+    /// Address the proxy listens on for HTTP.
+    pub bind_address: String,
+    /// Redirect plain HTTP requests to HTTPS.
+    pub https_redirect: bool,
+    /// TLS termination for the proxy.
+    pub ssl: ProxySslConfig,
+    /// Default load balancing settings.
+    pub balancing: BalancingConfig,
+    /// Upstream health checks.
+    pub health: HealthConfig,
+    /// Named groups of backend servers.
+    pub upstreams: Vec<UpstreamConfig>,
+    /// Rules mapping requests to upstreams.
+    pub routes: Vec<RouteConfig>,
+    /// Request and response middleware.
+    pub middleware: MiddlewareConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProxySslConfig {
+    /// Terminate TLS on the proxy.
+    pub enabled: bool,
+    /// Address the proxy listens on for HTTPS.
+    pub bind_address: String,
+    /// Directory of PEM certificates.
+    pub cert_dir: String,
+    /// Directory of PEM private keys.
+    pub key_dir: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BalancingConfig {
+    /// Strategy for upstreams that do not set their own.
+    pub strategy: BalancingStrategy,
+    /// Send each client back to the server that first answered it, named
+    /// in a cookie, while that server is in rotation.
+    pub session_affinity: bool,
+    /// Cookie used for session affinity.
+    pub session_cookie: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    /// Actively probe upstream servers.
+    pub enabled: bool,
+    /// Time between probes.
+    pub interval: HumanDuration,
+    /// Probe timeout.
+    pub timeout: HumanDuration,
+    /// Consecutive successes before a server is marked healthy.
+    pub healthy_threshold: u32,
+    /// Consecutive failures before a server is marked unhealthy.
+    pub unhealthy_threshold: u32,
+    /// HTTP path probed on each server.
+    pub check_path: String,
+    /// `tcp` only checks that a connection opens; defaults to `http`.
+    pub check_type: Option<HealthCheckType>,
+    /// Statuses that pass an HTTP check; defaults to `["2xx", "3xx"]`.
+    pub expected_status: Option<Vec<StatusRange>>,
+    /// Text the body of an HTTP check response must contain.
+    pub expected_body: Option<String>,
+    /// How long a server takes to ramp up to its full weight after it
+    /// recovers; off when unset.
+    pub slow_start: Option<HumanDuration>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// Name referenced by `routes.upstream`.
+    pub name: String,
+    /// Load balancing strategy for this upstream; defaults to
+    /// `proxy.balancing.strategy`.
+    pub strategy: Option<BalancingStrategy>,
+    /// `h2` speaks HTTP/2 with prior knowledge; defaults to `http1`.
+    pub protocol: Option<UpstreamProtocol>,
+    /// What `ring_hash` and `maglev` hash on; defaults to `ip`.
+    pub hash_key: Option<HashKey>,
+    /// Stops sending requests while too many of them fail; off when unset.
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Backend servers.
+    pub servers: Vec<ServerInstanceConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Share of failed requests within `window`, from 0 to 1, that opens the breaker.
+    pub error_rate: f64,
+    /// Requests within `window` before the error rate counts.
+    pub min_requests: u32,
+    /// Period over which requests are counted.
+    pub window: HumanDuration,
+    /// How long the breaker stays open before letting a trial request through.
+    pub cooldown: HumanDuration,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ServerInstanceConfig {
+    /// Server address as `host:port`.
+    pub address: String,
+    /// Relative share of traffic, from 1 to 10000.
+    pub weight: u32,
+    /// Failures within `fail_timeout` before the server is ejected.
+    pub max_fails: u32,
+    /// Window for counting failures, and how long an ejected server stays out.
+    pub fail_timeout: HumanDuration,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    /// Path prefix to match; a trailing `/*` matches everything below it.
+    pub path: String,
+    /// HTTP method to match, or ANY.
+    pub method: String,
+    /// Upstream that handles matching requests.
+    pub upstream: String,
+    /// Set to false to forward the original path despite `strip_prefix`.
+    pub rewrite: Option<bool>,
+    /// Prefix removed from the path before forwarding.
+    pub strip_prefix: Option<String>,
+    /// Headers added to forwarded requests.
+    pub add_headers: Option<HashMap<String, String>>,
+    /// Time allowed for the response headers, across all attempts.
+    pub timeout: Option<HumanDuration>,
+    /// Time allowed to open a connection to a server.
+    pub connect_timeout: Option<HumanDuration>,
+    /// Time allowed for the response headers of each attempt.
+    pub header_timeout: Option<HumanDuration>,
+    /// Attempts after the first when one fails as listed in `retry_on`.
+    pub retries: Option<u32>,
+    /// Failures worth retrying; defaults to all of them.
+    pub retry_on: Option<Vec<RetryCondition>>,
+    /// Pass `Upgrade` requests, such as WebSocket and h2c, through to an
+    /// `http1` upstream.
+    pub websocket: Option<bool>,
+    /// Close an upgraded connection after this long without traffic.
+    pub upgrade_idle_timeout: Option<HumanDuration>,
+    /// Upgraded connections open at once; further upgrades get a 503.
+    pub max_upgraded_connections: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MiddlewareConfig {
+    /// Limit requests per client.
+    pub rate_limit_enabled: bool,
+    /// Requests allowed per window.
+    pub rate_limit_requests: u32,
+    /// Length of the rate limit window.
+    pub rate_limit_window: HumanDuration,
+    /// What identifies a client: ip, header or cookie.
+    pub rate_limit_key: String,
+    /// Headers added to requests.
+    pub add_request_headers: Option<HashMap<String, String>>,
+    /// Headers removed from requests.
+    pub remove_request_headers: Option<Vec<String>>,
+    /// Headers added to responses.
+    pub add_response_headers: Option<HashMap<String, String>>,
+    /// Headers removed from responses.
+    pub remove_response_headers: Option<Vec<String>>,
+    /// Compress responses.
+    pub compression_enabled: bool,
+    /// Compression level from 1 to 9.
+    pub compression_level: u32,
+    /// Content types eligible for compression.
+    pub compression_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerConfig {
+    /// Docker daemon address.
+    pub docker_host: String,
+    /// Docker API version.
+    pub api_version: String,
+    /// Timeout for Docker API calls.
+    pub timeout: HumanDuration,
+    /// Defaults for new containers.
+    pub defaults: ContainerDefaults,
+    /// Security options for new containers.
+    pub security: ContainerSecurity,
+    /// Container networking.
+    pub network: NetworkConfig,
+    /// Volume storage.
+    pub volumes: VolumeConfig,
+    /// Image pulling and cleanup.
+    pub images: ImageConfig,
+    /// Registries and their credentials.
+    pub registries: Vec<RegistryConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerDefaults {
+    /// CPU cores per container.
+    pub cpu_limit: CpuQuota,
+    /// Memory per container.
+    pub memory_limit: ByteSize,
+    /// Docker network mode.
+    pub network_mode: String,
+    /// Docker restart policy.
+    pub restart_policy: RestartPolicy,
+    /// Docker log driver.
+    pub log_driver: String,
+    /// Options passed to the log driver.
+    pub log_options: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerSecurity {
+    /// Linux capabilities to drop.
+    pub drop_capabilities: Vec<String>,
+    /// Linux capabilities to add back after dropping.
+    pub add_capabilities: Vec<String>,
+    /// User to run as, as `uid:gid`.
+    pub user: String,
+    /// Mount the root filesystem read-only.
+    pub read_only: bool,
+    /// Block privilege escalation through setuid binaries.
+    pub no_new_privileges: bool,
+    /// Seccomp profile name or path.
+    pub seccomp_profile: String,
+    /// AppArmor profile name.
+    pub apparmor_profile: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Network containers are attached to.
+    pub default_network: String,
+    /// Isolate containers from each other.
+    pub enable_isolation: bool,
+    /// DNS servers for containers.
+    pub dns_servers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VolumeConfig {
+    /// Directory holding volume data.
+    pub base_path: String,
+    /// Mount options applied to volumes.
+    pub default_options: Vec<String>,
+    /// Remove volumes no container uses.
+    pub cleanup_orphaned: bool,
+    /// Time between orphaned volume sweeps.
+    pub cleanup_interval: HumanDuration,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ImageConfig {
+    /// Pull images that are not present locally.
+    pub auto_pull: bool,
+    /// When to pull images.
+    pub pull_policy: PullPolicy,
+    /// Remove images no container uses.
+    pub cleanup_unused: bool,
+    /// Time between image cleanup sweeps.
+    pub cleanup_interval: HumanDuration,
+    /// Keep tagged images during cleanup.
+    pub keep_tagged: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Registry hostname, as used in image references.
+    pub name: String,
+    /// Registry API URL.
+    pub url: String,
+    /// Registry username.
+    pub username: String,
+    /// Registry password or token.
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct JobConfig {
+    /// Queue used when a job does not name one.
+    pub default_queue: String,
+    /// Jobs allowed to run at once.
+    pub max_concurrent_jobs: u32,
+    /// Maximum run time for a job.
+    pub job_timeout: HumanDuration,
+    /// Delete finished jobs after `cleanup_after`.
+    pub cleanup_completed: bool,
+    /// How long finished jobs are kept.
+    pub cleanup_after: HumanDuration,
+    /// Job worker pool.
+    pub workers: WorkerConfig,
+    /// Container settings for jobs.
+    pub container: JobContainerConfig,
+    /// Sandbox settings for jobs.
+    pub security: JobSecurityConfig,
+    /// Job input and output files.
+    pub files: FileConfig,
+    /// Retry policy for failed jobs.
+    pub retry: RetryConfig,
+    /// Job metrics and log collection.
+    pub monitoring: MonitoringConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WorkerConfig {
+    /// Number of workers.
+    pub count: u32,
+    /// Time between queue polls.
+    pub poll_interval: HumanDuration,
+    /// Jobs fetched per poll.
+    pub batch_size: u32,
+    /// Memory available to each worker.
+    pub max_memory: ByteSize,
+    /// CPU cores available to each worker.
+    pub max_cpu: CpuQuota,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct JobContainerConfig {
+    /// Image used when a job does not name one.
+    pub base_image: String,
+    /// Docker network mode for job containers.
+    pub network_mode: String,
+    /// CPU cores per job.
+    pub cpu_limit: CpuQuota,
+    /// Memory per job.
+    pub memory_limit: ByteSize,
+    /// Disk space per job.
+    pub disk_limit: ByteSize,
+    /// Maximum run time for a job container.
+    pub timeout: HumanDuration,
+    /// Remove job containers after they finish.
+    pub cleanup: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct JobSecurityConfig {
+    /// User to run as, as `uid:gid`.
+    pub user: String,
+    /// Mount the root filesystem read-only.
+    pub read_only: bool,
+    /// Writable directories inside the job container.
+    pub work_dirs: Vec<String>,
+    /// Linux capabilities to drop.
+    pub drop_capabilities: Vec<String>,
+    /// Run jobs without network access.
+    pub no_network: bool,
+    /// Maximum processes per job.
+    pub max_processes: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Staging directory for job inputs.
+    pub input_dir: String,
+    /// Directory collecting job outputs.
+    pub output_dir: String,
+    /// Maximum size of a job input file.
+    pub max_input_size: ByteSize,
+    /// Maximum total output per job.
+    pub max_output_size: ByteSize,
+    /// How long job files are kept.
+    pub retention_period: HumanDuration,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Attempts before a job fails permanently.
+    pub max_attempts: u32,
+    /// How the delay grows between attempts.
+    pub backoff_strategy: BackoffStrategy,
+    /// Delay before the first retry.
+    pub base_delay: HumanDuration,
+    /// Upper bound on the delay.
+    pub max_delay: HumanDuration,
+    /// Randomize delays to spread out retries.
+    pub jitter: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MonitoringConfig {
+    /// Collect job metrics.
+    pub collect_metrics: bool,
+    /// Time between metric samples.
+    pub metrics_interval: HumanDuration,
+    /// Record CPU and memory use.
+    pub monitor_resources: bool,
+    /// Store job container logs.
+    pub collect_logs: bool,
+    /// Minimum level of job logs to store.
+    pub log_level: String,
+}
+
+impl Config {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::load_with_sources(path)?.0)
+    }
+
+    /// Like [`Config::load_from_file`], also recording where each value came from.
+    pub fn load_with_sources<P: AsRef<Path>>(path: P) -> Result<(Self, Sources)> {
+        let content = Self::read(path.as_ref())?;
+
+        let (config, mut errors) = Self::parse_values(&content)?;
+        let Some(mut config) = config else {
+            errors.locate(&content);
+            return Err(ConfigError::Validation(errors).into());
+        };
+        let env = config.apply_env_overrides();
+
+        errors.issues.extend(validation::validate(&config).issues);
+        if !errors.is_empty() {
+            errors.locate(&content);
+            return Err(ConfigError::Validation(errors).into());
+        }
+
+        Ok((config, Sources::new(path.as_ref(), &content, env)))
+    }
+
+    /// Parses a file as written, without env overrides or validation.
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&Self::read(path.as_ref())?)
+    }
+
+    fn read(path: &Path) -> Result<String> {
+        Ok(
+            fs::read_to_string(path).map_err(|_| ConfigError::FileNotFound {
+                path: path.display().to_string(),
+            })?,
+        )
+    }
+
+    /// Parses TOML without applying env overrides or validating, except
+    /// that every malformed typed value is reported.
+    pub fn parse(content: &str) -> Result<Self> {
+        match Self::parse_values(content)? {
+            (Some(config), errors) if errors.is_empty() => Ok(config),
+            (_, mut errors) => {
+                errors.locate(content);
+                Err(ConfigError::Validation(errors).into())
+            }
+        }
+    }
+
+    /// Parses TOML, collecting all malformed typed values. The config, when
+    /// there are some, has them left at their defaults, and is `None` if the
+    /// rest cannot be deserialized without them.
+    fn parse_values(content: &str) -> Result<(Option<Self>, ValidationErrors)> {
+        let errors = validation::check_values(content);
+        if errors.is_empty() {
+            return Ok((Some(Self::deserialize(content)?), errors));
+        }
+        let config =
+            validation::strip(content, &errors).and_then(|rest| Self::deserialize(&rest).ok());
+        Ok((config, errors))
+    }
+
+    fn deserialize(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|error| {
+            match schema::suggest_field(error.message()) {
+                Some(suggestion) => ConfigError::UnknownField { error, suggestion },
+                None => ConfigError::Parse(error),
+            }
+            .into()
+        })
+    }
+
+    pub fn load_from_env() -> Result<Self> {
+        Self::load_from_file(Self::path_from_env())
+    }
+
+    pub fn path_from_env() -> PathBuf {
+        env::var("CONTAINER_CODES_CONFIG")
+            .unwrap_or_else(|_| "config/server.toml".to_string())
+            .into()
+    }
+
+    /// Applies `CONTAINER_CODES_*` variables, returning which variable set
+    /// each config path.
+    fn apply_env_overrides(&mut self) -> HashMap<&'static str, String> {
+        let mut applied = HashMap::new();
+        for (key, value) in env::vars() {
+            if let Some(config_key) = key.strip_prefix("CONTAINER_CODES_") {
+                if let Some(path) = self.set_from_env_key(config_key, &value) {
+                    applied.insert(path, key);
+                }
+            }
+        }
+        applied
+    }
+
+    fn set_from_env_key(&mut self, key: &str, value: &str) -> Option<&'static str> {
+        let parts: Vec<&str> = key.split('_').collect();
+
+        match parts.as_slice() {
+            ["SERVER", "PORT"] => {
+                self.server.port = value.parse().ok()?;
+                Some("server.port")
+            }
+            ["SERVER", "HOST"] => {
+                self.server.host = value.to_string();
+                Some("server.host")
+            }
+            ["SERVER", "ENVIRONMENT"] => {
+                self.server.environment = match value {
+                    "development" => Environment::Development,
+                    "production" => Environment::Production,
+                    _ => return None,
+                };
+                Some("server.environment")
+            }
+            ["SERVER", "SECURITY", "CURSOR", "SECRET"] => {
+                self.server.security.cursor_secret = Some(value.to_string());
+                Some("server.security.cursor_secret")
+            }
+            ["DATABASE", "URL"] => {
+                self.database.url = value.to_string();
+                Some("database.url")
+            }
+            ["REDIS", "URL"] => {
+                self.redis.url = value.to_string();
+                Some("redis.url")
+            }
+            ["LOGGING", "LEVEL"] => {
+                self.logging.level = value.to_string();
+                Some("logging.level")
+            }
+            _ => {
+                // Handle nested configurations with double underscores
+                // Implementation would be more complex for full support
+                None
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let errors = validation::validate(self);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(errors).into())
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            workers: 0,
+            shutdown_timeout: HumanDuration::from_secs(30),
+            environment: Environment::Production,
+            tls: TlsConfig::default(),
+            static_files: StaticConfig::default(),
+            security: SecurityConfig::default(),
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_file: None,
+            key_file: None,
+            auto_cert: false,
+            domains: vec![],
+            acme_email: None,
+            acme_directory: "https://acme-v02.api.letsencrypt.org/directory".to_string(),
+        }
+    }
+}
+
+impl Default for StaticConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            root: "./public".to_string(),
+            index_files: vec!["index.html".to_string(), "index.htm".to_string()],
+            compression: true,
+            compression_types: vec![
+                "text/html".to_string(),
+                "text/css".to_string(),
+                "application/javascript".to_string(),
+                "application/json".to_string(),
+            ],
+            cache_control: "public, max-age=3600".to_string(),
+            etag: true,
+        }
+    }
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            cors_origins: vec!["*".to_string()],
+            cors_methods: vec![
+                "GET".to_string(),
+                "POST".to_string(),
+                "PUT".to_string(),
+                "DELETE".to_string(),
+            ],
+            cors_headers: vec!["Content-Type".to_string(), "Authorization".to_string()],
+            rate_limit_enabled: false,
+            rate_limit_requests: 100,
+            rate_limit_window: HumanDuration::from_secs(60),
+            security_headers: true,
+            hsts_max_age: 31536000,
+            content_type_nosniff: true,
+            frame_options: "DENY".to_string(),
+            xss_protection: true,
+            cursor_secret: None,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+            output: "stdout".to_string(),
+            file_path: None,
+            rotation: "daily".to_string(),
+            max_files: 30,
+            max_file_size: ByteSize::from_bytes(100 << 20),
+            tracing: TracingConfig::default(),
+        }
+    }
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            jaeger_endpoint: None,
+            service_name: "container-codes".to_string(),
+            sample_rate: 0.1,
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: "postgresql://localhost/container_codes".to_string(),
+            replica_urls: Vec::new(),
+            max_connections: 50,
+            min_connections: 5,
+            connection_timeout: HumanDuration::from_secs(30),
+            idle_timeout: HumanDuration::from_secs(600),
+            max_lifetime: HumanDuration::from_secs(3600),
+            connect_attempts: 5,
+            connect_backoff: HumanDuration::from_secs(1),
+            statement_timeout: HumanDuration::from_secs(30),
+            slow_query_threshold: HumanDuration::from_secs(1),
+            auto_migrate: false,
+            migration_path: String::new(),
+        }
+    }
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+            pool_size: 50,
+            connection_timeout: HumanDuration::from_secs(5),
+            command_timeout: HumanDuration::from_secs(30),
+            retry_attempts: 3,
+            breaker_threshold: 5,
+            breaker_cooldown: HumanDuration::from_secs(30),
+            key_prefix: "container-codes".to_string(),
+            queue: QueueConfig::default(),
+        }
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            default_queue: "jobs".to_string(),
+            retry_queue: "retry".to_string(),
+            failed_queue: "failed".to_string(),
+            max_retries: 3,
+        }
+    }
+}
+
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    s.parse::<HumanDuration>()
+        .map(Duration::from)
+        .map_err(|_| crate::Error::config_invalid("duration", s))
+}
+
+/// Parses Docker-style sizes such as `512m` or `1g` into bytes.
+pub fn parse_byte_size(s: &str) -> Result<u64> {
+    s.parse::<ByteSize>()
+        .map(|size| size.as_bytes())
+        .map_err(|_| crate::Error::config_invalid("byte size", s))
+}
@@ -0,0 +1,235 @@
+use super::Config;
+use crate::Result;
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, Table};
+
+/// File name editors look for next to `server.toml`, see [`default_toml`].
+pub const SCHEMA_FILE: &str = "server.schema.json";
+
+/// JSON Schema for `server.toml`, generated from the [`Config`] structs.
+pub fn json_schema() -> Value {
+    schemars::schema_for!(Config).to_value()
+}
+
+/// Example values for the optional keys and sections, see [`default_toml`].
+const EXAMPLE: &str = include_str!("example.toml");
+
+/// A complete `server.toml` holding [`Config::default`], with each key
+/// commented from its doc comment. Optional keys and sections are included
+/// commented out, with the values from `example.toml`.
+pub fn default_toml() -> Result<String> {
+    let schema = json_schema();
+    let defaults = to_document(&Config::default())?;
+    let mut document = to_document(&Config::parse(EXAMPLE)?)?;
+    annotate(document.as_table_mut(), &schema, &schema);
+
+    let mut optional = DocumentMut::new();
+    let sections: Vec<String> = document
+        .iter()
+        .filter(|(key, _)| !defaults.contains_key(key))
+        .map(|(key, _)| key.to_string())
+        .collect();
+    for key in sections {
+        if let Some(item) = document.remove(&key) {
+            optional.insert(&key, item);
+        }
+    }
+    comment_out(document.as_table_mut(), defaults.as_table());
+
+    let optional: String = optional
+        .to_string()
+        .lines()
+        .map(|line| match line {
+            "" => "\n".to_string(),
+            line if line.starts_with('#') => format!("{}\n", line),
+            line => format!("# {}\n", line),
+        })
+        .collect();
+
+    Ok(format!(
+        "#:schema ./{}\n# Container Codes server configuration.\n{}{}",
+        SCHEMA_FILE, document, optional
+    ))
+}
+
+fn to_document(config: &Config) -> Result<DocumentMut> {
+    toml::to_string(config)
+        .map_err(|e| crate::Error::internal(e.to_string()))?
+        .parse()
+        .map_err(|e: toml_edit::TomlError| crate::Error::internal(e.to_string()))
+}
+
+/// Comments out the keys of `table` that `defaults` does not have.
+fn comment_out(table: &mut Table, defaults: &Table) {
+    for (mut key, item) in table.iter_mut() {
+        match (item, defaults.get(key.get())) {
+            (Item::Table(child), Some(Item::Table(defaults))) => comment_out(child, defaults),
+            (Item::Value(_), None) => {
+                let prefix = key
+                    .leaf_decor()
+                    .prefix()
+                    .and_then(|prefix| prefix.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                key.leaf_decor_mut().set_prefix(format!("{}# ", prefix));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn annotate(table: &mut Table, schema: &Value, root: &Value) {
+    let properties = &resolve(schema, root)["properties"];
+
+    for (index, (mut key, item)) in table.iter_mut().enumerate() {
+        let property = &properties[key.get()];
+        let comment = describe(property, root);
+
+        match item {
+            Item::Table(child) => {
+                if let Some(comment) = &comment {
+                    child.decor_mut().set_prefix(format!("\n# {}\n", comment));
+                }
+                annotate(child, property, root);
+            }
+            Item::ArrayOfTables(array) => {
+                let items = &resolve(property, root)["items"];
+                for (index, child) in array.iter_mut().enumerate() {
+                    if let (0, Some(comment)) = (index, &comment) {
+                        child.decor_mut().set_prefix(format!("\n# {}\n", comment));
+                    }
+                    annotate(child, items, root);
+                }
+            }
+            _ => {
+                if let Some(comment) = &comment {
+                    let gap = if index == 0 { "" } else { "\n" };
+                    key.leaf_decor_mut()
+                        .set_prefix(format!("{}# {}\n", gap, comment));
+                }
+            }
+        }
+    }
+}
+
+fn describe(property: &Value, root: &Value) -> Option<String> {
+    let resolved = resolve(property, root);
+    let mut comment = property["description"]
+        .as_str()
+        .or_else(|| resolved["description"].as_str())?
+        .replace('\n', " ");
+
+    if let Some(values) = resolved["enum"].as_array() {
+        let values: Vec<&str> = values.iter().filter_map(Value::as_str).collect();
+        comment.push_str(&format!(" One of: {}.", values.join(", ")));
+    }
+
+    Some(comment)
+}
+
+/// Follows `$ref`s and unwraps `Option`'s `anyOf: [T, null]`.
+pub(super) fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    if let Some(pointer) = schema["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+        return root.pointer(pointer).map_or(schema, |s| resolve(s, root));
+    }
+
+    if let Some(variants) = schema["anyOf"].as_array() {
+        if let Some(inner) = variants.iter().find(|v| v["type"] != "null") {
+            return resolve(inner, root);
+        }
+    }
+
+    schema
+}
+
+/// For serde's "unknown field `x`, expected one of `a`, `b`" errors, the
+/// expected field closest to the misspelled one.
+pub(super) fn suggest_field(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("unknown field `")?;
+    let (unknown, expected) = rest.split_once('`')?;
+
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .filter_map(|candidate| {
+            let distance = edit_distance(unknown, candidate);
+            let close = distance <= (unknown.len() / 3).max(2)
+                || candidate.starts_with(unknown)
+                || unknown.starts_with(candidate);
+            close.then_some((distance, candidate))
+        })
+        .min()
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_committed_schema_is_current() {
+        let committed: Value =
+            serde_json::from_str(include_str!("../../../../config/server.schema.json")).unwrap();
+        assert!(
+            committed == json_schema(),
+            "config/server.schema.json is stale, regenerate it with \
+             `container-codes config schema > config/server.schema.json`"
+        );
+    }
+
+    #[test]
+    fn test_default_toml_round_trips() {
+        let source = default_toml().unwrap();
+        let config: Config = toml::from_str(&source).unwrap();
+
+        assert!(source.contains("# Port to listen on.\nport = 8080"));
+        assert!(source.contains("One of: json, pretty, compact."));
+        assert_eq!(config.server.port, Config::default().server.port);
+    }
+
+    #[test]
+    fn test_default_toml_comments_out_optional_settings() {
+        let source = default_toml().unwrap();
+        let config = Config::parse(&source).unwrap();
+
+        assert!(source.contains("\n# cert_file = \"/etc/container-codes/tls/cert.pem\"\n"));
+        assert!(source.contains("\n# [proxy]\n"));
+        assert!(source.contains("\n# [[proxy.upstreams]]\n"));
+        assert!(source.contains("\n# [jobs.retry]\n"));
+        assert!(config.server.tls.cert_file.is_none());
+        assert!(config.proxy.is_none() && config.containers.is_none() && config.jobs.is_none());
+    }
+
+    #[test]
+    fn test_unknown_field_suggestion() {
+        let err = toml::from_str::<Config>("[server]\nhots = \"0.0.0.0\"").unwrap_err();
+        assert_eq!(suggest_field(err.message()).as_deref(), Some("host"));
+
+        let err = toml::from_str::<Config>("[server.static]\nenabled = true").unwrap_err();
+        assert_eq!(
+            suggest_field(err.message()).as_deref(),
+            Some("static_files")
+        );
+
+        let err = toml::from_str::<Config>("[server]\nfoo = 1").unwrap_err();
+        assert_eq!(suggest_field(err.message()), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Where effective config values come from, and hiding secrets when they
+//! are displayed.
+
+use super::validation::location_at;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use toml_edit::{ImDocument, Item, Table};
+
+const REDACTED: &str = "<redacted>";
+
+/// Origin of one effective config value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File { path: String, line: usize },
+    Env(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File { path, line } => write!(f, "{}:{}", path, line),
+            Source::Env(var) => write!(f, "env {}", var),
+        }
+    }
+}
+
+/// Records what a loaded config was built from; see [`Config::load_with_sources`](super::Config::load_with_sources).
+pub struct Sources {
+    path: String,
+    content: String,
+    document: Option<ImDocument<String>>,
+    env: HashMap<&'static str, String>,
+}
+
+impl Sources {
+    pub(super) fn new(path: &Path, content: &str, env: HashMap<&'static str, String>) -> Self {
+        Self {
+            path: path.display().to_string(),
+            content: content.to_string(),
+            document: ImDocument::parse(content.to_string()).ok(),
+            env,
+        }
+    }
+
+    /// Origin of the value at a dotted path such as `proxy.routes[1].upstream`.
+    pub fn of(&self, path: &str) -> Source {
+        if let Some(var) = self.env.get(path) {
+            return Source::Env(var.clone());
+        }
+        self.document
+            .as_ref()
+            .and_then(|document| find_key(document.as_table(), path))
+            .map_or(Source::Default, |offset| Source::File {
+                path: self.path.clone(),
+                line: location_at(&self.content, offset).line,
+            })
+    }
+}
+
+/// Byte offset of the key at `path`, only if the file sets it itself.
+fn find_key(root: &Table, path: &str) -> Option<usize> {
+    let mut table = root;
+    let mut segments = path.split('.').peekable();
+
+    while let Some(segment) = segments.next() {
+        let (key, index) = match segment.split_once('[') {
+            Some((key, rest)) => (key, Some(rest.trim_end_matches(']').parse::<usize>().ok()?)),
+            None => (segment, None),
+        };
+        let (key, item) = table.get_key_value(key)?;
+        let last = segments.peek().is_none();
+
+        table = match (item, index) {
+            (Item::ArrayOfTables(tables), Some(i)) if !last => tables.get(i)?,
+            (Item::Table(t), None) if !last => t,
+            (Item::Value(toml_edit::Value::InlineTable(t)), None) if !last => {
+                let rest: Vec<_> = segments.collect();
+                let (key, _) = t.get_key_value(&rest.join("."))?;
+                return key.span().map(|s| s.start);
+            }
+            (_, _) if last => return key.span().map(|s| s.start),
+            _ => return None,
+        };
+    }
+    None
+}
+
+/// Replaces secrets in a serialized config: passwords, tokens and secrets,
+/// `acme_email`, and the password in any URL with credentials.
+pub fn redact(value: &mut Value) {
+    redact_at("", value);
+}
+
+/// `value` at `path` with secrets replaced, as [`redact`] would.
+pub fn redacted(path: &str, value: &Value) -> Value {
+    let mut value = value.clone();
+    redact_at(path, &mut value);
+    value
+}
+
+fn redact_at(path: &str, value: &mut Value) {
+    let key = path.rsplit('.').next().unwrap_or(path);
+    let key = key.split('[').next().unwrap_or(key);
+    match value {
+        Value::Object(map) => {
+            for (child, value) in map.iter_mut() {
+                let child_path = if path.is_empty() {
+                    child.clone()
+                } else {
+                    format!("{}.{}", path, child)
+                };
+                redact_at(&child_path, value);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                redact_at(&format!("{}[{}]", path, i), item);
+            }
+        }
+        Value::String(s) if !s.is_empty() && is_secret(key) => *s = REDACTED.to_string(),
+        Value::String(s) => {
+            if let Some(url) = redact_url(s) {
+                *s = url;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_secret(key: &str) -> bool {
+    key == "acme_email"
+        || ["password", "secret", "token"]
+            .iter()
+            .any(|word| key.contains(word))
+}
+
+/// `scheme://user:<redacted>@host/...` for a URL carrying a password.
+fn redact_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let (userinfo, host) = rest[..authority_end].rsplit_once('@')?;
+    let (user, _password) = userinfo.split_once(':')?;
+    Some(format!(
+        "{}://{}:{}@{}{}",
+        scheme,
+        user,
+        REDACTED,
+        host,
+        &rest[authority_end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_sources_and_redaction() {
+        let content =
+            "[server]\nport = 9000\n\n[database]\nurl = \"postgres://cc:hunter2@db:5432/cc\"\n";
+        let env = HashMap::from([("server.host", "CONTAINER_CODES_SERVER_HOST".to_string())]);
+        let sources = Sources::new(Path::new("server.toml"), content, env);
+
+        assert_eq!(
+            sources.of("server.port"),
+            Source::File {
+                path: "server.toml".into(),
+                line: 2
+            }
+        );
+        assert_eq!(
+            sources.of("server.host"),
+            Source::Env("CONTAINER_CODES_SERVER_HOST".into())
+        );
+        assert_eq!(sources.of("server.workers"), Source::Default);
+
+        let mut value = serde_json::to_value(Config::parse(content).unwrap()).unwrap();
+        value["server"]["tls"]["acme_email"] = "ops@example.com".into();
+        redact(&mut value);
+        assert_eq!(
+            value["database"]["url"],
+            "postgres://cc:<redacted>@db:5432/cc"
+        );
+        assert_eq!(value["server"]["tls"]["acme_email"], REDACTED);
+        assert_eq!(value["server"]["port"], 9000);
+    }
+}
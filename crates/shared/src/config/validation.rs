@@ -0,0 +1,879 @@
+use super::{schema, values, Config};
+use serde::Serialize;
+use serde_json::Value as Json;
+use std::fmt;
+use std::path::Path;
+use toml_edit::{DocumentMut, ImDocument, Item, Table, Value};
+
+const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error"];
+const LOG_OUTPUTS: &[&str] = &["stdout", "stderr", "file"];
+const LOG_ROTATIONS: &[&str] = &["daily", "weekly", "size"];
+const FRAME_OPTIONS: &[&str] = &["DENY", "SAMEORIGIN"];
+const ROUTE_METHODS: &[&str] = &[
+    "GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS", "ANY",
+];
+const RATE_LIMIT_KEYS: &[&str] = &["ip", "header", "cookie"];
+const MAX_SERVER_WEIGHT: u32 = 10_000;
+
+/// Position of a key in the source TOML, both 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub path: String,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some(loc) => write!(
+                f,
+                "{} (line {}, column {}): {}",
+                self.path, loc.line, loc.column, self.message
+            ),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Every problem found in a config, not just the first one.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationErrors {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationErrors {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter()
+    }
+
+    /// Fills in line and column for each issue from the TOML it was loaded from.
+    pub fn locate(&mut self, source: &str) {
+        let Ok(document) = ImDocument::parse(source) else {
+            return;
+        };
+
+        for issue in &mut self.issues {
+            issue.location = find_span(document.as_table(), &issue.path)
+                .map(|offset| location_at(source, offset));
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid value(s)", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the typed values in `source` (durations, sizes, enums and the
+/// like) before deserialization, which would stop at the first bad one.
+/// Returns nothing if `source` is not valid TOML; serde reports that.
+pub(super) fn check_values(source: &str) -> ValidationErrors {
+    let Ok(document) = ImDocument::parse(source) else {
+        return ValidationErrors::default();
+    };
+    let root = schema::json_schema();
+    let mut v = Validator::default();
+    check_table(&mut v, document.as_table(), "", &root, &root);
+    ValidationErrors { issues: v.issues }
+}
+
+/// `source` without the values `errors` point at, so that the rest of it
+/// can still be deserialized and validated.
+pub(super) fn strip(source: &str, errors: &ValidationErrors) -> Option<String> {
+    let mut document: DocumentMut = source.parse().ok()?;
+    for issue in errors.iter() {
+        // A bad array element takes the whole array with it.
+        let path = issue
+            .path
+            .strip_suffix(']')
+            .map_or(issue.path.as_str(), |p| {
+                p.rsplit_once('[').map_or(p, |(p, _)| p)
+            });
+        let mut item = document.as_item_mut();
+        for segment in path.split('.') {
+            let (key, index) = match segment.split_once('[') {
+                Some((key, rest)) => (key, rest.trim_end_matches(']').parse::<usize>().ok()),
+                None => (segment, None),
+            };
+            item = item.get_mut(key)?;
+            if let Some(i) = index {
+                item = item.get_mut(i)?;
+            }
+        }
+        *item = Item::None;
+    }
+    Some(document.to_string())
+}
+
+fn check_table(v: &mut Validator, table: &Table, path: &str, schema: &Json, root: &Json) {
+    let schema = schema::resolve(schema, root);
+    for (key, item) in table.iter() {
+        // Unknown keys are left to serde, which suggests a correction.
+        let Some(property) = property(schema, key) else {
+            continue;
+        };
+        let path = if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        match item {
+            Item::Table(table) => check_table(v, table, &path, property, root),
+            Item::ArrayOfTables(tables) => {
+                let items = &schema::resolve(property, root)["items"];
+                for (i, table) in tables.iter().enumerate() {
+                    check_table(v, table, &format!("{}[{}]", path, i), items, root);
+                }
+            }
+            Item::Value(value) => check_value(v, value, &path, property, root),
+            Item::None => {}
+        }
+    }
+}
+
+fn check_value(v: &mut Validator, value: &Value, path: &str, schema: &Json, root: &Json) {
+    if let Some(result) = kind(schema).and_then(|kind| values::check_raw(kind, value)) {
+        if let Err(message) = result {
+            v.error(path, message);
+        }
+        return;
+    }
+
+    let schema = schema::resolve(schema, root);
+    if let Some(allowed) = schema["enum"].as_array() {
+        let allowed: Vec<&str> = allowed.iter().filter_map(Json::as_str).collect();
+        match value.as_str() {
+            Some(s) => v.one_of(path, s, &allowed),
+            None => v.error(path, format!("expected one of: {}", allowed.join(", "))),
+        }
+        return;
+    }
+    match value {
+        Value::InlineTable(table) => {
+            for (key, value) in table.iter() {
+                if let Some(property) = property(schema, key) {
+                    check_value(v, value, &format!("{}.{}", path, key), property, root);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                check_value(
+                    v,
+                    value,
+                    &format!("{}[{}]", path, i),
+                    &schema["items"],
+                    root,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Schema of `key` in an object schema, including map entries.
+fn property<'a>(schema: &'a Json, key: &str) -> Option<&'a Json> {
+    schema["properties"].get(key).or_else(|| {
+        schema["additionalProperties"]
+            .as_object()
+            .map(|_| &schema["additionalProperties"])
+    })
+}
+
+/// Name of the type a schema refers to, looking inside `Option`'s `anyOf`.
+fn kind(schema: &Json) -> Option<&str> {
+    if let Some(name) = schema["$ref"]
+        .as_str()
+        .and_then(|r| r.strip_prefix("#/$defs/"))
+    {
+        return Some(name);
+    }
+    schema["anyOf"].as_array()?.iter().find_map(kind)
+}
+
+pub(super) fn validate(config: &Config) -> ValidationErrors {
+    let mut v = Validator::default();
+
+    let server = &config.server;
+    v.check(
+        server.port != 0,
+        "server.port",
+        "must be between 1 and 65535",
+    );
+    v.check(!server.host.is_empty(), "server.host", "must not be empty");
+
+    let tls = &server.tls;
+    if tls.enabled {
+        if tls.auto_cert {
+            v.check(
+                !tls.domains.is_empty(),
+                "server.tls.domains",
+                "required when auto_cert is enabled",
+            );
+            v.check(
+                tls.acme_email.is_some(),
+                "server.tls.acme_email",
+                "required when auto_cert is enabled",
+            );
+            v.url("server.tls.acme_directory", &tls.acme_directory, &["https"]);
+        } else {
+            v.file("server.tls.cert_file", tls.cert_file.as_deref());
+            v.file("server.tls.key_file", tls.key_file.as_deref());
+        }
+    }
+
+    let security = &server.security;
+    v.one_of(
+        "server.security.frame_options",
+        &security.frame_options,
+        FRAME_OPTIONS,
+    );
+
+    let logging = &config.logging;
+    v.one_of("logging.level", &logging.level.to_lowercase(), LOG_LEVELS);
+    v.one_of("logging.output", &logging.output, LOG_OUTPUTS);
+    v.one_of("logging.rotation", &logging.rotation, LOG_ROTATIONS);
+    if logging.output == "file" {
+        v.check(
+            logging.file_path.is_some(),
+            "logging.file_path",
+            "required when output is \"file\"",
+        );
+    }
+    v.check(
+        (0.0..=1.0).contains(&logging.tracing.sample_rate),
+        "logging.tracing.sample_rate",
+        "must be between 0.0 and 1.0",
+    );
+    if let Some(endpoint) = &logging.tracing.jaeger_endpoint {
+        v.url(
+            "logging.tracing.jaeger_endpoint",
+            endpoint,
+            &["http", "https"],
+        );
+    }
+
+    let database = &config.database;
+    if !database.url.is_empty() {
+        v.url(
+            "database.url",
+            &database.url,
+            &["postgres", "postgresql", "sqlite"],
+        );
+    }
+    v.check(
+        database.min_connections <= database.max_connections,
+        "database.min_connections",
+        "must not exceed max_connections",
+    );
+    v.check(
+        database.connect_attempts > 0,
+        "database.connect_attempts",
+        "must be greater than 0",
+    );
+    for (i, url) in database.replica_urls.iter().enumerate() {
+        v.url(
+            &format!("database.replica_urls[{}]", i),
+            url,
+            &["postgres", "postgresql"],
+        );
+    }
+    v.check(
+        database.replica_urls.is_empty() || !database.url.starts_with("sqlite:"),
+        "database.replica_urls",
+        "read replicas require a PostgreSQL database",
+    );
+
+    let redis = &config.redis;
+    if !redis.url.is_empty() {
+        v.url("redis.url", &redis.url, &["redis", "rediss", "unix"]);
+    }
+    v.check(
+        redis.pool_size > 0,
+        "redis.pool_size",
+        "must be greater than 0",
+    );
+    v.check(
+        !redis.command_timeout.as_duration().is_zero(),
+        "redis.command_timeout",
+        "must be greater than 0",
+    );
+
+    if let Some(proxy) = &config.proxy {
+        validate_proxy(&mut v, proxy);
+    }
+    if let Some(containers) = &config.containers {
+        validate_containers(&mut v, containers);
+    }
+    if let Some(jobs) = &config.jobs {
+        validate_jobs(&mut v, jobs);
+    }
+
+    ValidationErrors { issues: v.issues }
+}
+
+fn validate_proxy(v: &mut Validator, proxy: &super::ProxyConfig) {
+    v.check(
+        proxy.bind_address.parse::<std::net::SocketAddr>().is_ok(),
+        "proxy.bind_address",
+        "must be an IP address and port such as 0.0.0.0:80",
+    );
+    // Accepted for forward compatibility, but the proxy only speaks plain
+    // HTTP and has no middleware yet; enabling them would do nothing.
+    for (unsupported, path) in [
+        (proxy.ssl.enabled, "proxy.ssl.enabled"),
+        (proxy.https_redirect, "proxy.https_redirect"),
+        (
+            proxy.middleware.rate_limit_enabled,
+            "proxy.middleware.rate_limit_enabled",
+        ),
+        (
+            proxy.middleware.compression_enabled,
+            "proxy.middleware.compression_enabled",
+        ),
+        (
+            proxy.middleware.add_request_headers.is_some(),
+            "proxy.middleware.add_request_headers",
+        ),
+        (
+            proxy.middleware.remove_request_headers.is_some(),
+            "proxy.middleware.remove_request_headers",
+        ),
+        (
+            proxy.middleware.add_response_headers.is_some(),
+            "proxy.middleware.add_response_headers",
+        ),
+        (
+            proxy.middleware.remove_response_headers.is_some(),
+            "proxy.middleware.remove_response_headers",
+        ),
+    ] {
+        v.check(!unsupported, path, "is not supported by the proxy yet");
+    }
+
+    let balancing = &proxy.balancing;
+    v.check(
+        !balancing.session_affinity
+            || (!balancing.session_cookie.is_empty()
+                && balancing
+                    .session_cookie
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))),
+        "proxy.balancing.session_cookie",
+        "must be a cookie name when session_affinity is set",
+    );
+
+    let health = &proxy.health;
+    v.check(
+        health.timeout < health.interval,
+        "proxy.health.timeout",
+        "must be shorter than the check interval",
+    );
+    v.check(
+        health.healthy_threshold > 0,
+        "proxy.health.healthy_threshold",
+        "must be greater than 0",
+    );
+    v.check(
+        health.unhealthy_threshold > 0,
+        "proxy.health.unhealthy_threshold",
+        "must be greater than 0",
+    );
+    v.check(
+        health.check_path.starts_with('/'),
+        "proxy.health.check_path",
+        "must start with '/'",
+    );
+
+    for (i, upstream) in proxy.upstreams.iter().enumerate() {
+        let path = format!("proxy.upstreams[{}]", i);
+        v.check(
+            !upstream.name.is_empty(),
+            &format!("{}.name", path),
+            "must not be empty",
+        );
+        if proxy.upstreams[..i].iter().any(|u| u.name == upstream.name) {
+            v.error(
+                &format!("{}.name", path),
+                format!("duplicate upstream \"{}\"", upstream.name),
+            );
+        }
+        v.check(
+            !upstream.servers.is_empty(),
+            &format!("{}.servers", path),
+            "must list at least one server",
+        );
+
+        for (j, server) in upstream.servers.iter().enumerate() {
+            let path = format!("{}.servers[{}]", path, j);
+            v.check(
+                server
+                    .address
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()),
+                &format!("{}.address", path),
+                "must be in host:port form",
+            );
+            v.check(
+                (1..=MAX_SERVER_WEIGHT).contains(&server.weight),
+                &format!("{}.weight", path),
+                &format!("must be between 1 and {}", MAX_SERVER_WEIGHT),
+            );
+        }
+
+        if let Some(breaker) = &upstream.circuit_breaker {
+            v.check(
+                breaker.error_rate > 0.0 && breaker.error_rate <= 1.0,
+                &format!("{}.circuit_breaker.error_rate", path),
+                "must be greater than 0 and at most 1",
+            );
+            v.check(
+                breaker.min_requests > 0,
+                &format!("{}.circuit_breaker.min_requests", path),
+                "must be greater than 0",
+            );
+        }
+    }
+
+    for (i, route) in proxy.routes.iter().enumerate() {
+        let path = format!("proxy.routes[{}]", i);
+        v.check(
+            route.path.starts_with('/'),
+            &format!("{}.path", path),
+            "must start with '/'",
+        );
+        v.one_of(&format!("{}.method", path), &route.method, ROUTE_METHODS);
+        match proxy.upstreams.iter().find(|u| u.name == route.upstream) {
+            None => v.error(
+                &format!("{}.upstream", path),
+                format!("unknown upstream \"{}\"", route.upstream),
+            ),
+            Some(upstream) => v.check(
+                route.websocket != Some(true)
+                    || upstream.protocol != Some(super::UpstreamProtocol::H2),
+                &format!("{}.websocket", path),
+                "upgrades need an http1 upstream",
+            ),
+        }
+    }
+
+    let middleware = &proxy.middleware;
+    v.one_of(
+        "proxy.middleware.rate_limit_key",
+        &middleware.rate_limit_key,
+        RATE_LIMIT_KEYS,
+    );
+    v.check(
+        (1..=9).contains(&middleware.compression_level),
+        "proxy.middleware.compression_level",
+        "must be between 1 and 9",
+    );
+}
+
+fn validate_containers(v: &mut Validator, containers: &super::ContainerConfig) {
+    for (i, registry) in containers.registries.iter().enumerate() {
+        v.url(
+            &format!("containers.registries[{}].url", i),
+            &registry.url,
+            &["http", "https"],
+        );
+    }
+}
+
+fn validate_jobs(v: &mut Validator, jobs: &super::JobConfig) {
+    v.check(
+        jobs.retry.base_delay <= jobs.retry.max_delay,
+        "jobs.retry.base_delay",
+        "must not exceed max_delay",
+    );
+    v.check(
+        jobs.files.max_input_size.as_bytes() > 0,
+        "jobs.files.max_input_size",
+        "must be greater than 0",
+    );
+    v.one_of(
+        "jobs.monitoring.log_level",
+        &jobs.monitoring.log_level.to_lowercase(),
+        LOG_LEVELS,
+    );
+}
+
+#[derive(Default)]
+struct Validator {
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            path: path.to_string(),
+            message: message.into(),
+            location: None,
+        });
+    }
+
+    fn check(&mut self, ok: bool, path: &str, message: &str) {
+        if !ok {
+            self.error(path, message);
+        }
+    }
+
+    fn one_of(&mut self, path: &str, value: &str, allowed: &[&str]) {
+        if !allowed.contains(&value) {
+            self.error(
+                path,
+                format!(
+                    "invalid value \"{}\" (expected one of: {})",
+                    value,
+                    allowed.join(", ")
+                ),
+            );
+        }
+    }
+
+    fn url(&mut self, path: &str, value: &str, schemes: &[&str]) {
+        let valid = value
+            .split_once("://")
+            .is_some_and(|(scheme, rest)| schemes.contains(&scheme) && !rest.is_empty());
+        if !valid {
+            self.error(
+                path,
+                format!(
+                    "invalid URL \"{}\" (expected scheme: {})",
+                    value,
+                    schemes.join(", ")
+                ),
+            );
+        }
+    }
+
+    fn file(&mut self, path: &str, value: Option<&str>) {
+        match value {
+            None => self.error(path, "required when TLS is enabled"),
+            Some(file) if !Path::new(file).is_file() => {
+                self.error(path, format!("file \"{}\" does not exist", file))
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Byte offset of the key (or array element) named by a dotted path such as
+/// `proxy.routes[1].upstream`. Falls back to the closest enclosing item.
+fn find_span(root: &Table, path: &str) -> Option<usize> {
+    let mut table = root;
+    let mut found = None;
+    let mut segments = path.split('.').peekable();
+
+    while let Some(segment) = segments.next() {
+        let (key, index) = match segment.split_once('[') {
+            Some((key, rest)) => (key, rest.trim_end_matches(']').parse::<usize>().ok()),
+            None => (segment, None),
+        };
+
+        let Some((key, item)) = table.get_key_value(key) else {
+            break;
+        };
+        found = key.span().map(|s| s.start).or(found);
+
+        let item = match (item, index) {
+            (Item::ArrayOfTables(tables), Some(i)) => match tables.get(i) {
+                Some(t) => {
+                    found = t.span().map(|s| s.start).or(found);
+                    if segments.peek().is_none() {
+                        break;
+                    }
+                    table = t;
+                    continue;
+                }
+                None => break,
+            },
+            (Item::Value(Value::Array(array)), Some(i)) => match array.get(i) {
+                Some(value) => {
+                    found = value.span().map(|s| s.start).or(found);
+                    break;
+                }
+                None => break,
+            },
+            (item, _) => item,
+        };
+
+        match item {
+            Item::Table(t) => table = t,
+            Item::Value(Value::InlineTable(t)) => {
+                if let Some(next) = segments.next() {
+                    found = t
+                        .get_key_value(next)
+                        .and_then(|(k, _)| k.span())
+                        .map(|s| s.start)
+                        .or(found);
+                }
+                break;
+            }
+            _ => break,
+        }
+    }
+
+    found
+}
+
+pub(super) fn location_at(source: &str, offset: usize) -> Location {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    Location { line, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = include_str!("../../../../config/server.toml");
+    const PROXY: &str = r#"[proxy]
+enabled = true
+bind_address = "0.0.0.0:80"
+https_redirect = false
+ssl = { enabled = false, bind_address = "0.0.0.0:443", cert_dir = "", key_dir = "" }
+balancing = { strategy = "round_robin", session_affinity = false, session_cookie = "SID" }
+health = { enabled = true, interval = "30s", timeout = "5s", healthy_threshold = 2, unhealthy_threshold = 3, check_path = "/health" }
+middleware = { rate_limit_enabled = false, rate_limit_requests = 0, rate_limit_window = "1m", rate_limit_key = "ip", compression_enabled = false, compression_level = 6, compression_types = [] }
+
+[[proxy.upstreams]]
+name = "web"
+strategy = "round_robin"
+servers = [{ address = "127.0.0.1:8001", weight = 1, max_fails = 3, fail_timeout = "30s" }]
+
+[[proxy.routes]]
+path = "/"
+method = "GET"
+upstream = "web"
+
+[[proxy.routes]]
+path = "/api"
+method = "GET"
+upstream = "api"
+"#;
+
+    #[test]
+    fn test_collects_all_errors_with_locations() {
+        let source = SOURCE
+            .replace("frame_options = \"DENY\"", "frame_options = \"ALLOW\"")
+            .replace("level = \"info\"", "level = \"verbose\"")
+            .replace("idle_timeout = \"10m\"", "idle_timeout = \"10 minutes\"");
+        let mut errors = check_values(&source);
+        let config = Config::parse(&strip(&source, &errors).unwrap()).unwrap();
+
+        errors.issues.extend(validate(&config).issues);
+        errors.locate(&source);
+
+        let issues: Vec<_> = errors
+            .iter()
+            .map(|i| (i.path.as_str(), i.location))
+            .collect();
+        let line_of = |needle: &str| source.lines().position(|l| l.contains(needle)).unwrap() + 1;
+
+        assert_eq!(
+            issues,
+            [
+                (
+                    "database.idle_timeout",
+                    Some(Location {
+                        line: line_of("\"10 minutes\""),
+                        column: 1
+                    })
+                ),
+                (
+                    "server.security.frame_options",
+                    Some(Location {
+                        line: line_of("\"ALLOW\""),
+                        column: 1
+                    })
+                ),
+                (
+                    "logging.level",
+                    Some(Location {
+                        line: line_of("\"verbose\""),
+                        column: 1
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collects_malformed_values() {
+        let source = SOURCE
+            .replace("format = \"pretty\"", "format = \"fancy\"")
+            .replacen(
+                "connection_timeout = \"30s\"",
+                "connection_timeout = \"5x\"",
+                1,
+            )
+            .replace("frame_options = \"DENY\"", "frame_options = \"ALLOW\"");
+        let path = std::env::temp_dir().join(format!(
+            "container-codes-config-{}.toml",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&path, &source).unwrap();
+        let result = Config::load_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let Err(crate::Error::Config(crate::error::ConfigError::Validation(errors))) = result
+        else {
+            panic!("expected validation errors");
+        };
+        let issues: Vec<_> = errors
+            .iter()
+            .map(|i| (i.path.as_str(), i.message.as_str()))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                ("logging.format", "invalid value \"fancy\" (expected one of: json, pretty, compact)"),
+                (
+                    "database.connection_timeout",
+                    "invalid duration \"5x\", expected a duration such as \"500ms\", \"30s\", \"1.5h\" or \"1h30m\""
+                ),
+                ("server.security.frame_options", "invalid value \"ALLOW\" (expected one of: DENY, SAMEORIGIN)"),
+            ]
+        );
+        assert!(errors.iter().all(|issue| issue.location.is_some()));
+
+        // Without a required value nothing else can be checked, but it is still reported.
+        let source = format!(
+            "{}\n{}",
+            SOURCE,
+            PROXY.replace("fail_timeout = \"30s\"", "fail_timeout = \"soon\"")
+        );
+        let Err(crate::Error::Config(crate::error::ConfigError::Validation(errors))) =
+            Config::parse(&source)
+        else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_route_upstream_must_exist() {
+        let source = format!("{}\n{}", SOURCE, PROXY);
+        let config: Config = toml::from_str(&source).unwrap();
+
+        let mut errors = validate(&config);
+        errors.locate(&source);
+
+        assert_eq!(errors.len(), 1);
+        let issue = &errors.issues[0];
+        assert_eq!(issue.path, "proxy.routes[1].upstream");
+        assert_eq!(
+            issue.location.map(|l| l.line),
+            source
+                .lines()
+                .position(|l| l == "upstream = \"api\"")
+                .map(|l| l + 1)
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported_proxy_features() {
+        let source = format!(
+            "{}\n{}",
+            SOURCE,
+            PROXY
+                .replace("https_redirect = false", "https_redirect = true")
+                .replace("ssl = { enabled = false", "ssl = { enabled = true")
+                .replace(
+                    "compression_enabled = false",
+                    "compression_enabled = true, add_request_headers = { \"X-A\" = \"1\" }"
+                )
+                .replace("upstream = \"api\"", "upstream = \"web\"")
+        );
+        let config: Config = toml::from_str(&source).unwrap();
+        let issues: Vec<_> = validate(&config)
+            .iter()
+            .map(|issue| (issue.path.clone(), issue.message.clone()))
+            .collect();
+        let paths: Vec<_> = issues.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "proxy.ssl.enabled",
+                "proxy.https_redirect",
+                "proxy.middleware.compression_enabled",
+                "proxy.middleware.add_request_headers",
+            ]
+        );
+        assert!(issues
+            .iter()
+            .all(|(_, message)| message == "is not supported by the proxy yet"));
+    }
+
+    #[test]
+    fn test_server_weight_is_bounded() {
+        for (weight, valid) in [(0, false), (1, true), (10_000, true), (10_001, false)] {
+            let source = format!(
+                "{}\n{}",
+                SOURCE,
+                PROXY
+                    .replace("weight = 1", &format!("weight = {}", weight))
+                    .replace("upstream = \"api\"", "upstream = \"web\"")
+            );
+            let config: Config = toml::from_str(&source).unwrap();
+            let issues: Vec<_> = validate(&config)
+                .iter()
+                .map(|issue| issue.path.clone())
+                .collect();
+            let expected: &[&str] = if valid {
+                &[]
+            } else {
+                &["proxy.upstreams[0].servers[0].weight"]
+            };
+            assert_eq!(issues, expected, "weight {}", weight);
+        }
+    }
+
+    #[test]
+    fn test_session_cookie_must_be_a_cookie_name() {
+        for (cookie, affinity, valid) in [
+            ("SID", true, true),
+            ("bad cookie", false, true),
+            ("bad cookie", true, false),
+            ("", true, false),
+        ] {
+            let source = format!(
+                "{}\n{}",
+                SOURCE,
+                PROXY
+                    .replace(
+                        "session_affinity = false, session_cookie = \"SID\"",
+                        &format!(
+                            "session_affinity = {}, session_cookie = {:?}",
+                            affinity, cookie
+                        )
+                    )
+                    .replace("upstream = \"api\"", "upstream = \"web\"")
+            );
+            let config: Config = toml::from_str(&source).unwrap();
+            assert_eq!(validate(&config).is_empty(), valid, "{:?}", cookie);
+        }
+    }
+}
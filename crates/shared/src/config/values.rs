@@ -0,0 +1,649 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid {kind} \"{value}\", expected {expected}")]
+pub struct ParseValueError {
+    kind: &'static str,
+    value: String,
+    expected: &'static str,
+}
+
+impl ParseValueError {
+    fn new(kind: &'static str, value: &str, expected: &'static str) -> Self {
+        Self {
+            kind,
+            value: value.to_string(),
+            expected,
+        }
+    }
+}
+
+const DURATION_EXPECTED: &str = "a duration such as \"500ms\", \"30s\", \"1.5h\" or \"1h30m\"";
+/// Schema pattern for durations; kept in step with `HumanDuration::from_str`.
+const DURATION_PATTERN: &str = r"^\s*((\d+\.?\d*|\.\d+)(d|h|m|s|ms|us|ns)\s*)+$";
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+/// Duration units from largest to smallest, in nanoseconds.
+const DURATION_UNITS: [(&str, u128); 7] = [
+    ("d", 86_400 * NANOS_PER_SEC),
+    ("h", 3_600 * NANOS_PER_SEC),
+    ("m", 60 * NANOS_PER_SEC),
+    ("s", NANOS_PER_SEC),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+const BYTE_SIZE_EXPECTED: &str = "a size such as \"512m\", \"1g\" or \"100kb\"";
+const CPU_QUOTA_EXPECTED: &str = "a positive number of CPUs such as \"0.5\" or \"2\"";
+const HASH_KEY_EXPECTED: &str = "\"ip\", \"path\", \"header:<name>\" or \"cookie:<name>\"";
+const STATUS_RANGE_EXPECTED: &str =
+    "an HTTP status such as \"200\", a class such as \"2xx\" or a range such as \"200-399\"";
+
+/// A duration written the way people do in config files: `500ms`, `30s`,
+/// `1.5h`, `7d` or compound values like `1h30m`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(Duration);
+
+impl HumanDuration {
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+
+    pub const fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseValueError::new("duration", s, DURATION_EXPECTED);
+        let mut rest = s.trim();
+        if rest.is_empty() {
+            return Err(err());
+        }
+
+        // Summed in whole nanoseconds so that compound values such as
+        // "1ms500us" parse exactly rather than drifting through f64.
+        let mut total = 0u128;
+        while !rest.is_empty() {
+            let number_end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .ok_or_else(err)?;
+            let (number, tail) = rest.split_at(number_end);
+            let unit_end = tail
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(tail.len());
+            let (unit, tail) = tail.split_at(unit_end);
+
+            let nanos = DURATION_UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, nanos)| *nanos)
+                .ok_or_else(err)?;
+            let value = (number.parse::<f64>().map_err(|_| err())? * nanos as f64).round();
+            if !value.is_finite() || value >= u128::MAX as f64 {
+                return Err(err());
+            }
+            total = total.checked_add(value as u128).ok_or_else(err)?;
+            rest = tail.trim_start();
+        }
+
+        let secs = u64::try_from(total / NANOS_PER_SEC).map_err(|_| err())?;
+        Ok(Self(Duration::new(secs, (total % NANOS_PER_SEC) as u32)))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return write!(f, "0s");
+        }
+
+        for (unit, size) in DURATION_UNITS {
+            if nanos >= size {
+                write!(f, "{}{}", nanos / size, unit)?;
+                nanos %= size;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A size in bytes, written with Docker-style binary suffixes (`512m`, `1g`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub const fn from_bytes(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub const fn as_bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseValueError::new("size", s, BYTE_SIZE_EXPECTED);
+        let lower = s.trim().to_lowercase();
+        let split = lower
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(lower.len());
+        let (number, unit) = lower.split_at(split);
+
+        let multiplier: u64 = match unit.trim() {
+            "" | "b" => 1,
+            "k" | "kb" => 1 << 10,
+            "m" | "mb" => 1 << 20,
+            "g" | "gb" => 1 << 30,
+            "t" | "tb" => 1 << 40,
+            _ => return Err(err()),
+        };
+
+        let number: f64 = number.parse().map_err(|_| err())?;
+        let bytes = number * multiplier as f64;
+        // `u64::MAX as f64` rounds up to 2^64, which is already out of range.
+        if bytes.fract() != 0.0 || bytes >= u64::MAX as f64 {
+            return Err(err());
+        }
+        Ok(Self(bytes as u64))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (unit, size) in [
+            ("t", 1u64 << 40),
+            ("g", 1 << 30),
+            ("m", 1 << 20),
+            ("k", 1 << 10),
+        ] {
+            if self.0 >= size && self.0.is_multiple_of(size) {
+                return write!(f, "{}{}", self.0 / size, unit);
+            }
+        }
+        write!(f, "{}b", self.0)
+    }
+}
+
+/// A CPU limit as a (possibly fractional) number of cores.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct CpuQuota(f64);
+
+impl CpuQuota {
+    pub fn cores(&self) -> f64 {
+        self.0
+    }
+
+    /// The limit in Docker's `NanoCpus` unit.
+    pub fn nano_cpus(&self) -> i64 {
+        (self.0 * 1e9) as i64
+    }
+}
+
+impl FromStr for CpuQuota {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<f64>() {
+            Ok(cores) if cores > 0.0 && cores.is_finite() => Ok(Self(cores)),
+            _ => Err(ParseValueError::new("CPU limit", s, CPU_QUOTA_EXPECTED)),
+        }
+    }
+}
+
+impl fmt::Display for CpuQuota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.fract() == 0.0 {
+            write!(f, "{:.1}", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// The part of a request that consistent hashing balancers hash on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    /// The client's IP address.
+    Ip,
+    /// The request path, without the query.
+    Path,
+    /// A request header.
+    Header(String),
+    /// A cookie.
+    Cookie(String),
+}
+
+impl FromStr for HashKey {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseValueError::new("hash key", s, HASH_KEY_EXPECTED);
+        match s.trim().split_once(':') {
+            None if s.trim() == "ip" => Ok(Self::Ip),
+            None if s.trim() == "path" => Ok(Self::Path),
+            Some((kind, name)) if !name.trim().is_empty() => match kind {
+                "header" => Ok(Self::Header(name.trim().to_ascii_lowercase())),
+                "cookie" => Ok(Self::Cookie(name.trim().to_string())),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip => f.write_str("ip"),
+            Self::Path => f.write_str("path"),
+            Self::Header(name) => write!(f, "header:{}", name),
+            Self::Cookie(name) => write!(f, "cookie:{}", name),
+        }
+    }
+}
+
+/// A set of HTTP status codes: one code, a class like `2xx`, or an inclusive range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusRange {
+    pub first: u16,
+    pub last: u16,
+}
+
+impl StatusRange {
+    pub fn contains(&self, status: u16) -> bool {
+        (self.first..=self.last).contains(&status)
+    }
+}
+
+impl FromStr for StatusRange {
+    type Err = ParseValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseValueError::new("status range", s, STATUS_RANGE_EXPECTED);
+        let code = |s: &str| {
+            s.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (100..=599).contains(code))
+        };
+        let s = s.trim();
+        let range = if let Some(class) = s.strip_suffix("xx").or_else(|| s.strip_suffix("XX")) {
+            let class = class
+                .parse::<u16>()
+                .ok()
+                .filter(|class| (1..=5).contains(class))
+                .ok_or_else(invalid)?;
+            Self {
+                first: class * 100,
+                last: class * 100 + 99,
+            }
+        } else if let Some((first, last)) = s.split_once('-') {
+            Self {
+                first: code(first).ok_or_else(invalid)?,
+                last: code(last).ok_or_else(invalid)?,
+            }
+        } else {
+            let code = code(s).ok_or_else(invalid)?;
+            Self {
+                first: code,
+                last: code,
+            }
+        };
+        if range.first > range.last {
+            return Err(invalid());
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for StatusRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else if self.first.is_multiple_of(100) && self.last == self.first + 99 {
+            write!(f, "{}xx", self.first / 100)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+macro_rules! string_serde {
+    ($ty:ty, $expected:expr, $pattern:expr) => {
+        impl JsonSchema for $ty {
+            fn schema_name() -> Cow<'static, str> {
+                stringify!($ty).into()
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                json_schema!({
+                    "type": "string",
+                    "pattern": $pattern,
+                    "description": format!("Expected {}.", $expected),
+                })
+            }
+        }
+
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(StrVisitor::<$ty>::new($expected))
+            }
+        }
+    };
+}
+
+string_serde!(HumanDuration, DURATION_EXPECTED, DURATION_PATTERN);
+string_serde!(
+    ByteSize,
+    BYTE_SIZE_EXPECTED,
+    r"^\s*\d+(\.\d+)?\s*([kKmMgGtT]?[bB]?)\s*$"
+);
+string_serde!(CpuQuota, CPU_QUOTA_EXPECTED, r"^\s*\d+(\.\d+)?\s*$");
+string_serde!(
+    HashKey,
+    HASH_KEY_EXPECTED,
+    r"^\s*(ip|path|(header|cookie):\s*\S+)\s*$"
+);
+string_serde!(
+    StatusRange,
+    STATUS_RANGE_EXPECTED,
+    r"^\s*([1-5]([xX]{2}|\d{2}(\s*-\s*[1-5]\d{2})?))\s*$"
+);
+
+/// Checks a raw TOML value for the typed value named `kind` in the config
+/// schema, so that validation can report every malformed one rather than
+/// only the first serde meets. `None` if `kind` is not one of these types.
+pub(super) fn check_raw(kind: &str, value: &toml_edit::Value) -> Option<Result<(), String>> {
+    fn check<T: FromStr<Err = ParseValueError>>(
+        value: &toml_edit::Value,
+        expected: &str,
+    ) -> Result<(), String> {
+        match value.as_str() {
+            Some(s) => s.parse::<T>().map(drop).map_err(|e| e.to_string()),
+            None => Err(format!("expected {}", expected)),
+        }
+    }
+
+    Some(match kind {
+        "HumanDuration" => check::<HumanDuration>(value, DURATION_EXPECTED),
+        "ByteSize" => check::<ByteSize>(value, BYTE_SIZE_EXPECTED),
+        "CpuQuota" => check::<CpuQuota>(value, CPU_QUOTA_EXPECTED),
+        "HashKey" => check::<HashKey>(value, HASH_KEY_EXPECTED),
+        "StatusRange" => check::<StatusRange>(value, STATUS_RANGE_EXPECTED),
+        _ => return None,
+    })
+}
+
+struct StrVisitor<T> {
+    expected: &'static str,
+    marker: PhantomData<T>,
+}
+
+impl<T> StrVisitor<T> {
+    fn new(expected: &'static str) -> Self {
+        Self {
+            expected,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> de::Visitor<'_> for StrVisitor<T>
+where
+    T: FromStr<Err = ParseValueError>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expected)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+/// Generates a config enum that (de)serializes as one of a fixed set of strings.
+macro_rules! config_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $value:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+        pub enum $name {
+            $(#[serde(rename = $value)] $variant),+
+        }
+
+        impl $name {
+            pub const ALL: &'static [&'static str] = &[$($value),+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $value),+
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+config_enum!(
+    /// How requests are spread across the servers of an upstream.
+    BalancingStrategy {
+        RoundRobin => "round_robin",
+        LeastConnections => "least_connections",
+        IpHash => "ip_hash",
+        Random => "random",
+        P2cEwma => "p2c_ewma",
+        RingHash => "ring_hash",
+        Maglev => "maglev",
+    }
+);
+
+config_enum!(
+    /// HTTP version the proxy speaks to an upstream's servers.
+    UpstreamProtocol {
+        Http1 => "http1",
+        H2 => "h2",
+    }
+);
+
+config_enum!(
+    /// A failed attempt the proxy may retry.
+    RetryCondition {
+        ConnectError => "connect_error",
+        Reset => "reset",
+        BadGateway => "502",
+        ServiceUnavailable => "503",
+        GatewayTimeout => "504",
+    }
+);
+
+config_enum!(
+    /// How a health check probes a server.
+    HealthCheckType {
+        Http => "http",
+        Tcp => "tcp",
+    }
+);
+
+config_enum!(
+    /// Docker restart policy for managed containers.
+    RestartPolicy {
+        No => "no",
+        Always => "always",
+        OnFailure => "on-failure",
+        UnlessStopped => "unless-stopped",
+    }
+);
+
+config_enum!(
+    /// When to pull an image before creating a container.
+    PullPolicy {
+        Always => "always",
+        Missing => "missing",
+        Never => "never",
+    }
+);
+
+config_enum!(
+    /// How the delay between job retries grows.
+    BackoffStrategy {
+        Fixed => "fixed",
+        Linear => "linear",
+        Exponential => "exponential",
+    }
+);
+
+config_enum!(
+    /// Deployment mode; production hides internal error details from clients.
+    Environment {
+        Development => "development",
+        Production => "production",
+    }
+);
+
+config_enum!(
+    /// Output format of the log subscriber.
+    LogFormat {
+        Json => "json",
+        Pretty => "pretty",
+        Compact => "compact",
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        let parse = |s: &str| s.parse::<HumanDuration>().map(Duration::from);
+
+        assert_eq!(parse("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("1.5h"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse(".5s"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse("1ms500us"), Ok(Duration::from_micros(1500)));
+        assert_eq!(parse("250ns"), Ok(Duration::from_nanos(250)));
+        assert!(parse("5x").is_err());
+        assert!(parse("30").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        for s in ["1h30m", "500ms", "7d", "0s", "1m5s", "1ms500us", "1s1ns"] {
+            assert_eq!(s.parse::<HumanDuration>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            "1.5ms".parse::<HumanDuration>().unwrap().to_string(),
+            "1ms500us"
+        );
+        assert_eq!(
+            HumanDuration::from(Duration::from_nanos(1_500_250)).to_string(),
+            "1ms500us250ns"
+        );
+        for s in ["512m", "1g", "100k", "1234b"] {
+            assert_eq!(s.parse::<ByteSize>().unwrap().to_string(), s);
+        }
+        assert_eq!("1.5g".parse::<ByteSize>().unwrap().to_string(), "1536m");
+        assert_eq!("1.0".parse::<CpuQuota>().unwrap().to_string(), "1.0");
+        assert_eq!("0.5".parse::<CpuQuota>().unwrap().to_string(), "0.5");
+        for s in ["ip", "path", "header:x-user", "cookie:SESSIONID"] {
+            assert_eq!(s.parse::<HashKey>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            "header:X-User".parse::<HashKey>().unwrap().to_string(),
+            "header:x-user"
+        );
+        assert!("query:q".parse::<HashKey>().is_err());
+        assert!("cookie:".parse::<HashKey>().is_err());
+        for s in ["200", "2xx", "200-399"] {
+            assert_eq!(s.parse::<StatusRange>().unwrap().to_string(), s);
+        }
+        assert_eq!("200-299".parse::<StatusRange>().unwrap().to_string(), "2xx");
+        assert!("399-200".parse::<StatusRange>().is_err());
+        assert!("6xx".parse::<StatusRange>().is_err());
+    }
+
+    #[test]
+    fn test_duration_pattern_matches_parser() {
+        let pattern = regex::Regex::new(DURATION_PATTERN).unwrap();
+        for s in [
+            "500ms", ".5s", "5.s", "1.5h", " 1h 30m ", "1ms500us", "10ns", "5x", "30", "", ".s",
+            "1 s", "1.2.3s", "1sx",
+        ] {
+            assert_eq!(
+                pattern.is_match(s),
+                s.parse::<HumanDuration>().is_ok(),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_byte_size_out_of_range() {
+        assert_eq!("0.5k".parse::<ByteSize>(), Ok(ByteSize::from_bytes(512)));
+        assert!("0.1k".parse::<ByteSize>().is_err());
+        assert!("1.5b".parse::<ByteSize>().is_err());
+        assert!("16777216t".parse::<ByteSize>().is_err());
+        assert!("99999999999999999999".parse::<ByteSize>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_errors_are_descriptive() {
+        #[derive(Debug, Deserialize)]
+        struct Section {
+            #[allow(dead_code)]
+            timeout: HumanDuration,
+        }
+
+        let err = toml::from_str::<Section>("timeout = \"5x\"")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("invalid duration \"5x\""), "{}", err);
+
+        let err = toml::from_str::<Section>("timeout = 30")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("expected a duration such as"), "{}", err);
+    }
+}
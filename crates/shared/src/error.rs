@@ -1,5 +1,23 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::config::ValidationErrors;
+use crate::types::ApiResponse;
+
+/// Whether 5xx responses carry the real error message; off in production.
+static EXPOSE_INTERNAL_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether internal error messages are sent to clients.
+pub fn expose_internal_errors(expose: bool) {
+    EXPOSE_INTERNAL_ERRORS.store(expose, Ordering::Relaxed);
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Error, Debug)]
@@ -31,6 +49,24 @@ pub enum Error {
     #[error("Authentication error: {0}")]
     Auth(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("{resource} '{id}' not found")]
+    NotFound { resource: &'static str, id: String },
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Rate limit exceeded")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("{0}")]
+    NotSupported(String),
+
     #[error("Validation error: {0}")]
     Validation(String),
 
@@ -49,8 +85,17 @@ pub enum ConfigError {
     #[error("Configuration parse error: {0}")]
     Parse(#[from] toml::de::Error),
 
+    #[error("Configuration parse error: {error}did you mean `{suggestion}`?")]
+    UnknownField {
+        error: toml::de::Error,
+        suggestion: String,
+    },
+
     #[error("File not found: {path}")]
     FileNotFound { path: String },
+
+    #[error("Configuration validation failed: {0}")]
+    Validation(ValidationErrors),
 }
 
 impl Error {
@@ -85,7 +130,153 @@ impl Error {
         Self::Validation(msg.into())
     }
 
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        Self::Forbidden(msg.into())
+    }
+
+    pub fn not_found(resource: &'static str, id: impl Into<String>) -> Self {
+        Self::NotFound {
+            resource,
+            id: id.into(),
+        }
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        Self::Conflict(msg.into())
+    }
+
+    pub fn rate_limited(retry_after: Option<Duration>) -> Self {
+        Self::RateLimited { retry_after }
+    }
+
+    pub fn unavailable(msg: impl Into<String>) -> Self {
+        Self::Unavailable(msg.into())
+    }
+
+    pub fn not_supported(msg: impl Into<String>) -> Self {
+        Self::NotSupported(msg.into())
+    }
+
     pub fn internal(msg: impl Into<String>) -> Self {
         Self::Internal(msg.into())
     }
-}
\ No newline at end of file
+
+    /// HTTP status this error is reported with.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Validation(_) => StatusCode::BAD_REQUEST,
+            Error::Auth(_) => StatusCode::UNAUTHORIZED,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::NotFound { .. } => StatusCode::NOT_FOUND,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Config(ConfigError::Validation(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::NotSupported(_) => StatusCode::NOT_IMPLEMENTED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable code, see `docs/api-specification.md`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Validation(_) => "INVALID_REQUEST",
+            Error::Auth(_) => "UNAUTHORIZED",
+            Error::Forbidden(_) => "FORBIDDEN",
+            Error::NotFound { resource, .. } => match *resource {
+                "container" => "CONTAINER_NOT_FOUND",
+                "job" => "JOB_NOT_FOUND",
+                "file" => "FILE_NOT_FOUND",
+                _ => "NOT_FOUND",
+            },
+            Error::Conflict(_) => "CONFLICT",
+            Error::Config(ConfigError::Validation(_)) => "INVALID_CONFIG",
+            Error::RateLimited { .. } => "RATE_LIMITED",
+            Error::Unavailable(_) => "SERVICE_UNAVAILABLE",
+            Error::NotSupported(_) => "NOT_SUPPORTED",
+            _ => "INTERNAL_ERROR",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Error::NotFound { resource, id } => Some(json!({ "resource": resource, "id": id })),
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+            } => Some(json!({ "retry_after_secs": retry_after.as_secs() })),
+            Error::Config(ConfigError::Validation(errors)) => Some(json!({
+                "issues": errors
+                    .iter()
+                    .map(|issue| json!({ "path": issue.path, "message": issue.message }))
+                    .collect::<Vec<_>>(),
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let code = self.code().to_string();
+
+        let expected = matches!(self, Error::Unavailable(_) | Error::NotSupported(_));
+        let body = if status.is_server_error() && !expected {
+            tracing::error!(error = %self, code = %code, "Request failed");
+            if EXPOSE_INTERNAL_ERRORS.load(Ordering::Relaxed) {
+                ApiResponse::<()>::error(code, self.to_string())
+            } else {
+                ApiResponse::<()>::error(code, "Internal server error".to_string())
+            }
+        } else {
+            match self.details() {
+                Some(details) => {
+                    ApiResponse::<()>::error_with_details(code, self.to_string(), details)
+                }
+                None => ApiResponse::<()>::error(code, self.to_string()),
+            }
+        };
+
+        let mut response = (status, Json(body)).into_response();
+        if let Error::RateLimited {
+            retry_after: Some(retry_after),
+        } = self
+        {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs()),
+            );
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_id::RequestId;
+
+    async fn respond(error: Error) -> (StatusCode, serde_json::Value) {
+        let request_id = RequestId::parse("req-1").unwrap();
+        let response = request_id.scope(async { error.into_response() }).await;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_responses() {
+        let (status, body) = respond(Error::not_found("container", "c123")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "CONTAINER_NOT_FOUND");
+        assert_eq!(body["error"]["details"]["id"], "c123");
+        assert_eq!(body["request_id"], "req-1");
+
+        let (status, body) = respond(Error::internal("password=hunter2")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"]["code"], "INTERNAL_ERROR");
+        assert_eq!(body["error"]["message"], "Internal server error");
+    }
+}
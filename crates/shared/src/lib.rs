@@ -1,9 +1,16 @@
 pub mod config;
+pub mod database;
 pub mod error;
+pub mod log_file;
 pub mod logging;
-pub mod database;
+pub mod pagination;
+pub mod pidfile;
+pub mod redis;
+pub mod reload;
+pub mod repository;
+pub mod request_id;
 pub mod security;
 pub mod types;
 
 pub use error::{Error, Result};
-pub use types::*;
\ No newline at end of file
+pub use types::*;
@@ -0,0 +1,193 @@
+//! The server's log file, used when `logging.output` is `file`.
+//!
+//! The active file keeps the configured name. On rotation it is renamed to
+//! `<name>.<UTC time>` (e.g. `server.log.20261018T164315123`), with `-0001`,
+//! `-0002`... appended when rotating again within the same millisecond, so
+//! rotated files sort oldest first by name, and only the newest `max_files`
+//! are kept.
+
+use crate::config::LoggingConfig;
+use chrono::{DateTime, Utc};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Daily,
+    /// Weeks start on Monday, UTC.
+    Weekly,
+    /// When the file would grow past this many bytes.
+    Size(u64),
+}
+
+impl Rotation {
+    pub fn from_config(config: &LoggingConfig) -> Self {
+        match config.rotation.as_str() {
+            "weekly" => Rotation::Weekly,
+            "size" => Rotation::Size(config.max_file_size.as_bytes()),
+            _ => Rotation::Daily,
+        }
+    }
+
+    /// Which day or week `time` falls in; `None` for size-based rotation.
+    fn period(&self, time: DateTime<Utc>) -> Option<i64> {
+        let days = time.timestamp().div_euclid(DAY_SECS);
+        match self {
+            Rotation::Daily => Some(days),
+            // 1970-01-01 was a Thursday.
+            Rotation::Weekly => Some((days + 3).div_euclid(7)),
+            Rotation::Size(_) => None,
+        }
+    }
+}
+
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    max_files: usize,
+    file: File,
+    size: u64,
+    period: Option<i64>,
+}
+
+impl RotatingFile {
+    pub fn open(
+        path: impl Into<PathBuf>,
+        rotation: Rotation,
+        max_files: usize,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        // A file left from an earlier period rotates on the first write.
+        let written: DateTime<Utc> = metadata.modified().map_or_else(|_| Utc::now(), Into::into);
+        Ok(Self {
+            period: rotation.period(written),
+            size: metadata.len(),
+            path,
+            rotation,
+            max_files,
+            file,
+        })
+    }
+
+    fn due(&self, incoming: usize) -> bool {
+        match self.rotation {
+            Rotation::Size(max) => self.size > 0 && self.size + incoming as u64 > max,
+            rotation => rotation.period(Utc::now()) != self.period,
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut stamp = self.path.as_os_str().to_owned();
+        stamp.push(format!(".{}", Utc::now().format("%Y%m%dT%H%M%S%3f")));
+        let mut name = stamp.clone();
+        let mut n = 0;
+        while fs::symlink_metadata(&name).is_ok() {
+            n += 1;
+            name = stamp.clone();
+            name.push(format!("-{:04}", n));
+        }
+        fs::rename(&self.path, &name)?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.period = self.rotation.period(Utc::now());
+
+        let rotated = rotated_files(&self.path);
+        if self.max_files > 0 && rotated.len() > self.max_files {
+            for old in &rotated[..rotated.len() - self.max_files] {
+                fs::remove_file(old)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.due(buf.len()) {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Rotated files of the log at `path`, oldest first.
+pub fn rotated_files(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return vec![];
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix(&prefix)
+                .is_some_and(|stamp| {
+                    !stamp.is_empty()
+                        && stamp
+                            .chars()
+                            .all(|c| c.is_ascii_digit() || c == 'T' || c == '-')
+                })
+        })
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_rotation_keeps_max_files() {
+        let dir =
+            std::env::temp_dir().join(format!("container-codes-logs-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("server.log");
+        let mut file = RotatingFile::open(&path, Rotation::Size(10), 3).unwrap();
+
+        // Rotations within the same millisecond must not overwrite each other.
+        for line in ["first\n", "second\n", "third\n", "fourth\n", "fifth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        let rotated = rotated_files(&path);
+        let contents: Vec<String> = rotated
+            .iter()
+            .map(|rotated| fs::read_to_string(rotated).unwrap())
+            .collect();
+        assert_eq!(contents, ["second\n", "third\n", "fourth\n"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fifth\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
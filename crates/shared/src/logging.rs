@@ -1,54 +1,108 @@
-use crate::config::LoggingConfig;
+use crate::config::{Config, LogFormat, LoggingConfig};
+use crate::log_file::{RotatingFile, Rotation};
+use crate::reload::ReloadHook;
 use crate::Result;
+use std::io::IsTerminal;
+use std::sync::Mutex;
 use tracing::{info, Level};
 use tracing_subscriber::{
-    fmt::{self, format::Format, time::UtcTime},
+    fmt::{self, time::UtcTime, writer::BoxMakeWriter},
     layer::SubscriberExt,
+    reload,
     util::SubscriberInitExt,
     EnvFilter, Registry,
 };
 
-pub fn init_logging(config: &LoggingConfig) -> Result<()> {
+/// Handle to the installed log filter, used to change the level at runtime.
+#[derive(Clone)]
+pub struct LogHandle {
+    filter: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogHandle {
+    pub fn set_level(&self, level: &str) -> Result<()> {
+        let level = parse_log_level(level)?;
+        self.filter
+            .reload(level_filter(level))
+            .map_err(|e| crate::Error::internal(format!("Failed to reload log filter: {}", e)))
+    }
+}
+
+impl ReloadHook for LogHandle {
+    fn name(&self) -> &'static str {
+        "logging"
+    }
+
+    fn sections(&self) -> &'static [&'static str] {
+        &["logging.level"]
+    }
+
+    fn apply(&self, config: &Config) -> Result<()> {
+        self.set_level(&config.logging.level)
+    }
+}
+
+pub fn init_logging(config: &LoggingConfig) -> Result<LogHandle> {
     let level = parse_log_level(&config.level)?;
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new(format!("container_codes={}", level)));
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| level_filter(level));
+    let (env_filter, filter) = reload::Layer::new(env_filter);
 
     let registry = Registry::default().with(env_filter);
 
-    match config.format.as_str() {
-        "json" => {
+    let (writer, ansi) = match config.output.as_str() {
+        "file" => {
+            let path = config.file_path.as_deref().unwrap_or("container-codes.log");
+            let file = RotatingFile::open(
+                path,
+                Rotation::from_config(config),
+                config.max_files as usize,
+            )?;
+            (BoxMakeWriter::new(Mutex::new(file)), false)
+        }
+        "stderr" => (
+            BoxMakeWriter::new(std::io::stderr),
+            std::io::stderr().is_terminal(),
+        ),
+        _ => (
+            BoxMakeWriter::new(std::io::stdout),
+            std::io::stdout().is_terminal(),
+        ),
+    };
+
+    match config.format {
+        LogFormat::Json => {
             let fmt_layer = fmt::layer()
+                .with_writer(writer)
+                .with_ansi(ansi)
                 .json()
-                .with_timestamp(UtcTime::rfc_3339())
+                .with_timer(UtcTime::rfc_3339())
                 .with_current_span(true)
                 .with_span_list(true);
 
             registry.with(fmt_layer).init();
         }
-        "pretty" => {
+        LogFormat::Pretty => {
             let fmt_layer = fmt::layer()
+                .with_writer(writer)
+                .with_ansi(ansi)
                 .pretty()
-                .with_timestamp(UtcTime::rfc_3339())
+                .with_timer(UtcTime::rfc_3339())
                 .with_target(true)
                 .with_thread_ids(true)
                 .with_thread_names(true);
 
             registry.with(fmt_layer).init();
         }
-        "compact" => {
+        LogFormat::Compact => {
             let fmt_layer = fmt::layer()
+                .with_writer(writer)
+                .with_ansi(ansi)
                 .compact()
-                .with_timestamp(UtcTime::rfc_3339())
+                .with_timer(UtcTime::rfc_3339())
                 .with_target(false);
 
             registry.with(fmt_layer).init();
         }
-        _ => {
-            return Err(crate::Error::config_invalid(
-                "logging.format",
-                &config.format,
-            ));
-        }
     }
 
     info!(
@@ -56,7 +110,12 @@ pub fn init_logging(config: &LoggingConfig) -> Result<()> {
         config.level, config.format
     );
 
-    Ok(())
+    Ok(LogHandle { filter })
+}
+
+/// Our crates plus sqlx, whose `sqlx::query` target reports slow statements.
+fn level_filter(level: Level) -> EnvFilter {
+    EnvFilter::new(format!("container_codes={},sqlx={}", level, level))
 }
 
 fn parse_log_level(level: &str) -> Result<Level> {
@@ -132,4 +191,4 @@ macro_rules! log_job_event {
             "Job event"
         );
     };
-}
\ No newline at end of file
+}
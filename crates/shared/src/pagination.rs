@@ -0,0 +1,731 @@
+//! Cursor pagination, filtering and sorting shared by every list endpoint.
+//!
+//! Clients send `?limit=50&sort=-created_at&filter=status:eq:running` and
+//! follow `next_cursor` from the response. Each endpoint describes its
+//! columns with a [`ListSpec`], which validates the query and appends the
+//! matching `WHERE`/`ORDER BY`/`LIMIT` to a SQL statement.
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, QueryBuilder, Sqlite};
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+use crate::{config::Config, reload::ReloadHook, types::PaginatedResponse, Error, Result};
+
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 500;
+
+static CURSOR_KEY: RwLock<Option<Arc<[u8]>>> = RwLock::new(None);
+
+/// Sets the key cursors are signed with. Without a secret, a random key is
+/// kept for the life of the process, so cursors stop working on restart.
+/// Changing the secret invalidates outstanding cursors.
+pub fn set_cursor_key(secret: Option<&str>) {
+    let mut key = CURSOR_KEY.write().unwrap_or_else(|e| e.into_inner());
+    match secret {
+        Some(secret) => *key = Some(secret.as_bytes().into()),
+        None if key.is_none() => *key = Some(Uuid::new_v4().as_bytes().as_slice().into()),
+        None => {}
+    }
+}
+
+fn cursor_key() -> Arc<[u8]> {
+    if let Some(key) = CURSOR_KEY.read().unwrap_or_else(|e| e.into_inner()).clone() {
+        return key;
+    }
+    set_cursor_key(None);
+    cursor_key()
+}
+
+/// Applies `server.security.cursor_secret` on reload.
+pub struct CursorKeyHook;
+
+impl ReloadHook for CursorKeyHook {
+    fn name(&self) -> &'static str {
+        "cursor key"
+    }
+
+    fn apply(&self, config: &Config) -> Result<()> {
+        set_cursor_key(config.server.security.cursor_secret.as_deref());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Integer,
+    Timestamp,
+}
+
+/// A column clients may sort or filter on, under its JSON field name.
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub column: &'static str,
+    pub kind: FieldKind,
+    pub sortable: bool,
+    pub filterable: bool,
+}
+
+impl Field {
+    pub const fn new(name: &'static str, column: &'static str, kind: FieldKind) -> Self {
+        Self {
+            name,
+            column,
+            kind,
+            sortable: true,
+            filterable: true,
+        }
+    }
+
+    pub const fn filter_only(mut self) -> Self {
+        self.sortable = false;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// Value is a `|`-separated list.
+    In,
+    /// Case-insensitive substring match on text fields.
+    Contains,
+}
+
+impl FilterOp {
+    fn parse(op: &str) -> Option<Self> {
+        Some(match op {
+            "eq" => Self::Eq,
+            "ne" => Self::Ne,
+            "lt" => Self::Lt,
+            "lte" => Self::Lte,
+            "gt" => Self::Gt,
+            "gte" => Self::Gte,
+            "in" => Self::In,
+            "contains" => Self::Contains,
+            _ => return None,
+        })
+    }
+
+    fn sql(&self) -> &'static str {
+        match self {
+            Self::Eq | Self::In => "=",
+            Self::Ne => "<>",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Contains => "ILIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+/// Raw list parameters from the query string, before they are checked
+/// against a [`ListSpec`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery {
+    pub limit: u32,
+    /// Rows to skip; ignored when a cursor is given.
+    pub offset: u32,
+    pub cursor: Option<String>,
+    pub sort: Vec<SortKey>,
+    pub filters: Vec<Filter>,
+}
+
+impl Default for ListQuery {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_LIMIT,
+            offset: 0,
+            cursor: None,
+            sort: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl ListQuery {
+    /// Parses `limit`, `offset`, `cursor`, `sort` (`-created_at,name`) and repeated
+    /// `filter` (`field:op:value`) parameters. Any other `key=value` is an
+    /// equality filter, so `?status=running` works as documented.
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut list = Self::default();
+
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "limit" => {
+                    list.limit = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+                        .ok_or_else(|| {
+                            Error::validation(format!("limit must be between 1 and {}", MAX_LIMIT))
+                        })?;
+                }
+                "offset" => {
+                    list.offset = value
+                        .parse()
+                        .map_err(|_| Error::validation("offset must be a non-negative integer"))?;
+                }
+                "cursor" => list.cursor = Some(value.into_owned()),
+                "sort" => {
+                    list.sort = value
+                        .split(',')
+                        .filter(|s| !s.is_empty())
+                        .map(|s| match s.strip_prefix('-') {
+                            Some(field) => SortKey {
+                                field: field.into(),
+                                descending: true,
+                            },
+                            None => SortKey {
+                                field: s.into(),
+                                descending: false,
+                            },
+                        })
+                        .collect();
+                }
+                "filter" => list.filters.push(parse_filter(&value)?),
+                field => list.filters.push(Filter {
+                    field: field.to_string(),
+                    op: FilterOp::Eq,
+                    value: value.into_owned(),
+                }),
+            }
+        }
+
+        Ok(list)
+    }
+}
+
+fn parse_filter(expression: &str) -> Result<Filter> {
+    let mut parts = expression.splitn(3, ':');
+    let (Some(field), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(Error::validation(format!(
+            "invalid filter \"{}\", expected field:op:value",
+            expression
+        )));
+    };
+    let op = FilterOp::parse(op).ok_or_else(|| {
+        Error::validation(format!(
+            "unknown filter operator \"{}\", expected one of eq, ne, lt, lte, gt, gte, in, contains",
+            op
+        ))
+    })?;
+
+    Ok(Filter {
+        field: field.to_string(),
+        op,
+        value: value.to_string(),
+    })
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ListQuery {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        Self::parse(parts.uri.query().unwrap_or_default())
+    }
+}
+
+/// A typed value bound into SQL and stored in cursors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SqlValue {
+    Integer(i64),
+    Timestamp(DateTime<Utc>),
+    Text(String),
+}
+
+impl SqlValue {
+    fn parse(field: &Field, value: &str) -> Result<Self> {
+        let invalid = |expected: &str| {
+            Error::validation(format!(
+                "invalid value \"{}\" for {}, expected {}",
+                value, field.name, expected
+            ))
+        };
+        match field.kind {
+            FieldKind::Text => Ok(Self::Text(value.to_string())),
+            FieldKind::Integer => value
+                .parse()
+                .map(Self::Integer)
+                .map_err(|_| invalid("an integer")),
+            FieldKind::Timestamp => DateTime::parse_from_rfc3339(value)
+                .map(|t| Self::Timestamp(t.with_timezone(&Utc)))
+                .map_err(|_| invalid("an RFC 3339 timestamp")),
+        }
+    }
+
+    /// Reads a row's value for `field` from its JSON form.
+    fn from_json(field: &Field, value: &Value) -> Option<Self> {
+        match (field.kind, value) {
+            (FieldKind::Integer, Value::Number(n)) => n.as_i64().map(Self::Integer),
+            (FieldKind::Timestamp, Value::String(s)) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|t| Self::Timestamp(t.with_timezone(&Utc))),
+            (FieldKind::Text, Value::String(s)) => Some(Self::Text(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// A database list queries can be built for.
+pub trait Backend: sqlx::Database {
+    /// Case-insensitive `LIKE` operator.
+    const ILIKE: &'static str;
+
+    fn push_value(builder: &mut QueryBuilder<'_, Self>, value: &SqlValue);
+}
+
+macro_rules! impl_backend {
+    ($db:ty, $ilike:literal) => {
+        impl Backend for $db {
+            const ILIKE: &'static str = $ilike;
+
+            fn push_value(builder: &mut QueryBuilder<'_, Self>, value: &SqlValue) {
+                match value {
+                    SqlValue::Integer(i) => builder.push_bind(*i),
+                    SqlValue::Timestamp(t) => builder.push_bind(*t),
+                    SqlValue::Text(s) => builder.push_bind(s.clone()),
+                };
+            }
+        }
+    };
+}
+
+impl_backend!(Postgres, "ILIKE");
+// SQLite's LIKE already ignores ASCII case.
+impl_backend!(Sqlite, "LIKE");
+
+/// The sortable and filterable columns of one list endpoint.
+pub struct ListSpec {
+    pub fields: &'static [Field],
+    /// Unique field appended to every sort so pages never overlap.
+    pub id: &'static str,
+    /// Sort used when the client sends none, e.g. `-created_at`.
+    pub default_sort: &'static str,
+}
+
+impl ListSpec {
+    fn field(&self, name: &str) -> Result<&'static Field> {
+        self.fields.iter().find(|f| f.name == name).ok_or_else(|| {
+            let known: Vec<_> = self.fields.iter().map(|f| f.name).collect();
+            Error::validation(format!(
+                "unknown field \"{}\", expected one of {}",
+                name,
+                known.join(", ")
+            ))
+        })
+    }
+
+    /// Checks `query` against this spec and decodes its cursor.
+    pub fn resolve(&'static self, query: &ListQuery) -> Result<Listing> {
+        let sort_keys = if query.sort.is_empty() {
+            ListQuery::parse(&format!("sort={}", self.default_sort))?.sort
+        } else {
+            query.sort.clone()
+        };
+
+        let mut sort = Vec::new();
+        for key in &sort_keys {
+            let field = self.field(&key.field)?;
+            if !field.sortable {
+                return Err(Error::validation(format!("cannot sort by {}", field.name)));
+            }
+            sort.push((field, key.descending));
+        }
+        if !sort.iter().any(|(f, _)| f.name == self.id) {
+            sort.push((self.field(self.id)?, false));
+        }
+
+        let mut filters = Vec::new();
+        for filter in &query.filters {
+            let field = self.field(&filter.field)?;
+            if !field.filterable {
+                return Err(Error::validation(format!(
+                    "cannot filter by {}",
+                    field.name
+                )));
+            }
+            if filter.op == FilterOp::Contains && field.kind != FieldKind::Text {
+                return Err(Error::validation(format!(
+                    "contains only applies to text fields, not {}",
+                    field.name
+                )));
+            }
+            let values = match filter.op {
+                FilterOp::In => filter
+                    .value
+                    .split('|')
+                    .map(|v| SqlValue::parse(field, v))
+                    .collect::<Result<Vec<_>>>()?,
+                _ => vec![SqlValue::parse(field, &filter.value)?],
+            };
+            filters.push((field, filter.op, values));
+        }
+
+        let mut listing = Listing {
+            limit: query.limit,
+            offset: query.offset,
+            sort,
+            filters,
+            after: None,
+            fingerprint: String::new(),
+        };
+        listing.fingerprint = listing.fingerprint();
+
+        if let Some(cursor) = &query.cursor {
+            listing.after = Some(listing.decode_cursor(cursor)?);
+        }
+
+        Ok(listing)
+    }
+}
+
+/// A validated list query, ready to be turned into SQL.
+#[derive(Debug)]
+pub struct Listing {
+    limit: u32,
+    offset: u32,
+    sort: Vec<(&'static Field, bool)>,
+    filters: Vec<(&'static Field, FilterOp, Vec<SqlValue>)>,
+    after: Option<Vec<SqlValue>>,
+    fingerprint: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorPayload {
+    /// Fingerprint of the sort and filters the cursor was issued for.
+    q: String,
+    /// Sort values of the last row of the previous page.
+    v: Vec<SqlValue>,
+}
+
+impl Listing {
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Appends the `WHERE` clause for the filters only, for `COUNT(*)` queries.
+    pub fn push_filters<DB: Backend>(&self, builder: &mut QueryBuilder<'_, DB>) {
+        self.push_where(builder, false);
+    }
+
+    /// Appends `WHERE`, `ORDER BY` and `LIMIT` to a `SELECT ... FROM table`.
+    /// One extra row is fetched to tell whether another page exists.
+    pub fn push_sql<DB: Backend>(&self, builder: &mut QueryBuilder<'_, DB>) {
+        self.push_where(builder, true);
+
+        builder.push(" ORDER BY ");
+        for (i, (field, descending)) in self.sort.iter().enumerate() {
+            if i > 0 {
+                builder.push(", ");
+            }
+            builder.push(field.column);
+            builder.push(if *descending { " DESC" } else { " ASC" });
+        }
+
+        builder.push(" LIMIT ");
+        DB::push_value(builder, &SqlValue::Integer(i64::from(self.limit) + 1));
+
+        if self.after.is_none() && self.offset > 0 {
+            builder.push(" OFFSET ");
+            DB::push_value(builder, &SqlValue::Integer(i64::from(self.offset)));
+        }
+    }
+
+    fn push_where<DB: Backend>(&self, builder: &mut QueryBuilder<'_, DB>, with_cursor: bool) {
+        let mut first = true;
+        let mut and = |builder: &mut QueryBuilder<'_, DB>| {
+            builder.push(if first { " WHERE " } else { " AND " });
+            first = false;
+        };
+
+        for (field, op, values) in &self.filters {
+            and(builder);
+            builder.push(field.column);
+            match op {
+                FilterOp::In => {
+                    builder.push(" IN (");
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            builder.push(", ");
+                        }
+                        DB::push_value(builder, value);
+                    }
+                    builder.push(")");
+                }
+                FilterOp::Contains => {
+                    let SqlValue::Text(text) = &values[0] else {
+                        unreachable!("checked in resolve")
+                    };
+                    builder.push(format!(" {} ", DB::ILIKE));
+                    DB::push_value(builder, &SqlValue::Text(format!("%{}%", escape_like(text))));
+                    builder.push(" ESCAPE '\\'");
+                }
+                op => {
+                    builder.push(format!(" {} ", op.sql()));
+                    DB::push_value(builder, &values[0]);
+                }
+            }
+        }
+
+        // Keyset condition: rows strictly after the cursor in sort order,
+        // i.e. (a > x) OR (a = x AND b > y) OR ... with per-key direction.
+        if let (true, Some(after)) = (with_cursor, &self.after) {
+            and(builder);
+            builder.push("(");
+            for i in 0..self.sort.len() {
+                if i > 0 {
+                    builder.push(" OR ");
+                }
+                builder.push("(");
+                for (j, (field, descending)) in self.sort.iter().enumerate().take(i + 1) {
+                    if j > 0 {
+                        builder.push(" AND ");
+                    }
+                    builder.push(field.column);
+                    builder.push(match (j == i, descending) {
+                        (false, _) => " = ",
+                        (true, true) => " < ",
+                        (true, false) => " > ",
+                    });
+                    DB::push_value(builder, &after[j]);
+                }
+                builder.push(")");
+            }
+            builder.push(")");
+        }
+    }
+
+    /// Trims the extra row fetched by [`Listing::push_sql`] and builds the
+    /// response, with a cursor pointing after the last returned row.
+    pub fn paginate<T: Serialize>(
+        &self,
+        mut rows: Vec<T>,
+        total: u64,
+    ) -> Result<PaginatedResponse<T>> {
+        let has_next = rows.len() > self.limit as usize;
+        rows.truncate(self.limit as usize);
+
+        let next_cursor = match rows.last() {
+            Some(last) if has_next => Some(self.encode_cursor(last)?),
+            _ => None,
+        };
+
+        let mut page = PaginatedResponse::with_cursor(rows, total, self.limit, next_cursor);
+        if self.after.is_none() {
+            page.offset = self.offset;
+        }
+        Ok(page)
+    }
+
+    fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (field, descending) in &self.sort {
+            hasher.update(format!("s:{}:{};", field.name, descending));
+        }
+        for (field, op, values) in &self.filters {
+            hasher.update(format!(
+                "f:{}:{:?}:{};",
+                field.name,
+                op,
+                serde_json::to_string(values).unwrap_or_default()
+            ));
+        }
+        URL_SAFE_NO_PAD.encode(&hasher.finalize()[..12])
+    }
+
+    fn encode_cursor<T: Serialize>(&self, row: &T) -> Result<String> {
+        let row = serde_json::to_value(row)?;
+        let values = self
+            .sort
+            .iter()
+            .map(|(field, _)| {
+                SqlValue::from_json(field, &row[field.name]).ok_or_else(|| {
+                    Error::internal(format!("row has no usable {} for a cursor", field.name))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&CursorPayload {
+            q: self.fingerprint.clone(),
+            v: values,
+        })?);
+        Ok(format!("{}.{}", payload, sign(&payload)))
+    }
+
+    fn decode_cursor(&self, cursor: &str) -> Result<Vec<SqlValue>> {
+        let invalid = || Error::validation("invalid or expired cursor");
+
+        let (payload, signature) = cursor.split_once('.').ok_or_else(invalid)?;
+        if !verify(payload, signature) {
+            return Err(invalid());
+        }
+
+        let bytes = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        let cursor: CursorPayload = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        if cursor.q != self.fingerprint {
+            return Err(Error::validation(
+                "cursor was issued for a different sort or filter",
+            ));
+        }
+        if cursor.v.len() != self.sort.len() {
+            return Err(invalid());
+        }
+
+        // Untagged decoding cannot tell a timestamp-like text from a
+        // timestamp, so re-read each value as its field's kind.
+        self.sort
+            .iter()
+            .zip(cursor.v)
+            .map(|((field, _), value)| {
+                serde_json::to_value(value)
+                    .ok()
+                    .and_then(|value| SqlValue::from_json(field, &value))
+                    .ok_or_else(invalid)
+            })
+            .collect()
+    }
+}
+
+/// Bytes of the HMAC kept in a cursor.
+const SIGNATURE_LEN: usize = 16;
+
+fn mac(payload: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&cursor_key()).expect("HMAC accepts any key length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+fn sign(payload: &str) -> String {
+    URL_SAFE_NO_PAD.encode(&mac(payload).finalize().into_bytes()[..SIGNATURE_LEN])
+}
+
+/// Checks a signature from [`sign`] in constant time.
+fn verify(payload: &str, signature: &str) -> bool {
+    URL_SAFE_NO_PAD.decode(signature).is_ok_and(|tag| {
+        tag.len() == SIGNATURE_LEN && mac(payload).verify_truncated_left(&tag).is_ok()
+    })
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    static JOBS: ListSpec = ListSpec {
+        fields: &[
+            Field::new("job_id", "id", FieldKind::Text),
+            Field::new("status", "status", FieldKind::Text),
+            Field::new("created_at", "created_at", FieldKind::Timestamp),
+            Field::new("name", "name", FieldKind::Text).filter_only(),
+        ],
+        id: "job_id",
+        default_sort: "-created_at",
+    };
+
+    fn sql(listing: &Listing) -> String {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM jobs");
+        listing.push_sql(&mut builder);
+        builder.into_sql()
+    }
+
+    #[test]
+    fn test_builds_filters_and_sort() {
+        let query = ListQuery::parse("status=running&filter=name:contains:etl&limit=2").unwrap();
+        let listing = JOBS.resolve(&query).unwrap();
+
+        assert_eq!(
+            sql(&listing),
+            "SELECT * FROM jobs WHERE status = $1 AND name ILIKE $2 ESCAPE '\\' \
+             ORDER BY created_at DESC, id ASC LIMIT $3"
+        );
+        assert!(JOBS
+            .resolve(&ListQuery::parse("sort=name").unwrap())
+            .is_err());
+        assert!(JOBS
+            .resolve(&ListQuery::parse("owner=me").unwrap())
+            .is_err());
+        assert!(ListQuery::parse("limit=0").is_err());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let query = ListQuery::parse("status=running&limit=1").unwrap();
+        let listing = JOBS.resolve(&query).unwrap();
+        let rows = vec![
+            json!({ "job_id": "j2", "status": "running", "created_at": "2024-01-02T00:00:00Z" }),
+            json!({ "job_id": "j1", "status": "running", "created_at": "2024-01-01T00:00:00Z" }),
+        ];
+
+        let page = listing.paginate(rows, 2).unwrap();
+        assert!(page.has_next);
+        let cursor = page.next_cursor.unwrap();
+
+        let next = JOBS
+            .resolve(&ListQuery {
+                cursor: Some(cursor.clone()),
+                ..query.clone()
+            })
+            .unwrap();
+        assert_eq!(
+            sql(&next),
+            "SELECT * FROM jobs WHERE status = $1 AND ((created_at < $2) OR \
+             (created_at = $3 AND id > $4)) ORDER BY created_at DESC, id ASC LIMIT $5"
+        );
+
+        let (payload, signature) = cursor.split_once('.').unwrap();
+        let truncated = format!("{}.{}", payload, &signature[..8]);
+        for tampered in [format!("{}x", cursor), truncated] {
+            assert!(JOBS
+                .resolve(&ListQuery {
+                    cursor: Some(tampered),
+                    ..query.clone()
+                })
+                .is_err());
+        }
+
+        let other_filter = ListQuery::parse("status=failed&limit=1").unwrap();
+        assert!(JOBS
+            .resolve(&ListQuery {
+                cursor: Some(cursor),
+                ..other_filter
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! PID file that doubles as a lock, so at most one server runs per file.
+//!
+//! The server holds an exclusive lock on the file for as long as it runs.
+//! The kernel releases it when the process exits, so a file left behind by a
+//! crash never blocks the next start; whether a server is running is decided
+//! by the lock, not by the file existing.
+
+use crate::{Error, Result};
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long [`PidFile::acquire`] waits out the shared lock of a concurrent
+/// [`running_pid`] probe, which holds it only for an instant.
+const PROBE_GRACE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Graceful shutdown: stop accepting and drain in-flight requests.
+    Terminate,
+    Kill,
+    /// Reload the configuration.
+    Hangup,
+}
+
+/// The lock held by a running server. Dropping it releases the lock.
+#[derive(Debug)]
+pub struct PidFile {
+    /// Kept open only to hold the lock.
+    _lock: File,
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Locks `path` and writes the current PID, failing if another server
+    /// already holds it. Probes by [`running_pid`] only delay it briefly.
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let file = open(path)?;
+        let deadline = Instant::now() + PROBE_GRACE;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Self::locked(file, path),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(PROBE_GRACE / 20);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(Error::conflict(match read_pid(path) {
+                        Some(pid) => format!(
+                            "server already running with pid {} ({})",
+                            pid,
+                            path.display()
+                        ),
+                        None => format!("server already running ({})", path.display()),
+                    }))
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Hands over from the server holding `path`: asks it to shut down and
+    /// takes the lock once it has drained and exited.
+    pub async fn take_over(path: &Path, timeout: Duration) -> Result<Self> {
+        let file = open(path)?;
+        let previous = running_pid(path)?;
+        if let Some(pid) = previous {
+            info!(pid, "Asking the previous server to shut down");
+            signal(pid, Signal::Terminate)?;
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Self::locked(file, path),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(Error::unavailable(format!(
+                        "previous server (pid {}) did not exit within {:?}",
+                        previous.map_or_else(|| "unknown".to_string(), |pid| pid.to_string()),
+                        timeout
+                    )));
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn locked(mut file: File, path: &Path) -> Result<Self> {
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+        Ok(Self {
+            _lock: file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn open(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Not truncated: the file may belong to a running server until locked.
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?)
+}
+
+/// The PID written to `path`, whether or not that process still runs.
+pub fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// PID of the server holding the lock on `path`, or `None` if none runs.
+pub fn running_pid(path: &Path) -> Result<Option<u32>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    match file.try_lock_shared() {
+        Ok(()) => Ok(None),
+        Err(TryLockError::WouldBlock) => Ok(read_pid(path)),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+pub fn signal(pid: u32, signal: Signal) -> Result<()> {
+    let signal = match signal {
+        Signal::Terminate => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+        Signal::Hangup => libc::SIGHUP,
+    };
+    let pid = libc::pid_t::try_from(pid)
+        .map_err(|_| Error::validation(format!("invalid pid {}", pid)))?;
+    // SAFETY: kill(2) takes plain integers and has no memory-safety requirements.
+    if unsafe { libc::kill(pid, signal) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Waits until no server holds the lock on `path`; false on timeout.
+pub async fn wait_for_exit(path: &Path, timeout: Duration) -> Result<bool> {
+    let deadline = Instant::now() + timeout;
+    while running_pid(path)?.is_some() {
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_lock_excludes_a_second_server() {
+        let path =
+            std::env::temp_dir().join(format!("container-codes-test-{}.pid", uuid::Uuid::new_v4()));
+        assert_eq!(running_pid(&path).unwrap(), None);
+
+        let pid_file = PidFile::acquire(&path).unwrap();
+        assert_eq!(running_pid(&path).unwrap(), Some(std::process::id()));
+        assert!(matches!(PidFile::acquire(&path), Err(Error::Conflict(_))));
+
+        drop(pid_file);
+        assert_eq!(running_pid(&path).unwrap(), None);
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_probes_do_not_block_acquire() {
+        let path =
+            std::env::temp_dir().join(format!("container-codes-test-{}.pid", uuid::Uuid::new_v4()));
+        let probes = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(AtomicBool::new(false));
+        let prober = std::thread::spawn({
+            let (path, probes, done) = (path.clone(), probes.clone(), done.clone());
+            move || {
+                while !done.load(Ordering::Relaxed) {
+                    running_pid(&path).unwrap();
+                    probes.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+
+        // Keep acquiring until the prober has overlapped with plenty of them.
+        let mut acquired = 0;
+        while acquired < 200 || probes.load(Ordering::Relaxed) < 5000 {
+            drop(PidFile::acquire(&path).unwrap());
+            acquired += 1;
+        }
+        done.store(true, Ordering::Relaxed);
+        prober.join().unwrap();
+
+        // A probe that holds its shared lock for longer, e.g. while descheduled.
+        let probe = File::open(&path).unwrap();
+        probe.lock_shared().unwrap();
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            drop(probe);
+        });
+        drop(PidFile::acquire(&path).unwrap());
+        release.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
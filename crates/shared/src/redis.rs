@@ -0,0 +1,453 @@
+//! Pooled Redis client shared by the job queue, rate limiter and cache.
+//!
+//! Commands run on one of `pool_size` multiplexed connections. Each one is
+//! opened on first use and reopened after a connection error, so the server
+//! starts even while Redis is down. Every command is bounded by
+//! `command_timeout` and retried up to `retry_attempts` times on connection
+//! errors and timeouts. After `breaker_threshold` consecutive failures the
+//! circuit breaker opens and commands fail fast with
+//! [`Error::Unavailable`](crate::Error::Unavailable) until `breaker_cooldown`
+//! has passed and a trial command succeeds.
+//!
+//! A retried write may be applied twice if Redis ran it but the reply was
+//! lost, so callers should prefer idempotent commands.
+
+use crate::{config::RedisConfig, Error, Result};
+use ::redis::{
+    aio::MultiplexedConnection, Client, Cmd, FromRedisValue, Pipeline, RedisError, RedisResult,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{sync::Mutex, time::timeout};
+use tracing::{debug, warn};
+
+/// First delay between retries; doubled on each further attempt.
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub struct RedisClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: Client,
+    slots: Vec<Mutex<Option<MultiplexedConnection>>>,
+    next_slot: AtomicUsize,
+    connection_timeout: Duration,
+    command_timeout: Duration,
+    retry_attempts: u32,
+    key_prefix: String,
+    breaker: CircuitBreaker,
+    metrics: Metrics,
+}
+
+/// Counters since startup, reported by `/api/system/info`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RedisStats {
+    pub breaker: BreakerState,
+    /// Pooled connections currently open.
+    pub open_connections: u64,
+    pub pool_size: u64,
+    /// Commands that succeeded.
+    pub commands: u64,
+    /// Commands that failed after all retries.
+    pub failures: u64,
+    pub retries: u64,
+    pub timeouts: u64,
+    /// Commands failed fast by the open breaker.
+    pub rejected: u64,
+    pub connects: u64,
+    pub breaker_opens: u64,
+    /// Mean latency of successful commands.
+    pub avg_latency_ms: f64,
+}
+
+#[derive(Default)]
+struct Metrics {
+    open_connections: AtomicU64,
+    commands: AtomicU64,
+    failures: AtomicU64,
+    retries: AtomicU64,
+    timeouts: AtomicU64,
+    rejected: AtomicU64,
+    connects: AtomicU64,
+    breaker_opens: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+#[derive(Clone, Copy)]
+enum Request<'a> {
+    Cmd(&'a Cmd),
+    Pipeline(&'a Pipeline),
+}
+
+impl Request<'_> {
+    async fn query<T: FromRedisValue>(
+        self,
+        connection: &mut MultiplexedConnection,
+    ) -> RedisResult<T> {
+        match self {
+            Request::Cmd(cmd) => cmd.query_async(connection).await,
+            Request::Pipeline(pipeline) => pipeline.query_async(connection).await,
+        }
+    }
+}
+
+impl RedisClient {
+    /// Validates the URL; connections are opened on first use.
+    pub fn new(config: &RedisConfig) -> Result<Self> {
+        let client = Client::open(config.url.as_str())?;
+        let slots = (0..config.pool_size.max(1))
+            .map(|_| Mutex::new(None))
+            .collect();
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                client,
+                slots,
+                next_slot: AtomicUsize::new(0),
+                connection_timeout: config.connection_timeout.as_duration(),
+                command_timeout: config.command_timeout.as_duration(),
+                retry_attempts: config.retry_attempts,
+                key_prefix: config.key_prefix.clone(),
+                breaker: CircuitBreaker::new(
+                    config.breaker_threshold,
+                    config.breaker_cooldown.as_duration(),
+                ),
+                metrics: Metrics::default(),
+            }),
+        })
+    }
+
+    /// Namespaces a key with `key_prefix`, e.g. `container-codes:jobs`.
+    pub fn key(&self, key: &str) -> String {
+        if self.inner.key_prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}:{}", self.inner.key_prefix, key)
+        }
+    }
+
+    /// Runs a command. Keys must already be namespaced with [`Self::key`].
+    pub async fn query<T: FromRedisValue>(&self, cmd: &Cmd) -> Result<T> {
+        self.run(Request::Cmd(cmd), self.inner.retry_attempts).await
+    }
+
+    /// Runs a pipeline, atomically if it was built with `.atomic()`.
+    pub async fn query_pipeline<T: FromRedisValue>(&self, pipeline: &Pipeline) -> Result<T> {
+        self.run(Request::Pipeline(pipeline), self.inner.retry_attempts)
+            .await
+    }
+
+    /// Sends one `PING`, without retries.
+    pub async fn health_check(&self) -> Result<()> {
+        let reply: String = self.run(Request::Cmd(&::redis::cmd("PING")), 0).await?;
+        if reply != "PONG" {
+            return Err(Error::unavailable(format!(
+                "unexpected PING reply '{}'",
+                reply
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> RedisStats {
+        let m = &self.inner.metrics;
+        let commands = m.commands.load(Ordering::Relaxed);
+        let latency_micros = m.latency_micros.load(Ordering::Relaxed);
+        RedisStats {
+            breaker: self.inner.breaker.state(Instant::now()),
+            open_connections: m.open_connections.load(Ordering::Relaxed),
+            pool_size: self.inner.slots.len() as u64,
+            commands,
+            failures: m.failures.load(Ordering::Relaxed),
+            retries: m.retries.load(Ordering::Relaxed),
+            timeouts: m.timeouts.load(Ordering::Relaxed),
+            rejected: m.rejected.load(Ordering::Relaxed),
+            connects: m.connects.load(Ordering::Relaxed),
+            breaker_opens: m.breaker_opens.load(Ordering::Relaxed),
+            avg_latency_ms: if commands == 0 {
+                0.0
+            } else {
+                latency_micros as f64 / commands as f64 / 1000.0
+            },
+        }
+    }
+
+    async fn run<T: FromRedisValue>(&self, request: Request<'_>, retries: u32) -> Result<T> {
+        let inner = &self.inner;
+        let mut backoff = RETRY_BACKOFF;
+        let mut attempt = 0;
+
+        loop {
+            if !inner.breaker.allow(Instant::now()) {
+                inner.metrics.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(Error::unavailable("Redis circuit breaker is open"));
+            }
+
+            let started = Instant::now();
+            let error = match self.attempt(request).await {
+                Ok(value) => {
+                    inner.breaker.record(true, Instant::now());
+                    inner.metrics.commands.fetch_add(1, Ordering::Relaxed);
+                    inner
+                        .metrics
+                        .latency_micros
+                        .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+
+            // Error replies such as WRONGTYPE mean Redis itself is healthy.
+            if !is_retryable(&error) {
+                inner.breaker.record(true, Instant::now());
+                inner.metrics.failures.fetch_add(1, Ordering::Relaxed);
+                return Err(error.into());
+            }
+
+            if error.is_timeout() {
+                inner.metrics.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+            if inner.breaker.record(false, Instant::now()) {
+                inner.metrics.breaker_opens.fetch_add(1, Ordering::Relaxed);
+                warn!(error = %error, "Redis circuit breaker opened");
+            }
+
+            if attempt >= retries || inner.breaker.state(Instant::now()) == BreakerState::Open {
+                inner.metrics.failures.fetch_add(1, Ordering::Relaxed);
+                return Err(error.into());
+            }
+            attempt += 1;
+            inner.metrics.retries.fetch_add(1, Ordering::Relaxed);
+            debug!(attempt, error = %error, "Retrying Redis command");
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        }
+    }
+
+    async fn attempt<T: FromRedisValue>(&self, request: Request<'_>) -> RedisResult<T> {
+        let inner = &self.inner;
+        let slot =
+            &inner.slots[inner.next_slot.fetch_add(1, Ordering::Relaxed) % inner.slots.len()];
+
+        let mut connection = {
+            let mut guard = slot.lock().await;
+            match &*guard {
+                Some(connection) => connection.clone(),
+                None => {
+                    let connection = timeout(
+                        inner.connection_timeout,
+                        inner.client.get_multiplexed_tokio_connection(),
+                    )
+                    .await
+                    .map_err(|_| timed_out("connecting to Redis"))??;
+                    inner.metrics.connects.fetch_add(1, Ordering::Relaxed);
+                    inner
+                        .metrics
+                        .open_connections
+                        .fetch_add(1, Ordering::Relaxed);
+                    *guard = Some(connection.clone());
+                    connection
+                }
+            }
+        };
+
+        let result = match timeout(inner.command_timeout, request.query(&mut connection)).await {
+            Ok(result) => result,
+            Err(_) => Err(timed_out("Redis command")),
+        };
+
+        // Drop the connection so the next command on this slot reconnects.
+        if matches!(&result, Err(error) if is_retryable(error))
+            && slot.lock().await.take().is_some()
+        {
+            inner
+                .metrics
+                .open_connections
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+fn is_retryable(error: &RedisError) -> bool {
+    error.is_io_error()
+        || error.is_timeout()
+        || error.is_connection_dropped()
+        || error.is_connection_refusal()
+}
+
+fn timed_out(what: &str) -> RedisError {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, format!("{} timed out", what)).into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    /// Failing fast until the cooldown ends.
+    Open,
+    /// Cooldown over; the next command is a trial.
+    HalfOpen,
+}
+
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: StdMutex<BreakerInner>,
+}
+
+#[derive(Default)]
+struct BreakerInner {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// When the trial command of a half-open breaker was let through.
+    trial_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: StdMutex::new(BreakerInner::default()),
+        }
+    }
+
+    /// Whether a command may run. Once the cooldown ends a single trial is
+    /// let through; another is allowed if it never reports back.
+    fn allow(&self, now: Instant) -> bool {
+        let mut state = self.lock();
+        match state.open_until {
+            None => true,
+            Some(until) if now < until => false,
+            Some(_) => match state.trial_started {
+                Some(started) if now.duration_since(started) < self.cooldown => false,
+                _ => {
+                    state.trial_started = Some(now);
+                    true
+                }
+            },
+        }
+    }
+
+    /// Records an outcome; returns true if this failure opened the breaker.
+    fn record(&self, success: bool, now: Instant) -> bool {
+        let mut state = self.lock();
+        if success {
+            *state = BreakerInner::default();
+            return false;
+        }
+
+        state.failures = state.failures.saturating_add(1);
+        let trial_failed = state.trial_started.is_some();
+        if self.threshold == 0 || (!trial_failed && state.failures < self.threshold) {
+            return false;
+        }
+        let opened = state.open_until.is_none();
+        state.open_until = Some(now + self.cooldown);
+        state.trial_started = None;
+        opened
+    }
+
+    fn state(&self, now: Instant) -> BreakerState {
+        let state = self.lock();
+        match state.open_until {
+            None => BreakerState::Closed,
+            Some(until) if now < until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// A panic while the lock was held leaves the counters usable, so the
+    /// breaker keeps working instead of failing every later command.
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerInner> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(!breaker.record(false, start));
+        assert!(breaker.allow(start));
+        assert!(breaker.record(false, start));
+        assert_eq!(breaker.state(start), BreakerState::Open);
+        assert!(!breaker.allow(start + Duration::from_secs(5)));
+
+        // One trial after the cooldown; a failed trial reopens at once.
+        let later = start + Duration::from_secs(10);
+        assert_eq!(breaker.state(later), BreakerState::HalfOpen);
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+        assert!(!breaker.record(false, later));
+        assert!(!breaker.allow(later + Duration::from_secs(5)));
+
+        let recovered = later + Duration::from_secs(10);
+        assert!(breaker.allow(recovered));
+        breaker.record(true, recovered);
+        assert_eq!(breaker.state(recovered), BreakerState::Closed);
+        assert!(breaker.allow(recovered));
+    }
+
+    #[test]
+    fn test_breaker_survives_a_poisoned_lock() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+        let start = Instant::now();
+        std::thread::scope(|scope| {
+            let result = scope
+                .spawn(|| {
+                    let _guard = breaker.lock();
+                    panic!("poison the breaker");
+                })
+                .join();
+            assert!(result.is_err());
+        });
+        assert!(breaker.state.is_poisoned());
+
+        assert!(breaker.allow(start));
+        assert!(breaker.record(false, start));
+        assert_eq!(breaker.state(start), BreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_fails_fast() {
+        let config = RedisConfig {
+            url: "redis://127.0.0.1:1".to_string(),
+            retry_attempts: 1,
+            breaker_threshold: 3,
+            ..RedisConfig::default()
+        };
+        let client = RedisClient::new(&config).unwrap();
+        assert_eq!(client.key("jobs"), "container-codes:jobs");
+
+        assert!(matches!(client.health_check().await, Err(Error::Redis(_))));
+        assert!(matches!(
+            client.query::<String>(&::redis::cmd("PING")).await,
+            Err(Error::Redis(_))
+        ));
+        assert!(matches!(
+            client.health_check().await,
+            Err(Error::Unavailable(_))
+        ));
+
+        let stats = client.stats();
+        assert_eq!(stats.breaker, BreakerState::Open);
+        assert_eq!((stats.failures, stats.retries, stats.rejected), (2, 1, 1));
+    }
+}
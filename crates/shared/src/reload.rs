@@ -0,0 +1,321 @@
+use crate::config::Config;
+use crate::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tracing::{info, instrument, warn};
+
+/// Config sections the server reads afresh on every use, so they can always
+/// be swapped in a running server. Hooks add the sections they apply, see
+/// [`ReloadHook::sections`]; changes anywhere else are reported back but only
+/// take effect after a restart.
+pub const HOT_RELOADABLE: &[&str] = &["server.static_files", "server.security"];
+
+/// Shared, swappable view of the active configuration.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    tx: Arc<watch::Sender<Arc<Config>>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Config) -> Self {
+        let (tx, _) = watch::channel(Arc::new(config));
+        Self { tx: Arc::new(tx) }
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        self.tx.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Config>> {
+        self.tx.subscribe()
+    }
+
+    fn replace(&self, config: Config) {
+        self.tx.send_replace(Arc::new(config));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigChange {
+    pub path: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+impl ConfigChange {
+    /// Whether the change lies within one of `sections`.
+    pub fn is_within(&self, sections: &[&str]) -> bool {
+        sections.iter().any(|section| {
+            self.path == *section
+                || self
+                    .path
+                    .strip_prefix(section)
+                    .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('['))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ReloadReport {
+    pub applied: Vec<ConfigChange>,
+    pub restart_required: Vec<ConfigChange>,
+}
+
+/// Component that needs to react when the hot-reloadable sections change.
+///
+/// If a later hook fails, earlier hooks are re-applied with the previous
+/// config, so `apply` must be safe to call with either.
+pub trait ReloadHook: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Sections that become hot-reloadable when this hook is registered.
+    fn sections(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn apply(&self, config: &Config) -> Result<()>;
+}
+
+pub struct ConfigReloader {
+    path: PathBuf,
+    handle: ConfigHandle,
+    hooks: Vec<Box<dyn ReloadHook>>,
+    lock: Mutex<()>,
+}
+
+impl ConfigReloader {
+    pub fn new(path: impl Into<PathBuf>, handle: ConfigHandle) -> Self {
+        Self {
+            path: path.into(),
+            handle,
+            hooks: vec![],
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn with_hook(mut self, hook: impl ReloadHook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn handle(&self) -> &ConfigHandle {
+        &self.handle
+    }
+
+    /// [`HOT_RELOADABLE`] and the sections the registered hooks apply.
+    pub fn hot_sections(&self) -> Vec<&'static str> {
+        let hooks = self.hooks.iter().flat_map(|hook| hook.sections());
+        HOT_RELOADABLE.iter().chain(hooks).copied().collect()
+    }
+
+    #[instrument(skip(self), fields(path = %self.path.display()))]
+    pub fn reload(&self) -> Result<ReloadReport> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let next = Config::load_from_file(&self.path)?;
+        let current = self.handle.current();
+        let sections = self.hot_sections();
+
+        let (applied, restart_required): (Vec<_>, Vec<_>) = diff(&current, &next)?
+            .into_iter()
+            .partition(|change| change.is_within(&sections));
+
+        for change in &restart_required {
+            warn!(path = %change.path, "Configuration change requires a restart");
+        }
+
+        if applied.is_empty() {
+            info!("No hot-reloadable configuration changes");
+            return Ok(ReloadReport {
+                applied,
+                restart_required,
+            });
+        }
+
+        let candidate = merge_sections(&current, &next, &sections)?;
+        candidate.validate()?;
+
+        for (i, hook) in self.hooks.iter().enumerate() {
+            if let Err(e) = hook.apply(&candidate) {
+                warn!(hook = hook.name(), error = %e, "Reload hook failed, rolling back");
+                for applied_hook in self.hooks[..i].iter().rev() {
+                    if let Err(e) = applied_hook.apply(&current) {
+                        warn!(hook = applied_hook.name(), error = %e, "Rollback failed");
+                    }
+                }
+                return Err(crate::Error::internal(format!(
+                    "Failed to apply configuration to {}: {}",
+                    hook.name(),
+                    e
+                )));
+            }
+        }
+
+        self.handle.replace(candidate);
+
+        info!(
+            applied = applied.len(),
+            restart_required = restart_required.len(),
+            "Configuration reloaded"
+        );
+
+        Ok(ReloadReport {
+            applied,
+            restart_required,
+        })
+    }
+}
+
+/// Field-level difference between two configs, as dotted key paths.
+pub fn diff(old: &Config, new: &Config) -> Result<Vec<ConfigChange>> {
+    let mut changes = vec![];
+    diff_values(
+        String::new(),
+        &serde_json::to_value(old)?,
+        &serde_json::to_value(new)?,
+        &mut changes,
+    );
+    Ok(changes)
+}
+
+fn diff_values(path: String, old: &Value, new: &Value, changes: &mut Vec<ConfigChange>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(
+                    child,
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_values(
+                    format!("{}[{}]", path, i),
+                    a.get(i).unwrap_or(&Value::Null),
+                    b.get(i).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old != new => changes.push(ConfigChange {
+            path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Returns `current` with each of `sections` taken from `next`.
+fn merge_sections(current: &Config, next: &Config, sections: &[&str]) -> Result<Config> {
+    let mut merged = serde_json::to_value(current)?;
+    let next = serde_json::to_value(next)?;
+
+    for section in sections {
+        let pointer = format!("/{}", section.replace('.', "/"));
+        if let (Some(target), Some(value)) = (merged.pointer_mut(&pointer), next.pointer(&pointer))
+        {
+            *target = value.clone();
+        }
+    }
+
+    Ok(serde_json::from_value(merged)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_leaf_paths() {
+        let old = Config::default();
+        let mut new = Config::default();
+        new.server.port = 9090;
+        new.server
+            .static_files
+            .index_files
+            .push("default.html".to_string());
+
+        let changes = diff(&old, &new).unwrap();
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+
+        assert_eq!(paths, ["server.port", "server.static_files.index_files[2]"]);
+        assert!(!changes[0].is_within(HOT_RELOADABLE));
+        assert!(changes[1].is_within(HOT_RELOADABLE));
+    }
+
+    #[test]
+    fn test_merge_only_takes_hot_sections() {
+        let old = Config::default();
+        let mut new = Config::default();
+        new.server.port = 9090;
+        new.logging.level = "debug".to_string();
+        new.server.security.frame_options = "SAMEORIGIN".to_string();
+
+        let merged =
+            merge_sections(&old, &new, &[HOT_RELOADABLE, &["logging.level"]].concat()).unwrap();
+
+        assert_eq!(merged.server.port, 8080);
+        assert_eq!(merged.logging.level, "debug");
+        assert_eq!(merged.server.security.frame_options, "SAMEORIGIN");
+    }
+
+    struct LevelHook;
+
+    impl ReloadHook for LevelHook {
+        fn name(&self) -> &'static str {
+            "level"
+        }
+
+        fn sections(&self) -> &'static [&'static str] {
+            &["logging.level"]
+        }
+
+        fn apply(&self, _: &Config) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sections_without_a_hook_require_restart() {
+        let mut new = Config::default();
+        new.logging.level = "debug".to_string();
+        let path = std::env::temp_dir().join(format!(
+            "container-codes-reload-{}.toml",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&path, toml::to_string(&new).unwrap()).unwrap();
+
+        let reloader = ConfigReloader::new(&path, ConfigHandle::new(Config::default()));
+        let report = reloader.reload().unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.restart_required[0].path, "logging.level");
+
+        let reloader =
+            ConfigReloader::new(&path, ConfigHandle::new(Config::default())).with_hook(LevelHook);
+        let report = reloader.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(report.applied[0].path, "logging.level");
+        assert_eq!(reloader.handle().current().logging.level, "debug");
+    }
+}
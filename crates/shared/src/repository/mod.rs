@@ -0,0 +1,432 @@
+//! Typed access to the tables created by `crates/shared/migrations`.
+//!
+//! Handlers and services depend on these traits rather than on SQL, so the
+//! storage backend can change without touching them. Lookups of a missing
+//! row return [`Error::NotFound`](crate::Error::NotFound) and unique key
+//! violations return [`Error::Conflict`](crate::Error::Conflict).
+
+mod postgres;
+mod sqlite;
+
+pub use postgres::PgRepositories;
+pub use sqlite::SqliteRepositories;
+
+use crate::{
+    config::ProxyConfig,
+    database::{Database, Pool},
+    pagination::{Field, FieldKind, ListQuery, ListSpec},
+    security::{ApiKey, User},
+    types::{
+        AuditEntry, ContainerInfo, ContainerStatus, JobAttempt, JobCreateRequest, JobInfo,
+        JobStatus, PaginatedResponse,
+    },
+    Error, Result,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Fields changed when a job moves to a new status. `None` leaves a field as is.
+#[derive(Debug, Clone)]
+pub struct JobUpdate {
+    pub status: JobStatus,
+    pub progress: Option<f64>,
+    pub container_id: Option<String>,
+    pub error_message: Option<String>,
+    pub output_files: Option<Vec<String>>,
+}
+
+impl JobUpdate {
+    pub fn status(status: JobStatus) -> Self {
+        Self {
+            status,
+            progress: None,
+            container_id: None,
+            error_message: None,
+            output_files: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub actor: Option<String>,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub details: serde_json::Value,
+}
+
+/// A saved proxy configuration; at most one version is active.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProxyConfigVersion {
+    pub version: i64,
+    pub config: ProxyConfig,
+    pub is_active: bool,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait JobRepository: Send + Sync {
+    /// Stores a queued job, tagged with the current request id.
+    async fn create(
+        &self,
+        id: &str,
+        request: &JobCreateRequest,
+        created_by: Option<&str>,
+    ) -> Result<JobInfo>;
+
+    async fn get(&self, id: &str) -> Result<JobInfo>;
+
+    /// The request the job was submitted with.
+    async fn spec(&self, id: &str) -> Result<JobCreateRequest>;
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<JobInfo>>;
+
+    /// Sets `started_at` on the first move to running and `finished_at` on
+    /// reaching a terminal status.
+    async fn update(&self, id: &str, update: &JobUpdate) -> Result<JobInfo>;
+
+    async fn delete(&self, id: &str) -> Result<()>;
+
+    /// Opens the next attempt for a job, numbered from 1.
+    async fn start_attempt(&self, job_id: &str, container_id: Option<&str>) -> Result<JobAttempt>;
+
+    async fn finish_attempt(
+        &self,
+        job_id: &str,
+        attempt: i32,
+        status: JobStatus,
+        exit_code: Option<i32>,
+        error_message: Option<&str>,
+    ) -> Result<JobAttempt>;
+
+    async fn attempts(&self, job_id: &str) -> Result<Vec<JobAttempt>>;
+}
+
+#[async_trait]
+pub trait ContainerRepository: Send + Sync {
+    /// Inserts or refreshes a container as last seen from Docker.
+    async fn upsert(&self, container: &ContainerInfo) -> Result<()>;
+
+    async fn get(&self, id: &str) -> Result<ContainerInfo>;
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<ContainerInfo>>;
+
+    async fn set_status(&self, id: &str, status: ContainerStatus) -> Result<()>;
+
+    async fn delete(&self, id: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(&self, key: &ApiKey) -> Result<()>;
+
+    async fn get(&self, id: &str) -> Result<ApiKey>;
+
+    /// The active, unexpired key with this hash, see [`crate::security::hash_api_key`].
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>>;
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<ApiKey>>;
+
+    /// Records that the key was just used.
+    async fn touch(&self, id: &str) -> Result<()>;
+
+    async fn revoke(&self, id: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn create(&self, user: &User) -> Result<()>;
+
+    async fn get(&self, id: Uuid) -> Result<User>;
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>>;
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<User>>;
+
+    async fn set_password(&self, id: Uuid, password_hash: &str) -> Result<()>;
+
+    async fn set_active(&self, id: Uuid, active: bool) -> Result<()>;
+}
+
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    /// Appends an entry, tagged with the current request id.
+    async fn record(&self, entry: &NewAuditEntry) -> Result<AuditEntry>;
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<AuditEntry>>;
+}
+
+#[async_trait]
+pub trait ProxyConfigRepository: Send + Sync {
+    /// Saves a new version, making it the active one if `activate` is set.
+    async fn save(
+        &self,
+        config: &ProxyConfig,
+        created_by: Option<&str>,
+        activate: bool,
+    ) -> Result<ProxyConfigVersion>;
+
+    async fn get(&self, version: i64) -> Result<ProxyConfigVersion>;
+
+    async fn active(&self) -> Result<Option<ProxyConfigVersion>>;
+
+    /// Makes an earlier version active again.
+    async fn activate(&self, version: i64) -> Result<ProxyConfigVersion>;
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<ProxyConfigVersion>>;
+}
+
+/// Every repository, backed by one database.
+#[derive(Clone)]
+pub struct Repositories {
+    pub jobs: Arc<dyn JobRepository>,
+    pub containers: Arc<dyn ContainerRepository>,
+    pub api_keys: Arc<dyn ApiKeyRepository>,
+    pub users: Arc<dyn UserRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub proxy_configs: Arc<dyn ProxyConfigRepository>,
+}
+
+impl Repositories {
+    fn from_backend<R>(backend: Arc<R>) -> Self
+    where
+        R: JobRepository
+            + ContainerRepository
+            + ApiKeyRepository
+            + UserRepository
+            + AuditRepository
+            + ProxyConfigRepository
+            + 'static,
+    {
+        Repositories {
+            jobs: backend.clone(),
+            containers: backend.clone(),
+            api_keys: backend.clone(),
+            users: backend.clone(),
+            audit: backend.clone(),
+            proxy_configs: backend,
+        }
+    }
+}
+
+impl Database {
+    pub fn repositories(&self) -> Repositories {
+        match self.pool() {
+            Pool::Postgres(pool) => Repositories::from_backend(Arc::new(PgRepositories::new(
+                pool.clone(),
+                self.replicas().clone(),
+            ))),
+            Pool::Sqlite(pool) => {
+                Repositories::from_backend(Arc::new(SqliteRepositories::new(pool.clone())))
+            }
+        }
+    }
+}
+
+// List specs and columns shared by both backends. Column expressions must
+// be valid in PostgreSQL and SQLite alike.
+
+static JOBS: ListSpec = ListSpec {
+    fields: &[
+        Field::new("job_id", "id", FieldKind::Text),
+        Field::new("name", "name", FieldKind::Text),
+        Field::new("status", "status", FieldKind::Text),
+        Field::new("created_at", "created_at", FieldKind::Timestamp),
+        Field::new("image", "image", FieldKind::Text).filter_only(),
+        Field::new("priority", "priority", FieldKind::Text).filter_only(),
+        Field::new("user", "created_by", FieldKind::Text).filter_only(),
+        Field::new("container_id", "container_id", FieldKind::Text).filter_only(),
+        Field::new("started_at", "started_at", FieldKind::Timestamp).filter_only(),
+        Field::new("finished_at", "finished_at", FieldKind::Timestamp).filter_only(),
+    ],
+    id: "job_id",
+    default_sort: "-created_at",
+};
+
+static CONTAINERS: ListSpec = ListSpec {
+    fields: &[
+        Field::new("id", "id", FieldKind::Text),
+        Field::new("name", "name", FieldKind::Text),
+        Field::new("image", "image", FieldKind::Text),
+        Field::new("status", "status", FieldKind::Text),
+        Field::new("created_at", "created_at", FieldKind::Timestamp),
+    ],
+    id: "id",
+    default_sort: "-created_at",
+};
+
+static API_KEYS: ListSpec = ListSpec {
+    fields: &[
+        Field::new("id", "id", FieldKind::Text),
+        Field::new("name", "name", FieldKind::Text),
+        Field::new("created_at", "created_at", FieldKind::Timestamp),
+        Field::new("user_id", "CAST(user_id AS TEXT)", FieldKind::Text).filter_only(),
+        Field::new("expires_at", "expires_at", FieldKind::Timestamp).filter_only(),
+        Field::new("last_used", "last_used", FieldKind::Timestamp).filter_only(),
+    ],
+    id: "id",
+    default_sort: "-created_at",
+};
+
+static USERS: ListSpec = ListSpec {
+    fields: &[
+        Field::new("id", "CAST(id AS TEXT)", FieldKind::Text),
+        Field::new("username", "username", FieldKind::Text),
+        Field::new("email", "email", FieldKind::Text).filter_only(),
+        Field::new("created_at", "created_at", FieldKind::Timestamp),
+    ],
+    id: "id",
+    default_sort: "username",
+};
+
+static AUDIT: ListSpec = ListSpec {
+    fields: &[
+        Field::new("id", "id", FieldKind::Integer),
+        Field::new("occurred_at", "occurred_at", FieldKind::Timestamp),
+        Field::new("action", "action", FieldKind::Text),
+        Field::new("actor", "actor", FieldKind::Text).filter_only(),
+        Field::new("resource_type", "resource_type", FieldKind::Text).filter_only(),
+        Field::new("resource_id", "resource_id", FieldKind::Text).filter_only(),
+        Field::new("request_id", "request_id", FieldKind::Text).filter_only(),
+    ],
+    id: "id",
+    default_sort: "-id",
+};
+
+static PROXY_CONFIGS: ListSpec = ListSpec {
+    fields: &[
+        Field::new("version", "version", FieldKind::Integer),
+        Field::new("created_at", "created_at", FieldKind::Timestamp),
+        Field::new("created_by", "created_by", FieldKind::Text).filter_only(),
+    ],
+    id: "version",
+    default_sort: "-version",
+};
+
+const JOB_COLUMNS: &str = "id, name, status, progress, created_at, started_at, finished_at, \
+     estimated_completion, container_id, output_files, error_message";
+const CONTAINER_COLUMNS: &str =
+    "id, name, image, status, created_at, started_at, finished_at, ports, environment, labels";
+const API_KEY_COLUMNS: &str =
+    "id, name, key_hash, user_id, permissions, created_at, expires_at, last_used, is_active";
+const USER_COLUMNS: &str =
+    "id, username, email, password_hash, permissions, is_active, created_at, updated_at";
+const AUDIT_COLUMNS: &str =
+    "id, occurred_at, actor, action, resource_type, resource_id, request_id, details";
+const PROXY_CONFIG_COLUMNS: &str = "version, config, is_active, created_by, created_at";
+
+/// Turns a unique key violation into [`Error::Conflict`].
+fn conflict(error: sqlx::Error, message: impl FnOnce() -> String) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => Error::conflict(message()),
+        _ => error.into(),
+    }
+}
+
+fn expect_row(rows_affected: u64, resource: &'static str, id: impl ToString) -> Result<()> {
+    if rows_affected == 0 {
+        return Err(Error::not_found(resource, id.to_string()));
+    }
+    Ok(())
+}
+
+fn is_terminal(status: &JobStatus) -> bool {
+    !matches!(status, JobStatus::Queued | JobStatus::Running)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    fn job(name: &str) -> JobCreateRequest {
+        JobCreateRequest {
+            name: name.to_string(),
+            image: "alpine".to_string(),
+            command: None,
+            environment: None,
+            input_files: None,
+            output_patterns: None,
+            resources: None,
+            priority: None,
+            retry_policy: None,
+            timeout: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_repositories() {
+        let config = DatabaseConfig {
+            url: "sqlite://:memory:".to_string(),
+            ..DatabaseConfig::default()
+        };
+        let database = Database::connect(&config).await.unwrap();
+        database.migrate().await.unwrap();
+        let repos = database.repositories();
+
+        for i in 0..5 {
+            repos
+                .jobs
+                .create(&format!("job_{}", i), &job(&format!("build {}", i)), None)
+                .await
+                .unwrap();
+        }
+        let duplicate = repos
+            .jobs
+            .create("job_0", &job("again"), None)
+            .await
+            .unwrap_err();
+        assert_eq!(duplicate.code(), "CONFLICT");
+
+        let running = repos
+            .jobs
+            .update("job_1", &JobUpdate::status(JobStatus::Running))
+            .await
+            .unwrap();
+        assert!(running.started_at.is_some() && running.finished_at.is_none());
+        assert_eq!(
+            repos
+                .jobs
+                .start_attempt("job_1", None)
+                .await
+                .unwrap()
+                .attempt,
+            1
+        );
+        assert_eq!(
+            repos
+                .jobs
+                .start_attempt("job_1", None)
+                .await
+                .unwrap()
+                .attempt,
+            2
+        );
+
+        let mut seen = Vec::new();
+        let mut query = ListQuery::parse("limit=2&sort=-created_at").unwrap();
+        loop {
+            let page = repos.jobs.list(&query).await.unwrap();
+            assert_eq!(page.total, 5);
+            seen.extend(page.items.into_iter().map(|j| j.job_id));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, ["job_4", "job_3", "job_2", "job_1", "job_0"]);
+
+        let filtered = repos
+            .jobs
+            .list(&ListQuery::parse("filter=name:contains:BUILD%203").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(filtered.items.len(), 1);
+    }
+}
@@ -0,0 +1,697 @@
+use super::{
+    conflict, expect_row, is_terminal, ApiKeyRepository, AuditRepository, ContainerRepository,
+    JobRepository, JobUpdate, NewAuditEntry, ProxyConfigRepository, ProxyConfigVersion,
+    UserRepository, API_KEYS, API_KEY_COLUMNS, AUDIT, AUDIT_COLUMNS, CONTAINERS, CONTAINER_COLUMNS,
+    JOBS, JOB_COLUMNS, PROXY_CONFIGS, PROXY_CONFIG_COLUMNS, USERS, USER_COLUMNS,
+};
+use crate::{
+    config::ProxyConfig,
+    pagination::{ListQuery, ListSpec},
+    request_id::RequestId,
+    security::{ApiKey, User},
+    types::{
+        AuditEntry, ContainerInfo, ContainerStatus, JobAttempt, JobCreateRequest, JobInfo,
+        JobStatus, PaginatedResponse,
+    },
+    Error, Result,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{sqlite::SqliteRow, types::Json, FromRow, QueryBuilder, Sqlite, SqlitePool};
+use std::collections::HashMap;
+use uuid::{fmt::Hyphenated, Uuid};
+
+/// SQLite implementation of every repository trait.
+///
+/// Timestamps are always bound from Rust rather than left to column
+/// defaults, so every stored value has the same text format and compares
+/// correctly in keyset pagination.
+pub struct SqliteRepositories {
+    pool: SqlitePool,
+}
+
+impl SqliteRepositories {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Runs a paginated `SELECT columns FROM table` for `spec`.
+    async fn fetch_page<R, T>(
+        &self,
+        columns: &str,
+        table: &str,
+        spec: &'static ListSpec,
+        query: &ListQuery,
+        convert: fn(R) -> Result<T>,
+    ) -> Result<PaginatedResponse<T>>
+    where
+        R: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+        T: Serialize,
+    {
+        let listing = spec.resolve(query)?;
+
+        let mut count = QueryBuilder::<Sqlite>::new(format!("SELECT COUNT(*) FROM {}", table));
+        listing.push_filters(&mut count);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut select = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM {}", columns, table));
+        listing.push_sql(&mut select);
+        let rows = select
+            .build_query_as::<R>()
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(convert)
+            .collect::<Result<Vec<_>>>()?;
+
+        listing.paginate(rows, total as u64)
+    }
+}
+
+#[derive(FromRow)]
+struct JobRow {
+    id: String,
+    name: String,
+    status: String,
+    progress: Option<f64>,
+    created_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    estimated_completion: Option<DateTime<Utc>>,
+    container_id: Option<String>,
+    output_files: Json<Vec<String>>,
+    error_message: Option<String>,
+}
+
+impl TryFrom<JobRow> for JobInfo {
+    type Error = Error;
+
+    fn try_from(row: JobRow) -> Result<Self> {
+        Ok(JobInfo {
+            job_id: row.id,
+            name: row.name,
+            status: row.status.parse()?,
+            progress: row.progress,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+            estimated_completion: row.estimated_completion,
+            container_id: row.container_id,
+            resource_usage: None,
+            output_files: row.output_files.0,
+            error_message: row.error_message,
+        })
+    }
+}
+
+#[derive(FromRow)]
+struct JobAttemptRow {
+    job_id: String,
+    attempt: i32,
+    status: String,
+    container_id: Option<String>,
+    exit_code: Option<i32>,
+    error_message: Option<String>,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<JobAttemptRow> for JobAttempt {
+    type Error = Error;
+
+    fn try_from(row: JobAttemptRow) -> Result<Self> {
+        Ok(JobAttempt {
+            job_id: row.job_id,
+            attempt: row.attempt,
+            status: row.status.parse()?,
+            container_id: row.container_id,
+            exit_code: row.exit_code,
+            error_message: row.error_message,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        })
+    }
+}
+
+#[async_trait]
+impl JobRepository for SqliteRepositories {
+    async fn create(
+        &self,
+        id: &str,
+        request: &JobCreateRequest,
+        created_by: Option<&str>,
+    ) -> Result<JobInfo> {
+        let row: JobRow = sqlx::query_as(&format!(
+            "INSERT INTO jobs (id, name, image, priority, spec, created_by, request_id, created_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) RETURNING {}",
+            JOB_COLUMNS
+        ))
+        .bind(id)
+        .bind(&request.name)
+        .bind(&request.image)
+        .bind(request.priority.as_deref().unwrap_or("normal"))
+        .bind(Json(request))
+        .bind(created_by)
+        .bind(RequestId::current().as_str())
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| conflict(e, || format!("job '{}' already exists", id)))?;
+        row.try_into()
+    }
+
+    async fn get(&self, id: &str) -> Result<JobInfo> {
+        let row: Option<JobRow> =
+            sqlx::query_as(&format!("SELECT {} FROM jobs WHERE id = ?1", JOB_COLUMNS))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        row.ok_or_else(|| Error::not_found("job", id))?.try_into()
+    }
+
+    async fn spec(&self, id: &str) -> Result<JobCreateRequest> {
+        let spec: Option<Json<JobCreateRequest>> =
+            sqlx::query_scalar("SELECT spec FROM jobs WHERE id = ?1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(spec.ok_or_else(|| Error::not_found("job", id))?.0)
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<JobInfo>> {
+        self.fetch_page::<JobRow, _>(JOB_COLUMNS, "jobs", &JOBS, query, JobInfo::try_from)
+            .await
+    }
+
+    async fn update(&self, id: &str, update: &JobUpdate) -> Result<JobInfo> {
+        let row: Option<JobRow> = sqlx::query_as(&format!(
+            "UPDATE jobs SET status = ?2, \
+                 progress = COALESCE(?3, progress), \
+                 container_id = COALESCE(?4, container_id), \
+                 error_message = COALESCE(?5, error_message), \
+                 output_files = COALESCE(?6, output_files), \
+                 started_at = CASE WHEN ?2 = 'running' THEN COALESCE(started_at, ?8) ELSE started_at END, \
+                 finished_at = CASE WHEN ?7 THEN ?8 ELSE finished_at END \
+             WHERE id = ?1 RETURNING {}",
+            JOB_COLUMNS
+        ))
+        .bind(id)
+        .bind(update.status.to_string())
+        .bind(update.progress)
+        .bind(&update.container_id)
+        .bind(&update.error_message)
+        .bind(update.output_files.as_ref().map(Json))
+        .bind(is_terminal(&update.status))
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?;
+        row.ok_or_else(|| Error::not_found("job", id))?.try_into()
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let result = sqlx::query("DELETE FROM jobs WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        expect_row(result.rows_affected(), "job", id)
+    }
+
+    async fn start_attempt(&self, job_id: &str, container_id: Option<&str>) -> Result<JobAttempt> {
+        let row: JobAttemptRow = sqlx::query_as(
+            "INSERT INTO job_attempts (job_id, attempt, status, container_id, started_at) \
+             SELECT ?1, COALESCE(MAX(attempt), 0) + 1, 'running', ?2, ?3 FROM job_attempts WHERE job_id = ?1 \
+             RETURNING *",
+        )
+        .bind(job_id)
+        .bind(container_id)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.is_foreign_key_violation() => Error::not_found("job", job_id),
+            _ => conflict(e, || format!("job '{}' started another attempt concurrently", job_id)),
+        })?;
+        row.try_into()
+    }
+
+    async fn finish_attempt(
+        &self,
+        job_id: &str,
+        attempt: i32,
+        status: JobStatus,
+        exit_code: Option<i32>,
+        error_message: Option<&str>,
+    ) -> Result<JobAttempt> {
+        let row: Option<JobAttemptRow> = sqlx::query_as(
+            "UPDATE job_attempts SET status = ?3, exit_code = ?4, error_message = ?5, finished_at = ?6 \
+             WHERE job_id = ?1 AND attempt = ?2 RETURNING *",
+        )
+        .bind(job_id)
+        .bind(attempt)
+        .bind(status.to_string())
+        .bind(exit_code)
+        .bind(error_message)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?;
+        row.ok_or_else(|| Error::not_found("job attempt", format!("{}#{}", job_id, attempt)))?
+            .try_into()
+    }
+
+    async fn attempts(&self, job_id: &str) -> Result<Vec<JobAttempt>> {
+        let rows: Vec<JobAttemptRow> =
+            sqlx::query_as("SELECT * FROM job_attempts WHERE job_id = ?1 ORDER BY attempt")
+                .bind(job_id)
+                .fetch_all(&self.pool)
+                .await?;
+        rows.into_iter().map(JobAttempt::try_from).collect()
+    }
+}
+
+#[derive(FromRow)]
+struct ContainerRow {
+    id: String,
+    name: String,
+    image: String,
+    status: String,
+    created_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    ports: Json<Vec<String>>,
+    environment: Json<HashMap<String, String>>,
+    labels: Json<HashMap<String, String>>,
+}
+
+impl TryFrom<ContainerRow> for ContainerInfo {
+    type Error = Error;
+
+    fn try_from(row: ContainerRow) -> Result<Self> {
+        Ok(ContainerInfo {
+            id: row.id,
+            name: row.name,
+            image: row.image,
+            status: row.status.parse()?,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+            ports: row.ports.0,
+            environment: row.environment.0,
+            labels: row.labels.0,
+            resource_usage: None,
+        })
+    }
+}
+
+#[async_trait]
+impl ContainerRepository for SqliteRepositories {
+    async fn upsert(&self, container: &ContainerInfo) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO containers (id, name, image, status, created_at, started_at, finished_at, ports, environment, labels, updated_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11) \
+             ON CONFLICT (id) DO UPDATE SET name = ?2, image = ?3, status = ?4, started_at = ?6, \
+                 finished_at = ?7, ports = ?8, environment = ?9, labels = ?10, updated_at = ?11",
+        )
+        .bind(&container.id)
+        .bind(&container.name)
+        .bind(&container.image)
+        .bind(container.status.to_string())
+        .bind(container.created_at)
+        .bind(container.started_at)
+        .bind(container.finished_at)
+        .bind(Json(&container.ports))
+        .bind(Json(&container.environment))
+        .bind(Json(&container.labels))
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| conflict(e, || format!("container name '{}' is already in use", container.name)))?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<ContainerInfo> {
+        let row: Option<ContainerRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM containers WHERE id = ?1",
+            CONTAINER_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.ok_or_else(|| Error::not_found("container", id))?
+            .try_into()
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<ContainerInfo>> {
+        self.fetch_page::<ContainerRow, _>(
+            CONTAINER_COLUMNS,
+            "containers",
+            &CONTAINERS,
+            query,
+            ContainerInfo::try_from,
+        )
+        .await
+    }
+
+    async fn set_status(&self, id: &str, status: ContainerStatus) -> Result<()> {
+        let result =
+            sqlx::query("UPDATE containers SET status = ?2, updated_at = ?3 WHERE id = ?1")
+                .bind(id)
+                .bind(status.to_string())
+                .bind(Utc::now())
+                .execute(&self.pool)
+                .await?;
+        expect_row(result.rows_affected(), "container", id)
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let result = sqlx::query("DELETE FROM containers WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        expect_row(result.rows_affected(), "container", id)
+    }
+}
+
+#[derive(FromRow)]
+struct ApiKeyRow {
+    id: String,
+    name: String,
+    key_hash: String,
+    user_id: Option<Hyphenated>,
+    permissions: Json<Vec<String>>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used: Option<DateTime<Utc>>,
+    is_active: bool,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        ApiKey {
+            id: row.id,
+            name: row.name,
+            key_hash: row.key_hash,
+            user_id: row.user_id.map(Hyphenated::into_uuid),
+            permissions: row.permissions.0,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used: row.last_used,
+            is_active: row.is_active,
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for SqliteRepositories {
+    async fn create(&self, key: &ApiKey) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO api_keys ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            API_KEY_COLUMNS
+        ))
+        .bind(&key.id)
+        .bind(&key.name)
+        .bind(&key.key_hash)
+        .bind(key.user_id.map(Uuid::hyphenated))
+        .bind(Json(&key.permissions))
+        .bind(key.created_at)
+        .bind(key.expires_at)
+        .bind(key.last_used)
+        .bind(key.is_active)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| conflict(e, || format!("API key '{}' already exists", key.id)))?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<ApiKey> {
+        let row: Option<ApiKeyRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM api_keys WHERE id = ?1",
+            API_KEY_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.ok_or_else(|| Error::not_found("API key", id))?.into())
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        let row: Option<ApiKeyRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM api_keys WHERE key_hash = ?1 AND is_active \
+             AND (expires_at IS NULL OR expires_at > ?2)",
+            API_KEY_COLUMNS
+        ))
+        .bind(key_hash)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ApiKey::from))
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<ApiKey>> {
+        self.fetch_page::<ApiKeyRow, _>(API_KEY_COLUMNS, "api_keys", &API_KEYS, query, |row| {
+            Ok(row.into())
+        })
+        .await
+    }
+
+    async fn touch(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE api_keys SET last_used = ?2 WHERE id = ?1")
+            .bind(id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke(&self, id: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE api_keys SET is_active = FALSE WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        expect_row(result.rows_affected(), "API key", id)
+    }
+}
+
+#[derive(FromRow)]
+struct UserRow {
+    id: Hyphenated,
+    username: String,
+    email: Option<String>,
+    password_hash: String,
+    permissions: Json<Vec<String>>,
+    is_active: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> Self {
+        User {
+            id: row.id.into_uuid(),
+            username: row.username,
+            email: row.email,
+            password_hash: row.password_hash,
+            permissions: row.permissions.0,
+            is_active: row.is_active,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteRepositories {
+    async fn create(&self, user: &User) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO users ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            USER_COLUMNS
+        ))
+        .bind(user.id.hyphenated())
+        .bind(&user.username)
+        .bind(&user.email)
+        .bind(&user.password_hash)
+        .bind(Json(&user.permissions))
+        .bind(user.is_active)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| conflict(e, || format!("user '{}' already exists", user.username)))?;
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<User> {
+        let row: Option<UserRow> =
+            sqlx::query_as(&format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS))
+                .bind(id.hyphenated())
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row
+            .ok_or_else(|| Error::not_found("user", id.to_string()))?
+            .into())
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>> {
+        let row: Option<UserRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM users WHERE username = ?1",
+            USER_COLUMNS
+        ))
+        .bind(username)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(User::from))
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<User>> {
+        self.fetch_page::<UserRow, _>(USER_COLUMNS, "users", &USERS, query, |row| Ok(row.into()))
+            .await
+    }
+
+    async fn set_password(&self, id: Uuid, password_hash: &str) -> Result<()> {
+        let result =
+            sqlx::query("UPDATE users SET password_hash = ?2, updated_at = ?3 WHERE id = ?1")
+                .bind(id.hyphenated())
+                .bind(password_hash)
+                .bind(Utc::now())
+                .execute(&self.pool)
+                .await?;
+        expect_row(result.rows_affected(), "user", id)
+    }
+
+    async fn set_active(&self, id: Uuid, active: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE users SET is_active = ?2, updated_at = ?3 WHERE id = ?1")
+            .bind(id.hyphenated())
+            .bind(active)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        expect_row(result.rows_affected(), "user", id)
+    }
+}
+
+#[async_trait]
+impl AuditRepository for SqliteRepositories {
+    async fn record(&self, entry: &NewAuditEntry) -> Result<AuditEntry> {
+        Ok(sqlx::query_as(&format!(
+            "INSERT INTO audit_log (actor, action, resource_type, resource_id, request_id, details, occurred_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) RETURNING {}",
+            AUDIT_COLUMNS
+        ))
+        .bind(&entry.actor)
+        .bind(&entry.action)
+        .bind(&entry.resource_type)
+        .bind(&entry.resource_id)
+        .bind(RequestId::current().as_str())
+        .bind(Json(&entry.details))
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<AuditEntry>> {
+        self.fetch_page(AUDIT_COLUMNS, "audit_log", &AUDIT, query, Ok)
+            .await
+    }
+}
+
+#[derive(FromRow)]
+struct ProxyConfigRow {
+    version: i64,
+    config: Json<ProxyConfig>,
+    is_active: bool,
+    created_by: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<ProxyConfigRow> for ProxyConfigVersion {
+    type Error = Error;
+
+    fn try_from(row: ProxyConfigRow) -> Result<Self> {
+        Ok(ProxyConfigVersion {
+            version: row.version,
+            config: row.config.0,
+            is_active: row.is_active,
+            created_by: row.created_by,
+            created_at: row.created_at,
+        })
+    }
+}
+
+#[async_trait]
+impl ProxyConfigRepository for SqliteRepositories {
+    async fn save(
+        &self,
+        config: &ProxyConfig,
+        created_by: Option<&str>,
+        activate: bool,
+    ) -> Result<ProxyConfigVersion> {
+        let mut tx = self.pool.begin().await?;
+        if activate {
+            sqlx::query("UPDATE proxy_configs SET is_active = FALSE WHERE is_active")
+                .execute(&mut *tx)
+                .await?;
+        }
+        let row: ProxyConfigRow = sqlx::query_as(&format!(
+            "INSERT INTO proxy_configs (config, is_active, created_by, created_at) VALUES (?1, ?2, ?3, ?4) RETURNING {}",
+            PROXY_CONFIG_COLUMNS
+        ))
+        .bind(Json(config))
+        .bind(activate)
+        .bind(created_by)
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        row.try_into()
+    }
+
+    async fn get(&self, version: i64) -> Result<ProxyConfigVersion> {
+        let row: Option<ProxyConfigRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM proxy_configs WHERE version = ?1",
+            PROXY_CONFIG_COLUMNS
+        ))
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.ok_or_else(|| Error::not_found("proxy config", version.to_string()))?
+            .try_into()
+    }
+
+    async fn active(&self) -> Result<Option<ProxyConfigVersion>> {
+        let row: Option<ProxyConfigRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM proxy_configs WHERE is_active",
+            PROXY_CONFIG_COLUMNS
+        ))
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(ProxyConfigVersion::try_from).transpose()
+    }
+
+    async fn activate(&self, version: i64) -> Result<ProxyConfigVersion> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE proxy_configs SET is_active = FALSE WHERE is_active")
+            .execute(&mut *tx)
+            .await?;
+        let row: Option<ProxyConfigRow> = sqlx::query_as(&format!(
+            "UPDATE proxy_configs SET is_active = TRUE WHERE version = ?1 RETURNING {}",
+            PROXY_CONFIG_COLUMNS
+        ))
+        .bind(version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let row = row.ok_or_else(|| Error::not_found("proxy config", version.to_string()))?;
+        tx.commit().await?;
+        row.try_into()
+    }
+
+    async fn list(&self, query: &ListQuery) -> Result<PaginatedResponse<ProxyConfigVersion>> {
+        self.fetch_page::<ProxyConfigRow, _>(
+            PROXY_CONFIG_COLUMNS,
+            "proxy_configs",
+            &PROXY_CONFIGS,
+            query,
+            ProxyConfigVersion::try_from,
+        )
+        .await
+    }
+}
@@ -1,13 +1,17 @@
+use crate::request_id::RequestId;
 use crate::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ApiKey {
     pub id: String,
     pub name: String,
+    #[serde(skip_serializing, default)]
     pub key_hash: String,
+    /// Owner; `None` for service keys.
+    pub user_id: Option<Uuid>,
     pub permissions: Vec<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
@@ -15,6 +19,19 @@ pub struct ApiKey {
     pub is_active: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    #[serde(skip_serializing, default)]
+    pub password_hash: String,
+    pub permissions: Vec<String>,
+    pub is_active: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub sub: String,
@@ -40,7 +57,7 @@ impl SecurityContext {
             session_id: None,
             permissions: vec![],
             api_key_id: None,
-            request_id: Uuid::new_v4().to_string(),
+            request_id: RequestId::current().into(),
         }
     }
 
@@ -50,7 +67,7 @@ impl SecurityContext {
             session_id: None,
             permissions: api_key.permissions.clone(),
             api_key_id: Some(api_key.id.clone()),
-            request_id: Uuid::new_v4().to_string(),
+            request_id: RequestId::current().into(),
         }
     }
 
@@ -60,13 +77,13 @@ impl SecurityContext {
             session_id: Some(claims.session_id.clone()),
             permissions: claims.permissions.clone(),
             api_key_id: None,
-            request_id: Uuid::new_v4().to_string(),
+            request_id: RequestId::current().into(),
         }
     }
 
     pub fn has_permission(&self, permission: &str) -> bool {
-        self.permissions.contains(&permission.to_string()) || 
-        self.permissions.contains(&"admin".to_string())
+        self.permissions.contains(&permission.to_string())
+            || self.permissions.contains(&"admin".to_string())
     }
 
     pub fn require_permission(&self, permission: &str) -> Result<()> {
@@ -108,7 +125,9 @@ pub fn validate_password(password: &str) -> Result<()> {
     let has_uppercase = password.chars().any(|c| c.is_uppercase());
     let has_lowercase = password.chars().any(|c| c.is_lowercase());
     let has_digit = password.chars().any(|c| c.is_ascii_digit());
-    let has_special = password.chars().any(|c| "!@#$%^&*()_+-=[]{}|;:,.<>?".contains(c));
+    let has_special = password
+        .chars()
+        .any(|c| "!@#$%^&*()_+-=[]{}|;:,.<>?".contains(c));
 
     if !has_uppercase {
         return Err(crate::Error::validation(
@@ -194,10 +213,7 @@ pub fn validate_environment_variables(env: &HashMap<String, String>) -> Result<(
             ));
         }
 
-        if !key
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '_')
-        {
+        if !key.chars().all(|c| c.is_alphanumeric() || c == '_') {
             return Err(crate::Error::validation(
                 "Environment variable names can only contain alphanumeric characters and underscores",
             ));
@@ -211,9 +227,14 @@ pub fn validate_environment_variables(env: &HashMap<String, String>) -> Result<(
 
         // Check for potentially sensitive values
         let sensitive_patterns = [
-            "password", "secret", "key", "token", "credential", "private",
+            "password",
+            "secret",
+            "key",
+            "token",
+            "credential",
+            "private",
         ];
-        
+
         if sensitive_patterns.iter().any(|pattern| {
             key.to_lowercase().contains(pattern) || value.to_lowercase().contains(pattern)
         }) {
@@ -257,4 +278,4 @@ mod tests {
         assert_eq!(sanitize_filename("file@#$%.pdf"), "file.pdf");
         assert_eq!(sanitize_filename("my_file-2.txt"), "my_file-2.txt");
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::request_id::RequestId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "ApiResponse_for_{T}")]
 pub struct ApiResponse<T> {
     pub data: Option<T>,
     pub error: Option<ApiError>,
@@ -11,23 +14,27 @@ pub struct ApiResponse<T> {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ApiError {
     pub code: String,
     pub message: String,
     pub details: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "PaginatedResponse_for_{T}")]
 pub struct PaginatedResponse<T> {
     pub items: Vec<T>,
     pub total: u64,
     pub limit: u32,
     pub offset: u32,
     pub has_next: bool,
+    /// Opaque cursor for the next page; pass it back as `?cursor=`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HealthStatus {
     pub status: String,
     pub timestamp: DateTime<Utc>,
@@ -35,16 +42,23 @@ pub struct HealthStatus {
     pub uptime: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SystemInfo {
     pub version: String,
     pub uptime: u64,
     pub memory_usage: u64,
     pub cpu_usage: f64,
     pub active_connections: u32,
+    /// Database pool usage; absent when the database is disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<crate::database::DatabaseStats>,
+    /// Shared Redis client counters; absent when Redis is disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redis: Option<crate::redis::RedisStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum ContainerStatus {
     Created,
     Running,
@@ -55,7 +69,7 @@ pub enum ContainerStatus {
     Dead,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ContainerInfo {
     pub id: String,
     pub name: String,
@@ -70,7 +84,7 @@ pub struct ContainerInfo {
     pub resource_usage: Option<ResourceUsage>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResourceUsage {
     pub cpu_usage: f64,
     pub memory_usage: u64,
@@ -82,7 +96,7 @@ pub struct ResourceUsage {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ContainerCreateRequest {
     pub name: String,
     pub image: String,
@@ -96,14 +110,31 @@ pub struct ContainerCreateRequest {
     pub labels: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A command to run inside a running container.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerExecRequest {
+    pub command: Vec<String>,
+    pub environment: Option<HashMap<String, String>>,
+    pub working_dir: Option<String>,
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerExecResult {
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResourceLimits {
     pub cpu_limit: Option<String>,
     pub memory_limit: Option<String>,
     pub disk_limit: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum JobStatus {
     Queued,
     Running,
@@ -113,7 +144,7 @@ pub enum JobStatus {
     Timeout,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JobInfo {
     pub job_id: String,
     pub name: String,
@@ -129,7 +160,7 @@ pub struct JobInfo {
     pub error_message: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JobCreateRequest {
     pub name: String,
     pub image: String,
@@ -143,21 +174,49 @@ pub struct JobCreateRequest {
     pub timeout: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One run of a job; retries add attempts.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JobAttempt {
+    pub job_id: String,
+    pub attempt: i32,
+    pub status: JobStatus,
+    pub container_id: Option<String>,
+    pub exit_code: Option<i32>,
+    pub error_message: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A recorded change made through the API.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    /// User or API key id; `None` for the server itself.
+    pub actor: Option<String>,
+    /// What happened, e.g. `job.cancel`.
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub request_id: Option<String>,
+    pub details: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FileMapping {
     pub source: String,
     pub destination: String,
     pub permissions: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RetryPolicy {
     pub max_attempts: u32,
     pub backoff: String,
     pub delay: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FileInfo {
     pub path: String,
     pub size: u64,
@@ -168,7 +227,7 @@ pub struct FileInfo {
     pub permissions: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct UpstreamServer {
     pub address: String,
     pub weight: u32,
@@ -179,7 +238,7 @@ pub struct UpstreamServer {
     pub error_rate: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProxyStats {
     pub requests_total: u64,
     pub requests_per_second: f64,
@@ -188,7 +247,7 @@ pub struct ProxyStats {
     pub upstreams: HashMap<String, UpstreamStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResponseTimeStats {
     pub avg: f64,
     pub p50: f64,
@@ -196,20 +255,39 @@ pub struct ResponseTimeStats {
     pub p99: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct UpstreamStats {
     pub total_requests: u64,
     pub active_connections: u32,
     pub servers: Vec<UpstreamServer>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum WebSocketMessage {
-    JobStatus { job_id: String, status: JobStatus, progress: Option<f64> },
-    ContainerEvent { container_id: String, event: String, timestamp: DateTime<Utc> },
-    SystemMetric { cpu_usage: f64, memory_usage: u64, timestamp: DateTime<Utc> },
-    ProxyStats { stats: ProxyStats },
-    LogEntry { level: String, message: String, timestamp: DateTime<Utc> },
+    JobStatus {
+        job_id: String,
+        status: JobStatus,
+        progress: Option<f64>,
+    },
+    ContainerEvent {
+        container_id: String,
+        event: String,
+        timestamp: DateTime<Utc>,
+    },
+    SystemMetric {
+        cpu_usage: f64,
+        memory_usage: u64,
+        timestamp: DateTime<Utc>,
+    },
+    ProxyStats {
+        stats: ProxyStats,
+    },
+    LogEntry {
+        level: String,
+        message: String,
+        timestamp: DateTime<Utc>,
+    },
 }
 
 impl<T> ApiResponse<T> {
@@ -217,7 +295,7 @@ impl<T> ApiResponse<T> {
         Self {
             data: Some(data),
             error: None,
-            request_id: Uuid::new_v4().to_string(),
+            request_id: RequestId::current().into(),
             timestamp: Utc::now(),
         }
     }
@@ -230,7 +308,7 @@ impl<T> ApiResponse<T> {
                 message,
                 details: None,
             }),
-            request_id: Uuid::new_v4().to_string(),
+            request_id: RequestId::current().into(),
             timestamp: Utc::now(),
         }
     }
@@ -243,7 +321,7 @@ impl<T> ApiResponse<T> {
                 message,
                 details: Some(details),
             }),
-            request_id: Uuid::new_v4().to_string(),
+            request_id: RequestId::current().into(),
             timestamp: Utc::now(),
         }
     }
@@ -251,13 +329,26 @@ impl<T> ApiResponse<T> {
 
 impl<T> PaginatedResponse<T> {
     pub fn new(items: Vec<T>, total: u64, limit: u32, offset: u32) -> Self {
-        let has_next = (offset + limit as u32) < total as u32;
+        let has_next = u64::from(offset) + u64::from(limit) < total;
         Self {
             items,
             total,
             limit,
             offset,
             has_next,
+            next_cursor: None,
+        }
+    }
+
+    /// A cursor-paginated page; `has_next` follows from `next_cursor`.
+    pub fn with_cursor(items: Vec<T>, total: u64, limit: u32, next_cursor: Option<String>) -> Self {
+        Self {
+            items,
+            total,
+            limit,
+            offset: 0,
+            has_next: next_cursor.is_some(),
+            next_cursor,
         }
     }
 }
@@ -287,4 +378,47 @@ impl std::fmt::Display for JobStatus {
             JobStatus::Timeout => write!(f, "timeout"),
         }
     }
-}
\ No newline at end of file
+}
+
+impl std::str::FromStr for ContainerStatus {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "created" => ContainerStatus::Created,
+            "running" => ContainerStatus::Running,
+            "paused" => ContainerStatus::Paused,
+            "restarting" => ContainerStatus::Restarting,
+            "removing" => ContainerStatus::Removing,
+            "exited" => ContainerStatus::Exited,
+            "dead" => ContainerStatus::Dead,
+            _ => {
+                return Err(crate::Error::validation(format!(
+                    "unknown container status '{}'",
+                    s
+                )))
+            }
+        })
+    }
+}
+
+impl std::str::FromStr for JobStatus {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "queued" => JobStatus::Queued,
+            "running" => JobStatus::Running,
+            "completed" => JobStatus::Completed,
+            "failed" => JobStatus::Failed,
+            "cancelled" => JobStatus::Cancelled,
+            "timeout" => JobStatus::Timeout,
+            _ => {
+                return Err(crate::Error::validation(format!(
+                    "unknown job status '{}'",
+                    s
+                )))
+            }
+        })
+    }
+}